@@ -0,0 +1,17 @@
+//! Production launchers only accept signed release manifests, so a release
+//! build without the manifest public key must not be produced at all.
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=LAUNCHER_ENVIRONMENT");
+    println!("cargo:rerun-if-env-changed=EXPLORER_MANIFEST_PUBLIC_KEY");
+
+    let production = std::env::var("LAUNCHER_ENVIRONMENT").is_ok_and(|env| env == "prod");
+    let has_key =
+        std::env::var("EXPLORER_MANIFEST_PUBLIC_KEY").is_ok_and(|key| !key.trim().is_empty());
+
+    if production && !has_key {
+        println!(
+            "cargo::error=EXPLORER_MANIFEST_PUBLIC_KEY must be set for LAUNCHER_ENVIRONMENT=prod builds"
+        );
+    }
+}
@@ -16,6 +16,7 @@ use session::SessionId;
 use crate::{
     config,
     environment::AppEnvironment,
+    rollout::RolloutAssignment,
     utils::{app_version, get_os_name},
 };
 
@@ -90,6 +91,20 @@ impl Analytics {
         }
     }
 
+    /// Release channel the builds come from, sent with every following event.
+    pub fn set_release_channel(&mut self, channel: &str) {
+        if let Self::Client(client) = self {
+            client.set_release_channel(channel.to_owned());
+        }
+    }
+
+    /// Staged rollout bucket of the fetched release, sent with every following event.
+    pub const fn set_rollout(&mut self, rollout: RolloutAssignment) {
+        if let Self::Client(client) = self {
+            client.set_rollout(rollout);
+        }
+    }
+
     async fn track_and_flush(&mut self, event: Event) -> Result<()> {
         match self {
             Self::Client(client) => {
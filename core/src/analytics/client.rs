@@ -17,6 +17,8 @@ use tokio::sync::Mutex;
 
 use crate::analytics::network_info::network_context;
 use crate::environment::AppEnvironment;
+use crate::mirrors;
+use crate::rollout::RolloutAssignment;
 
 use super::event::Event;
 use super::fingerprint::ClientFingerprint;
@@ -29,6 +31,8 @@ pub struct AnalyticsClient {
     os: String,
     launcher_version: String,
     campaign_anon_user_id: Option<String>,
+    release_channel: Option<String>,
+    rollout: Option<RolloutAssignment>,
     session_id: SessionId,
     fingerprint_props: Map<String, Value>,
     batcher: QueuedBatcher,
@@ -59,6 +63,8 @@ impl AnalyticsClient {
             os,
             launcher_version,
             campaign_anon_user_id: None,
+            release_channel: None,
+            rollout: None,
             session_id,
             fingerprint_props: ClientFingerprint::current().into(),
             batcher,
@@ -71,6 +77,14 @@ impl AnalyticsClient {
         self
     }
 
+    pub fn set_release_channel(&mut self, channel: String) {
+        self.release_channel = Some(channel);
+    }
+
+    pub const fn set_rollout(&mut self, rollout: RolloutAssignment) {
+        self.rollout = Some(rollout);
+    }
+
     async fn track(&mut self, event: String, mut properties: Map<String, Value>) -> Result<()> {
         properties.insert("os".to_owned(), Value::String(self.os.clone()));
         properties.insert(
@@ -90,6 +104,22 @@ impl AnalyticsClient {
             );
         }
 
+        if let Some(channel) = &self.release_channel {
+            properties.insert("releaseChannel".to_owned(), Value::String(channel.clone()));
+        }
+
+        if let Some(mirror) = mirrors::active() {
+            properties.insert("mirror".to_owned(), Value::String(mirror));
+        }
+
+        if let Some(rollout) = &self.rollout {
+            properties.insert("rolloutBucket".to_owned(), Value::from(rollout.bucket));
+            properties.insert(
+                "rolloutCohort".to_owned(),
+                Value::String(rollout.cohort().to_owned()),
+            );
+        }
+
         merge_static_defaults(&mut properties, &self.fingerprint_props);
 
         let user = User::AnonymousId {
@@ -23,6 +23,8 @@ pub enum Event {
     },
     DOWNLOAD_VERSION {
         version: String,
+        /// Bytes per second, `None` when downloads aren't limited.
+        download_rate_limit: Option<u64>,
     },
     DOWNLOAD_VERSION_PROGRESS {
         downloaded_file_url: String,
@@ -31,6 +33,7 @@ pub enum Event {
     },
     DOWNLOAD_VERSION_SUCCESS {
         version: String,
+        download_rate_limit: Option<u64>,
     },
     DOWNLOAD_VERSION_ERROR {
         version: Option<String>,
@@ -39,12 +42,42 @@ pub enum Event {
     DOWNLOAD_VERSION_CANCELLED {
         version: String,
     },
+    DOWNLOAD_VERSION_CHECKSUM_MISMATCH {
+        version: String,
+        expected: String,
+        actual: String,
+    },
     DOWNLOAD_VERSION_SKIPPED {
         version: String,
     },
+    DOWNLOAD_VERSION_NOT_ENOUGH_SPACE {
+        version: String,
+        required: u64,
+        available: u64,
+        reclaimable: u64,
+    },
+    OLD_VERSIONS_PRUNED {
+        freed: u64,
+    },
+    DOWNLOAD_VERSION_PATCH {
+        version: String,
+        from_version: String,
+    },
+    DOWNLOAD_VERSION_FILES {
+        version: String,
+        files_total: u64,
+        files_missing: u64,
+        size_missing: u64,
+    },
     INSTALL_VERSION_START {
         version: String,
     },
+    INSTALL_VERSION_PROGRESS {
+        version: String,
+        size_extracted: u64,
+        size_remaining: u64,
+        entries_extracted: u64,
+    },
     INSTALL_VERSION_SUCCESS {
         version: String,
     },
@@ -55,6 +88,32 @@ pub enum Event {
     INSTALL_VERSION_SKIPPED {
         version: String,
     },
+    INSTALL_VERSION_PATCH_FALLBACK {
+        version: String,
+        from_version: String,
+        error: String,
+    },
+    INSTALL_VERSION_FILES_FALLBACK {
+        version: String,
+        error: String,
+    },
+    INSTALL_VERSION_RECOVERED {
+        version: String,
+        rolled_forward: bool,
+    },
+    INSTALL_VERSION_VERIFIED {
+        version: String,
+        /// The release publishes no file manifest, only the executable was
+        /// checked.
+        verifiable: bool,
+        files_missing: u64,
+        files_modified: u64,
+    },
+    INSTALL_VERSION_REPAIRED {
+        version: String,
+        kind: String,
+        files: u64,
+    },
     LAUNCH_CLIENT_START {
         version: String,
     },
@@ -115,11 +174,26 @@ impl Display for Event {
                 Event::DOWNLOAD_VERSION_SUCCESS { .. } => "Download Version Success",
                 Event::DOWNLOAD_VERSION_ERROR { .. } => "Download Version Error",
                 Event::DOWNLOAD_VERSION_CANCELLED { .. } => "Download Version Cancelled",
+                Event::DOWNLOAD_VERSION_CHECKSUM_MISMATCH { .. } => {
+                    "Download Version Checksum Mismatch"
+                }
                 Event::DOWNLOAD_VERSION_SKIPPED { .. } => "Download Version Skipped",
+                Event::DOWNLOAD_VERSION_NOT_ENOUGH_SPACE { .. } => {
+                    "Download Version Not Enough Space"
+                }
+                Event::OLD_VERSIONS_PRUNED { .. } => "Old Versions Pruned",
+                Event::DOWNLOAD_VERSION_PATCH { .. } => "Download Version Patch",
+                Event::DOWNLOAD_VERSION_FILES { .. } => "Download Version Files",
                 Event::INSTALL_VERSION_START { .. } => "Install Version Start",
+                Event::INSTALL_VERSION_PROGRESS { .. } => "Install Version Progress",
                 Event::INSTALL_VERSION_SUCCESS { .. } => "Install Version Success",
                 Event::INSTALL_VERSION_ERROR { .. } => "Install Version Error",
                 Event::INSTALL_VERSION_SKIPPED { .. } => "Install Version Skipped",
+                Event::INSTALL_VERSION_PATCH_FALLBACK { .. } => "Install Version Patch Fallback",
+                Event::INSTALL_VERSION_FILES_FALLBACK { .. } => "Install Version Files Fallback",
+                Event::INSTALL_VERSION_RECOVERED { .. } => "Install Version Recovered",
+                Event::INSTALL_VERSION_VERIFIED { .. } => "Install Version Verified",
+                Event::INSTALL_VERSION_REPAIRED { .. } => "Install Version Repaired",
                 Event::LAUNCH_CLIENT_START { .. } => "Launch Client Start",
                 Event::LAUNCH_CLIENT_SUCCESS { .. } => "Launch Client Success",
                 Event::LAUNCH_CLIENT_ERROR { .. } => "Launch Client Error",
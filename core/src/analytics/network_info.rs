@@ -204,3 +204,49 @@ fn network_context_internal() -> NetworkInfo {
         info
     }
 }
+
+#[cfg(target_os = "linux")]
+fn network_context_internal() -> NetworkInfo {
+    use get_if_addrs::get_if_addrs;
+    use std::collections::HashSet;
+    use std::path::Path;
+
+    // Same filter as macOS: skip loopback and link-local v4 addresses
+    let active_ifaces: HashSet<String> = match get_if_addrs() {
+        Ok(addrs) => addrs
+            .into_iter()
+            .filter(|iface| {
+                if iface.is_loopback() {
+                    return false;
+                }
+                match iface.ip() {
+                    std::net::IpAddr::V4(ip) => !ip.is_link_local(),
+                    std::net::IpAddr::V6(ip) => !ip.is_loopback(),
+                }
+            })
+            .map(|iface| iface.name)
+            .collect(),
+        Err(_) => return NetworkInfo::default(),
+    };
+
+    let mut info = NetworkInfo::default();
+    for iface in &active_ifaces {
+        let sys_path = Path::new("/sys/class/net").join(iface);
+
+        // Wireless drivers expose the `wireless` (or `phy80211`) entry
+        if sys_path.join("wireless").exists() || sys_path.join("phy80211").exists() {
+            info.wifi = true;
+        }
+        // Modems managed by ModemManager use the `wwan` prefix (`ww` with
+        // predictable interface names)
+        if iface.starts_with("ww") {
+            info.cellular = true;
+        }
+
+        if info.wifi && info.cellular {
+            break;
+        }
+    }
+
+    info
+}
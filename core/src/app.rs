@@ -6,9 +6,11 @@ use crate::download_origin_metadata::campaign_anon_user_id_storage::CampaignAnon
 use crate::download_origin_metadata::campaign_attribution_marker::CampaignAttributionMarker;
 use crate::download_origin_metadata::dcl_env_storage::DclEnvStorage;
 use crate::download_origin_metadata::referrer_storage::ReferrerStorage;
-use crate::flow::{LaunchFlow, LaunchFlowState};
-use crate::installs;
+use crate::flow::{LaunchFlow, LaunchFlowState, LaunchMode};
+use crate::flow_control::FlowControl;
+use crate::installs::{self, InstallsHub};
 use crate::instances::RunningInstances;
+use crate::logs::LogConsole;
 use crate::monitoring::Monitoring;
 use crate::protocols::Protocol;
 use crate::{analytics, logs, utils};
@@ -24,11 +26,25 @@ pub struct AppState {
     pub state: Arc<Mutex<LaunchFlowState>>,
     pub protocol: Protocol,
     pub analytics: Arc<Mutex<Analytics>>,
+    pub installs_hub: Arc<Mutex<InstallsHub>>,
+    pub running_instances: Arc<Mutex<RunningInstances>>,
+    /// Not behind the state mutex, so the UI can cancel or pause a running
+    /// flow.
+    pub flow_control: FlowControl,
 }
 
 impl AppState {
     pub async fn setup() -> Result<Self> {
-        logs::dispath_logs()?;
+        Self::setup_with_logs(LogConsole::Stdout).await
+    }
+
+    /// Same as [`Self::setup`], with the logs echoed to stderr.
+    pub async fn setup_headless() -> Result<Self> {
+        Self::setup_with_logs(LogConsole::Stderr).await
+    }
+
+    async fn setup_with_logs(console: LogConsole) -> Result<Self> {
+        logs::dispath_logs(console)?;
 
         info!(
             "Application setup start. Version: {} commit: {} pr: {}",
@@ -81,20 +97,38 @@ impl AppState {
             }
         }
 
+        if let Some(recovery) = installs::recover_interrupted_install() {
+            analytics
+                .track_and_flush_silent(Event::INSTALL_VERSION_RECOVERED {
+                    version: recovery.version,
+                    rolled_forward: recovery.rolled_forward,
+                })
+                .await;
+        }
+
         let analytics = Arc::new(Mutex::new(analytics));
         let running_instances = Arc::new(Mutex::new(RunningInstances::default()));
-        let installs_hub = Arc::new(Mutex::new(installs::InstallsHub::new(
+        let installs_hub = Arc::new(Mutex::new(InstallsHub::new(
             analytics.clone(),
             running_instances.clone(),
         )));
 
-        let flow = LaunchFlow::new(installs_hub, analytics.clone(), running_instances);
-        let flow_state = LaunchFlowState::default();
+        let flow_control = FlowControl::default();
+        let flow = LaunchFlow::new(
+            installs_hub.clone(),
+            analytics.clone(),
+            running_instances.clone(),
+            flow_control.clone(),
+        );
+        let flow_state = LaunchFlowState::new(LaunchMode::from_cmd_args());
         let app_state = Self {
             flow,
             state: Arc::new(Mutex::new(flow_state)),
             protocol: Protocol {},
             analytics,
+            installs_hub,
+            running_instances,
+            flow_control,
         };
 
         info!("Application setup complete");
@@ -1,3 +1,5 @@
+pub mod ndjson;
+
 use anyhow::Result;
 
 use crate::types::Status;
@@ -0,0 +1,178 @@
+//! Machine-readable event stream for wrapper scripts and test harnesses:
+//! every [`Status`] becomes one JSON object per line, e.g.
+//!
+//! ```text
+//! {"seq":1,"timestamp":"2025-01-01T10:00:00.000Z","event":"state","data":{"step":{...}}}
+//! ```
+//!
+//! Enabled with `--event-stream [target]`, see [`EventStreamTarget::parse`].
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+
+use super::EventChannel;
+use crate::environment::AppEnvironment;
+use crate::types::Status;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventStreamTarget {
+    Stdout,
+    Stderr,
+    /// Appended to, so several runs can share one file.
+    File(PathBuf),
+    /// `host:port` of a listening TCP socket.
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl EventStreamTarget {
+    /// `stdout` (or `-`), `stderr`, `tcp://host:port`, `unix:/path/to.sock`,
+    /// anything else is a file path.
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "stdout" | "-" => Self::Stdout,
+            "stderr" => Self::Stderr,
+            _ => {
+                if let Some(address) = value.strip_prefix("tcp://") {
+                    return Self::Tcp(address.to_owned());
+                }
+                #[cfg(unix)]
+                if let Some(path) = value.strip_prefix("unix:") {
+                    return Self::Unix(PathBuf::from(path));
+                }
+                Self::File(PathBuf::from(value))
+            }
+        }
+    }
+
+    /// The target given with `--event-stream`, if any.
+    pub fn from_args() -> Option<Self> {
+        AppEnvironment::cmd_args()
+            .event_stream
+            .as_deref()
+            .map(Self::parse)
+    }
+
+    fn open(&self) -> std::io::Result<Box<dyn Write + Send>> {
+        Ok(match self {
+            Self::Stdout => Box::new(std::io::stdout()),
+            Self::Stderr => Box::new(std::io::stderr()),
+            Self::File(path) => Box::new(OpenOptions::new().create(true).append(true).open(path)?),
+            Self::Tcp(address) => Box::new(std::net::TcpStream::connect(address)?),
+            #[cfg(unix)]
+            Self::Unix(path) => Box::new(std::os::unix::net::UnixStream::connect(path)?),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    seq: u64,
+    timestamp: String,
+    #[serde(flatten)]
+    status: &'a Status,
+}
+
+pub struct NdjsonChannel {
+    writer: Mutex<Box<dyn Write + Send>>,
+    /// Lets consumers notice dropped lines.
+    next_seq: AtomicU64,
+}
+
+impl NdjsonChannel {
+    pub fn open(target: &EventStreamTarget) -> Result<Self> {
+        let writer = target
+            .open()
+            .map_err(|e| anyhow!("Cannot open the event stream {:?}: {}", target, e))?;
+        Ok(Self {
+            writer: Mutex::new(writer),
+            next_seq: AtomicU64::new(1),
+        })
+    }
+}
+
+impl EventChannel for NdjsonChannel {
+    fn send(&self, status: Status) -> Result<()> {
+        let record = Record {
+            seq: self.next_seq.fetch_add(1, Ordering::Relaxed),
+            timestamp: humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+            status: &status,
+        };
+        let line = serde_json::to_string(&record)?;
+
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|_| anyhow!("Event stream writer is poisoned"))?;
+        writeln!(writer, "{line}")?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_path;
+    use crate::types::Step;
+    use serde_json::Value;
+
+    #[test]
+    fn targets_are_parsed() {
+        assert_eq!(EventStreamTarget::parse("-"), EventStreamTarget::Stdout);
+        assert_eq!(
+            EventStreamTarget::parse("tcp://127.0.0.1:9000"),
+            EventStreamTarget::Tcp("127.0.0.1:9000".to_owned())
+        );
+        assert_eq!(
+            EventStreamTarget::parse("events.ndjson"),
+            EventStreamTarget::File(PathBuf::from("events.ndjson"))
+        );
+    }
+
+    #[test]
+    fn statuses_are_written_one_per_line() -> Result<()> {
+        let path = temp_path("dcl-events");
+        let channel = NdjsonChannel::open(&EventStreamTarget::File(path.clone()))?;
+        channel.send(Status::State {
+            step: Step::Fetching,
+        })?;
+        channel.send(Status::Error {
+            message: "failed".to_owned(),
+            can_free_space: false,
+            can_repair: false,
+        })?;
+
+        let content = std::fs::read_to_string(&path)?;
+        std::fs::remove_file(path)?;
+        let records = content
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<serde_json::Result<Vec<Value>>>()?;
+
+        let [fetching, error] = records.as_slice() else {
+            return Err(anyhow!("Expected two records, got {}", records.len()));
+        };
+        let field = |record: &Value, pointer: &str| record.pointer(pointer).cloned();
+        assert_eq!(field(fetching, "/seq"), Some(1.into()));
+        assert_eq!(field(fetching, "/event"), Some("state".into()));
+        assert_eq!(field(fetching, "/data/step/event"), Some("fetching".into()));
+        assert!(
+            fetching
+                .get("timestamp")
+                .and_then(Value::as_str)
+                .is_some_and(|t| t.ends_with('Z'))
+        );
+        assert_eq!(field(error, "/seq"), Some(2.into()));
+        assert_eq!(field(error, "/data/message"), Some("failed".into()));
+        Ok(())
+    }
+}
@@ -0,0 +1,544 @@
+//! Headless mode of the `dcl_launcher_core` binary, used by QA automation and
+//! support to drive the launcher without the UI:
+//!
+//! ```text
+//! dcl_launcher_core check
+//! dcl_launcher_core download
+//! dcl_launcher_core install --from <zip> [--version X]
+//! dcl_launcher_core launch [--version X]
+//! dcl_launcher_core list-versions
+//! dcl_launcher_core rollback [--version X]
+//! dcl_launcher_core verify
+//! dcl_launcher_core repair [--full]
+//! dcl_launcher_core clean
+//! dcl_launcher_core status
+//! ```
+//!
+//! Every command prints a single JSON document to stdout and exits with
+//! [`DCLError::exit_code`] on failure. Logs go to stderr, and so does the
+//! progress as an NDJSON event stream unless `--event-stream` points it
+//! elsewhere.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anyhow::Context;
+use serde::Serialize;
+use serde_json::{Value, json};
+use thiserror::Error;
+
+use crate::app::AppState;
+use crate::channel::ndjson::{EventStreamTarget, NdjsonChannel};
+use crate::errors::{DCLError, DCLErrorResult, DCLErrorTyped};
+use crate::flow::Verification;
+use crate::release_channel::ReleaseChannel;
+use crate::{installs, s3, utils};
+
+/// Exit code for a malformed command line.
+pub const USAGE_EXIT_CODE: u8 = 2;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Fetches the latest release and compares it with the installed one.
+    Check,
+    /// Downloads the latest release without installing it.
+    Download,
+    /// Installs a build archive, as the latest release when no version is given.
+    Install {
+        from: PathBuf,
+        version: Option<String>,
+    },
+    /// Launches an installed build, the current one by default.
+    Launch {
+        version: Option<String>,
+    },
+    ListVersions,
+    /// Goes back to a build kept for rollback, the newest older one by default.
+    Rollback {
+        version: Option<String>,
+    },
+    /// Compares the installed build with the file manifest of its release.
+    Verify,
+    /// Downloads the damaged files again, the whole build with `--full`.
+    Repair {
+        full: bool,
+    },
+    /// Removes the builds kept for rollback and leftover downloads.
+    Clean,
+    Status,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum UsageError {
+    #[error("Unknown command: {0}")]
+    UnknownCommand(String),
+    #[error("`{command}` requires --{flag} <value>")]
+    MissingValue {
+        command: &'static str,
+        flag: &'static str,
+    },
+}
+
+impl Command {
+    /// `None` when the first argument isn't a command, e.g. a flag or a
+    /// deeplink, the regular launch flow runs then.
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Option<Self>, UsageError> {
+        let args: Vec<String> = args.skip(1).collect();
+        let Some((name, rest)) = args.split_first() else {
+            return Ok(None);
+        };
+        if name.starts_with('-') || name.contains("://") {
+            return Ok(None);
+        }
+
+        let command = match name.as_str() {
+            "check" => Self::Check,
+            "download" => Self::Download,
+            "install" => Self::Install {
+                from: flag_value("from", rest).map(PathBuf::from).ok_or(
+                    UsageError::MissingValue {
+                        command: "install",
+                        flag: "from",
+                    },
+                )?,
+                version: flag_value("version", rest),
+            },
+            "launch" => Self::Launch {
+                version: flag_value("version", rest),
+            },
+            "list-versions" => Self::ListVersions,
+            "rollback" => Self::Rollback {
+                version: flag_value("version", rest),
+            },
+            "verify" => Self::Verify,
+            "repair" => Self::Repair {
+                full: has_flag("full", rest),
+            },
+            "clean" => Self::Clean,
+            "status" => Self::Status,
+            unknown => return Err(UsageError::UnknownCommand(unknown.to_owned())),
+        };
+        Ok(Some(command))
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Check => "check",
+            Self::Download => "download",
+            Self::Install { .. } => "install",
+            Self::Launch { .. } => "launch",
+            Self::ListVersions => "list-versions",
+            Self::Rollback { .. } => "rollback",
+            Self::Verify => "verify",
+            Self::Repair { .. } => "repair",
+            Self::Clean => "clean",
+            Self::Status => "status",
+        }
+    }
+}
+
+fn has_flag(flag: &str, args: &[String]) -> bool {
+    args.iter().any(|arg| arg.strip_prefix("--") == Some(flag))
+}
+
+/// Accepts both `--flag value` and `--flag=value`.
+fn flag_value(flag: &str, args: &[String]) -> Option<String> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let Some(name) = arg.strip_prefix("--") else {
+            continue;
+        };
+        if name == flag {
+            return iter
+                .next()
+                .filter(|value| !value.starts_with("--"))
+                .cloned();
+        }
+        if let Some(value) = name.strip_prefix(flag).and_then(|v| v.strip_prefix('=')) {
+            return Some(value.to_owned());
+        }
+    }
+    None
+}
+
+pub async fn run(command: Command) -> ExitCode {
+    let app_state = match AppState::setup_headless().await {
+        Ok(app_state) => app_state,
+        Err(e) => return report(command.name(), Err(e.context("Cannot setup state").into())),
+    };
+
+    let target = EventStreamTarget::from_args().unwrap_or(EventStreamTarget::Stderr);
+    let result = match NdjsonChannel::open(&target) {
+        Ok(channel) => execute(&command, &app_state, &channel).await,
+        Err(e) => Err(e.into()),
+    };
+    app_state.cleanup().await;
+    report(command.name(), result)
+}
+
+pub fn report_usage_error(error: &UsageError) -> ExitCode {
+    println!(
+        "{}",
+        json!({
+            "ok": false,
+            "error": { "code": "USAGE", "message": error.to_string() },
+        })
+    );
+    ExitCode::from(USAGE_EXIT_CODE)
+}
+
+fn report(command: &str, result: DCLErrorTyped<Value>) -> ExitCode {
+    let (output, exit_code) = match result {
+        Ok(result) => (
+            json!({ "ok": true, "command": command, "result": result }),
+            0,
+        ),
+        Err(e) => (
+            json!({
+                "ok": false,
+                "command": command,
+                "error": {
+                    "code": e.code(),
+                    "message": e.user_message(),
+                    "detail": format!("{e:?}"),
+                },
+            }),
+            e.exit_code(),
+        ),
+    };
+    println!("{output}");
+    ExitCode::from(exit_code)
+}
+
+async fn execute(
+    command: &Command,
+    app_state: &AppState,
+    channel: &NdjsonChannel,
+) -> DCLErrorTyped<Value> {
+    match command {
+        Command::Check => check().await,
+        Command::Download => download(app_state, channel).await,
+        Command::Install { from, version } => {
+            install(app_state, channel, from, version.clone()).await
+        }
+        Command::Launch { version } => launch(app_state, version.as_deref()).await,
+        Command::ListVersions => to_json(list_versions()),
+        Command::Rollback { version } => rollback(app_state, version.as_deref()).await,
+        Command::Verify => verify(app_state, channel).await,
+        Command::Repair { full } => repair(app_state, channel, *full).await,
+        Command::Clean => clean(app_state).await,
+        Command::Status => status(app_state).await,
+    }
+}
+
+fn to_json(output: impl Serialize) -> DCLErrorTyped<Value> {
+    let value = serde_json::to_value(output).context("Cannot serialize the command output")?;
+    Ok(value)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckOutput {
+    channel: String,
+    latest: String,
+    installed: Option<String>,
+    update_available: bool,
+}
+
+async fn check() -> DCLErrorTyped<Value> {
+    let channel = ReleaseChannel::selected();
+    let release = s3::get_latest_explorer_release(&channel).await?;
+    to_json(CheckOutput {
+        // A release outside the launcher bounds isn't installed
+        update_available: !installs::is_explorer_updated(&release.version, &channel)
+            && release.check_launcher_version().is_ok(),
+        channel: channel.name().to_owned(),
+        latest: release.version,
+        installed: installs::installed_latest_version(),
+    })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadOutput {
+    version: Option<String>,
+    path: Option<PathBuf>,
+    kind: Option<&'static str>,
+    up_to_date: bool,
+}
+
+async fn download(app_state: &AppState, channel: &NdjsonChannel) -> DCLErrorTyped<Value> {
+    let downloaded = app_state
+        .flow
+        .download(channel, app_state.state.clone())
+        .await?;
+    to_json(match downloaded {
+        Some(build) => DownloadOutput {
+            version: Some(build.version),
+            path: Some(build.path),
+            kind: Some(build.kind),
+            up_to_date: false,
+        },
+        None => DownloadOutput {
+            version: installs::installed_latest_version(),
+            path: None,
+            kind: None,
+            up_to_date: true,
+        },
+    })
+}
+
+async fn install(
+    app_state: &AppState,
+    channel: &NdjsonChannel,
+    from: &PathBuf,
+    version: Option<String>,
+) -> DCLErrorTyped<Value> {
+    if !from.exists() {
+        return Err(DCLError::E1001_FILE_NOT_FOUND {
+            expected_path: Some(from.to_string_lossy().into_owned()),
+        });
+    }
+    let version = match version {
+        Some(version) => version,
+        None => {
+            s3::get_latest_explorer_release(&ReleaseChannel::selected())
+                .await?
+                .version
+        }
+    };
+
+    // The install removes the archive, so it works on a copy of the given one
+    let archive = installs::target_download_path();
+    std::fs::copy(from, &archive).map_err(|source| DCLError::E1007_FILE_CREATE_FAILED {
+        file_path: archive.to_string_lossy().into_owned(),
+        source,
+    })?;
+    app_state
+        .flow
+        .install_archive(channel, app_state.state.clone(), version.clone(), archive)
+        .await?;
+
+    Ok(json!({ "version": version }))
+}
+
+async fn launch(app_state: &AppState, version: Option<&str>) -> DCLErrorTyped<Value> {
+    app_state
+        .installs_hub
+        .lock()
+        .await
+        .launch_explorer(None, version)
+        .await?;
+    let version = version
+        .map(str::to_owned)
+        .or_else(installs::installed_latest_version);
+    Ok(json!({ "version": version }))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VersionOutput {
+    version: String,
+    installed_at: Option<u64>,
+    last_launched_at: Option<u64>,
+    current: bool,
+    path: PathBuf,
+}
+
+fn list_versions() -> Vec<VersionOutput> {
+    installs::installed_versions()
+        .into_iter()
+        .map(|installed| VersionOutput {
+            version: installed.version,
+            installed_at: installed.installed_at,
+            last_launched_at: installed.last_launched_at,
+            current: installed.current,
+            path: installed.path,
+        })
+        .collect()
+}
+
+async fn ensure_explorer_not_running(app_state: &AppState) -> DCLErrorResult {
+    let running = app_state
+        .running_instances
+        .lock()
+        .await
+        .explorer_processes_by_path();
+    if !running.is_empty() {
+        return Err(DCLError::E3008_EXPLORER_ALREADY_RUNNING { processes: running });
+    }
+    Ok(())
+}
+
+async fn rollback(app_state: &AppState, version: Option<&str>) -> DCLErrorTyped<Value> {
+    ensure_explorer_not_running(app_state).await?;
+
+    let _lock = installs::lock_install()?;
+    let (from, to) = installs::rollback(version)?;
+    Ok(json!({ "from": from, "to": to }))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifyOutput {
+    version: String,
+    channel: String,
+    /// Only a verifiable build is reported intact.
+    intact: bool,
+    /// `false` when the release publishes no file manifest, only the
+    /// executable is checked then.
+    verifiable: bool,
+    executable_found: bool,
+    checked: u64,
+    missing: Vec<String>,
+    modified: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repaired: Option<&'static str>,
+}
+
+impl From<&Verification> for VerifyOutput {
+    fn from(verification: &Verification) -> Self {
+        let report = verification.report.clone().unwrap_or_default();
+        Self {
+            version: verification.version.clone(),
+            channel: verification.channel.name().to_owned(),
+            intact: verification.is_intact(),
+            verifiable: verification.report.is_some(),
+            executable_found: verification.executable_found,
+            checked: report.checked,
+            missing: report.missing,
+            modified: report.modified,
+            repaired: None,
+        }
+    }
+}
+
+async fn verify(app_state: &AppState, channel: &NdjsonChannel) -> DCLErrorTyped<Value> {
+    let verification = app_state.flow.verify(channel).await?;
+    to_json(VerifyOutput::from(&verification))
+}
+
+async fn repair(app_state: &AppState, channel: &NdjsonChannel, full: bool) -> DCLErrorTyped<Value> {
+    let repair = app_state
+        .flow
+        .repair(channel, app_state.state.clone(), full)
+        .await?;
+    to_json(VerifyOutput {
+        repaired: Some(repair.kind.label()),
+        ..VerifyOutput::from(&repair.verification)
+    })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CleanOutput {
+    freed_old_versions: u64,
+    freed_downloads: u64,
+}
+
+async fn clean(app_state: &AppState) -> DCLErrorTyped<Value> {
+    ensure_explorer_not_running(app_state).await?;
+
+    to_json(CleanOutput {
+        freed_old_versions: installs::prune_old_versions()?,
+        freed_downloads: installs::remove_downloads(),
+    })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatusOutput {
+    launcher_version: &'static str,
+    installed_version: Option<String>,
+    installed_channel: Option<String>,
+    versions: Vec<VersionOutput>,
+    running_explorers: Vec<String>,
+    reclaimable_space: u64,
+    log_path: Option<PathBuf>,
+}
+
+async fn status(app_state: &AppState) -> DCLErrorTyped<Value> {
+    let running_explorers = app_state
+        .running_instances
+        .lock()
+        .await
+        .explorer_processes_by_path();
+    to_json(StatusOutput {
+        launcher_version: utils::app_version(),
+        installed_version: installs::installed_latest_version(),
+        installed_channel: installs::installed_release_channel().map(|c| c.name().to_owned()),
+        versions: list_versions(),
+        running_explorers,
+        reclaimable_space: installs::reclaimable_space(),
+        log_path: installs::log_file_path().ok(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        std::iter::once("dcl_launcher_core")
+            .chain(args.iter().copied())
+            .map(ToOwned::to_owned)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[rstest]
+    #[case(&[], None)]
+    #[case(&["--skip-analytics"], None)]
+    #[case(&["decentraland://?position=0,0"], None)]
+    #[case(&["check", "--skip-analytics"], Some(Command::Check))]
+    #[case(&["launch"], Some(Command::Launch { version: None }))]
+    #[case(
+        &["launch", "--version", "v1.2.3"],
+        Some(Command::Launch { version: Some("v1.2.3".to_owned()) })
+    )]
+    #[case(
+        &["install", "--from=/tmp/build.zip", "--version=v1.2.3"],
+        Some(Command::Install {
+            from: PathBuf::from("/tmp/build.zip"),
+            version: Some("v1.2.3".to_owned()),
+        })
+    )]
+    #[case(&["list-versions"], Some(Command::ListVersions))]
+    #[case(&["rollback"], Some(Command::Rollback { version: None }))]
+    #[case(
+        &["rollback", "--version", "v1.2.0"],
+        Some(Command::Rollback { version: Some("v1.2.0".to_owned()) })
+    )]
+    #[case(&["verify"], Some(Command::Verify))]
+    #[case(&["repair"], Some(Command::Repair { full: false }))]
+    #[case(&["repair", "--full"], Some(Command::Repair { full: true }))]
+    fn parses_commands(#[case] input: &[&str], #[case] expected: Option<Command>) {
+        assert_eq!(Command::parse(args(input)), Ok(expected));
+    }
+
+    #[test]
+    fn rejects_malformed_commands() {
+        assert_eq!(
+            Command::parse(args(&["instal"])),
+            Err(UsageError::UnknownCommand("instal".to_owned()))
+        );
+        assert_eq!(
+            Command::parse(args(&["install", "--from", "--version", "v1.2.3"])),
+            Err(UsageError::MissingValue {
+                command: "install",
+                flag: "from",
+            })
+        );
+    }
+
+    #[test]
+    fn exit_codes_follow_error_codes() {
+        assert_eq!(DCLError::E3003_CANT_GET_VERSION.exit_code(), 153);
+        assert_eq!(
+            DCLError::E2006_DOWNLOAD_FAILED_NETWORK_TIMEOUT.exit_code(),
+            106
+        );
+        assert_eq!(DCLError::from(anyhow::anyhow!("generic")).exit_code(), 1);
+    }
+}
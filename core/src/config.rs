@@ -1,5 +1,6 @@
 use anyhow::{Context, Result, anyhow};
 use log::error;
+use serde::de::DeserializeOwned;
 use serde_json::{Map, Value};
 
 use crate::installs::config_path;
@@ -22,7 +23,8 @@ fn write_config(value: &Map<String, Value>) -> Result<()> {
     Ok(())
 }
 
-fn user_id() -> Result<String> {
+/// Analytics user id, generated and persisted on first use.
+pub fn user_id() -> Result<String> {
     const KEY: &str = "analytics-user-id";
     let config = config_content()?;
     if let Some(id) = config.get(KEY) {
@@ -51,6 +53,37 @@ pub fn user_id_or_none() -> String {
     })
 }
 
+/// Value under `key`, `None` when it is missing or has another type.
+fn typed_value<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let config = config_content()
+        .inspect_err(|e| error!("Error on reading config content: {}", e))
+        .ok()?;
+    let value = config.get(key)?.clone();
+    serde_json::from_value(value)
+        .inspect_err(|e| error!("Value under key {} is in a wrong format: {}", key, e))
+        .ok()
+}
+
+/// Download rate limit in KiB per second.
+pub fn download_rate_limit() -> Option<u64> {
+    typed_value("download-rate-limit")
+}
+
+/// Name of the release channel, see `release_channel`.
+pub fn release_channel() -> Option<String> {
+    typed_value("release-channel")
+}
+
+/// Explorer version to stay on, see `--pin-version`.
+pub fn pinned_version() -> Option<String> {
+    typed_value("pinned-version")
+}
+
+/// Origins tried before the compiled ones, see `mirrors`.
+pub fn mirrors() -> Vec<String> {
+    typed_value("mirrors").unwrap_or_default()
+}
+
 pub fn arguments_from_key(key: &str) -> Vec<String> {
     let config = config_content();
     match config {
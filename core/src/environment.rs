@@ -7,8 +7,12 @@ use crate::config;
 const DEFAULT_PROVIDER: &str = "dcl";
 
 const BUCKET_URL: &str = env!("VITE_AWS_S3_BUCKET_PUBLIC_URL");
+/// Comma separated origins serving the same content as `BUCKET_URL`.
+const BUCKET_MIRROR_URLS: Option<&str> = option_env!("VITE_AWS_S3_BUCKET_MIRROR_URLS");
 const PROVIDER: Option<&str> = option_env!("VITE_PROVIDER");
 const LAUNCHER_ENVIRONMENT: Option<&str> = option_env!("LAUNCHER_ENVIRONMENT");
+/// Base64 encoded ed25519 public key that signs the Explorer release manifests.
+const EXPLORER_MANIFEST_PUBLIC_KEY: Option<&str> = option_env!("EXPLORER_MANIFEST_PUBLIC_KEY");
 
 const ARG_SKIP_ANALYTICS: &str = "skip-analytics";
 const ARG_FORCE_IN_MEMORY_ANALYTICS_QUEUE: &str = "force-in-memory-analytics-queue";
@@ -17,6 +21,15 @@ const ARG_NEVER_TRIGGER_UPDATER: &str = "never-trigger-updater";
 const ARG_USE_UPDATER_URL: &str = "use-updater-url";
 
 const ARG_USE_LATEST_JSON_URL: &str = "use-latest-json-url";
+const ARG_USE_LATEST_MANIFEST_URL: &str = "use-latest-manifest-url";
+
+const ARG_DOWNLOAD_CONNECTIONS: &str = "download-connections";
+const ARG_DOWNLOAD_RATE_LIMIT: &str = "download-rate-limit";
+
+const ARG_EVENT_STREAM: &str = "event-stream";
+
+const ARG_RELEASE_CHANNEL: &str = "release-channel";
+const ARG_PIN_VERSION: &str = "pin-version";
 
 pub const ARG_OPEN_DEEPLINK_IN_NEW_INSTANCE: &str = "open-deeplink-in-new-instance";
 // Alias of ARG_OPEN_DEEPLINK_IN_NEW_INSTANCE: either flag enables the same behavior.
@@ -50,6 +63,21 @@ pub struct Args {
     pub use_updater_url: Option<String>,
 
     pub use_latest_json_url: Option<String>,
+    pub use_latest_manifest_url: Option<String>,
+
+    /// More than one splits build downloads into concurrent range requests.
+    pub download_connections: Option<usize>,
+    /// KiB per second, overrides `download-rate-limit` of `config.json`.
+    pub download_rate_limit: Option<u64>,
+
+    /// Target of the NDJSON event stream of the core binary, see
+    /// `channel::ndjson`. `stdout` when the flag is given without a value.
+    pub event_stream: Option<String>,
+
+    /// Release channel to install builds from, see `release_channel`.
+    pub release_channel: Option<String>,
+    /// Explorer version to stay on instead of the latest release.
+    pub pin_version: Option<String>,
 
     // used by the client
     pub local_scene: bool,
@@ -75,6 +103,24 @@ impl Args {
                 .use_latest_json_url
                 .clone()
                 .or_else(|| other.use_latest_json_url.clone()),
+            use_latest_manifest_url: self
+                .use_latest_manifest_url
+                .clone()
+                .or_else(|| other.use_latest_manifest_url.clone()),
+            download_connections: self.download_connections.or(other.download_connections),
+            download_rate_limit: self.download_rate_limit.or(other.download_rate_limit),
+            event_stream: self
+                .event_stream
+                .clone()
+                .or_else(|| other.event_stream.clone()),
+            release_channel: self
+                .release_channel
+                .clone()
+                .or_else(|| other.release_channel.clone()),
+            pin_version: self
+                .pin_version
+                .clone()
+                .or_else(|| other.pin_version.clone()),
             local_scene: self.local_scene || other.local_scene,
             bridge_only: self.bridge_only || other.bridge_only,
         }
@@ -97,6 +143,15 @@ impl Args {
             never_trigger_updater: Self::has_flag(ARG_NEVER_TRIGGER_UPDATER, &vector),
             use_updater_url: Self::value_by_flag(ARG_USE_UPDATER_URL, &vector),
             use_latest_json_url: Self::value_by_flag(ARG_USE_LATEST_JSON_URL, &vector),
+            use_latest_manifest_url: Self::value_by_flag(ARG_USE_LATEST_MANIFEST_URL, &vector),
+            download_connections: Self::value_by_flag(ARG_DOWNLOAD_CONNECTIONS, &vector)
+                .and_then(|v| v.parse().ok()),
+            download_rate_limit: Self::value_by_flag(ARG_DOWNLOAD_RATE_LIMIT, &vector)
+                .and_then(|v| v.parse().ok()),
+            event_stream: Self::value_by_flag(ARG_EVENT_STREAM, &vector)
+                .or_else(|| Self::has_flag(ARG_EVENT_STREAM, &vector).then(|| "stdout".to_owned())),
+            release_channel: Self::value_by_flag(ARG_RELEASE_CHANNEL, &vector),
+            pin_version: Self::value_by_flag(ARG_PIN_VERSION, &vector),
             local_scene: Self::has_flag(ARG_LOCAL_SCENE, &vector),
             bridge_only: Self::has_flag(ARG_BRIDGE_ONLY, &vector),
         }
@@ -141,6 +196,20 @@ impl AppEnvironment {
         String::from(BUCKET_URL)
     }
 
+    pub fn bucket_mirror_urls() -> Vec<String> {
+        BUCKET_MIRROR_URLS
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+
+    pub const fn manifest_public_key() -> Option<&'static str> {
+        EXPLORER_MANIFEST_PUBLIC_KEY
+    }
+
     pub fn launcher_environment() -> LauncherEnvironment {
         match LAUNCHER_ENVIRONMENT {
             Some(raw) => match raw {
@@ -253,6 +322,43 @@ mod tests {
         assert!(args.use_updater_url.is_some());
     }
 
+    #[test]
+    fn test_download_connections_parsed() {
+        let parse = |values: &[&str]| {
+            Args::parse(values.iter().map(|v| (*v).to_owned())).download_connections
+        };
+
+        assert_eq!(parse(&["app", "--download-connections", "4"]), Some(4));
+        assert_eq!(parse(&["app", "--download-connections", "many"]), None);
+        assert_eq!(parse(&["app"]), None);
+    }
+
+    #[test]
+    fn test_download_rate_limit_parsed() {
+        let args = Args::parse(
+            ["app", "--download-rate-limit", "512"]
+                .map(ToOwned::to_owned)
+                .into_iter(),
+        );
+        assert_eq!(args.download_rate_limit, Some(512));
+    }
+
+    #[test]
+    fn test_event_stream_parsed() {
+        let parse =
+            |values: &[&str]| Args::parse(values.iter().map(|v| (*v).to_owned())).event_stream;
+
+        assert_eq!(
+            parse(&["app", "--event-stream", "/tmp/events.ndjson"]).as_deref(),
+            Some("/tmp/events.ndjson")
+        );
+        assert_eq!(
+            parse(&["app", "--event-stream", "--skip-analytics"]).as_deref(),
+            Some("stdout")
+        );
+        assert_eq!(parse(&["app"]), None);
+    }
+
     #[test]
     fn test_merge_with() {
         let a = Args {
@@ -263,6 +369,12 @@ mod tests {
             never_trigger_updater: false,
             use_updater_url: Some("https://one.com".into()),
             use_latest_json_url: None,
+            use_latest_manifest_url: None,
+            download_connections: None,
+            download_rate_limit: None,
+            event_stream: None,
+            release_channel: Some("beta".into()),
+            pin_version: None,
             local_scene: false,
             bridge_only: false,
         };
@@ -275,6 +387,12 @@ mod tests {
             never_trigger_updater: true,
             use_updater_url: Some("https://two.com".into()),
             use_latest_json_url: Some("https://one.com".into()),
+            use_latest_manifest_url: None,
+            download_connections: None,
+            download_rate_limit: None,
+            event_stream: None,
+            release_channel: None,
+            pin_version: Some("v1.2.3".into()),
             local_scene: false,
             bridge_only: false,
         };
@@ -292,5 +410,7 @@ mod tests {
             merged.use_latest_json_url.as_deref(),
             Some("https://one.com")
         );
+        assert_eq!(merged.release_channel.as_deref(), Some("beta"));
+        assert_eq!(merged.pin_version.as_deref(), Some("v1.2.3"));
     }
 }
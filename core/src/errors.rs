@@ -4,19 +4,54 @@ use strum::IntoStaticStr;
 use thiserror::Error;
 
 use crate::installs::downloads::{DownloadFileError, FileIncompleteError};
+use crate::installs::incremental::IncrementalError;
+use crate::installs::patches::PatchError;
 
 use crate::deeplink_bridge::PlaceDeeplinkError;
+use crate::flow_control::Cancelled;
+use crate::mirrors::{MirrorFailure, is_mirror_http_failure};
+use crate::s3::manifest::ManifestSignatureError;
 
 use super::types::Status;
 
+#[derive(Default)]
 pub struct FlowError {
     pub user_message: String,
+    /// Old versions kept for rollback can be removed to make room, see
+    /// `installs::prune_old_versions`.
+    pub can_free_space: bool,
+    /// The installed build looks damaged, it can be verified and repaired,
+    /// see `LaunchFlow::repair`.
+    pub can_repair: bool,
+}
+
+impl From<&DCLError> for FlowError {
+    fn from(error: &DCLError) -> Self {
+        Self {
+            user_message: error.user_message().to_owned(),
+            can_free_space: matches!(
+                error,
+                DCLError::E1009_NOT_ENOUGH_DISK_SPACE { reclaimable, .. } if *reclaimable > 0
+            ),
+            can_repair: matches!(
+                error,
+                DCLError::E3009_EXPLORER_NOT_INSTALLED { .. }
+                    | DCLError::E3010_EXPLORER_LAUNCH_FAILED { .. }
+                    | DCLError::E3011_EXPLORER_PROCESS_NOT_STARTED { .. }
+                    | DCLError::E3012_EXPLORER_EXITED_ON_LAUNCH { .. }
+                    | DCLError::E3013_EXPLORER_BINARY_ACCESS_FAILED { .. }
+                    | DCLError::E3020_INSTALL_VERIFY_FAILED { .. }
+            ),
+        }
+    }
 }
 
 impl From<&FlowError> for Status {
     fn from(err: &FlowError) -> Self {
         Self::Error {
             message: err.user_message.clone(),
+            can_free_space: err.can_free_space,
+            can_repair: err.can_repair,
         }
     }
 }
@@ -50,6 +85,11 @@ impl<T> From<DCLError> for DCLErrorTyped<T> {
 
 #[allow(non_camel_case_types)]
 #[derive(Error, Debug, IntoStaticStr)]
+#[cfg_attr(test, derive(strum::EnumDiscriminants))]
+#[cfg_attr(
+    test,
+    strum_discriminants(derive(strum::EnumIter, IntoStaticStr), allow(non_camel_case_types))
+)]
 pub enum DCLError {
     E0000_GENERIC_ERROR {
         #[source]
@@ -84,6 +124,17 @@ pub enum DCLError {
         #[source]
         source: std::io::Error,
     },
+    E1008_UNSAFE_ARCHIVE_ENTRY {
+        entry: String,
+        reason: &'static str,
+    },
+    E1009_NOT_ENOUGH_DISK_SPACE {
+        required: u64,
+        available: u64,
+        /// Taken by old versions kept for rollback.
+        reclaimable: u64,
+        user_message: String,
+    },
 
     E2001_DOWNLOAD_FAILED {
         url: Option<String>,
@@ -106,6 +157,30 @@ pub enum DCLError {
     },
     E2005_DOWNLOAD_FAILED_FILE_INCOMPLETE(#[from] FileIncompleteError),
     E2006_DOWNLOAD_FAILED_NETWORK_TIMEOUT,
+    E2007_DOWNLOAD_CHECKSUM_MISMATCH {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+    E2008_RELEASE_MANIFEST_UNVERIFIED {
+        url: String,
+        #[source]
+        error: ManifestSignatureError,
+    },
+    E2009_LAUNCHER_UPDATE_REQUIRED {
+        version: String,
+        launcher_version: String,
+        min_launcher_version: String,
+    },
+    E2010_LAUNCHER_VERSION_UNSUPPORTED {
+        version: String,
+        launcher_version: String,
+        max_launcher_version: String,
+    },
+    E2011_RELEASE_MANIFEST_NOT_PUBLISHED {
+        version: String,
+        url: String,
+    },
     E3001_OPEN_DEEPLINK_TIMEOUT,
     E3002_PLACE_DEEPLINK_ERROR(#[from] PlaceDeeplinkError),
     E3003_CANT_GET_VERSION,
@@ -147,6 +222,31 @@ pub enum DCLError {
         #[source]
         source: std::io::Error,
     },
+    E3014_PATCH_APPLY_FAILED {
+        from_version: String,
+        #[source]
+        error: PatchError,
+    },
+    E3015_INCREMENTAL_UPDATE_FAILED {
+        #[source]
+        error: IncrementalError,
+    },
+    E3016_NO_ROLLBACK_VERSION {
+        current: String,
+    },
+    E3017_ROLLBACK_VERSION_NOT_RETAINED {
+        version: String,
+    },
+    E3018_FLOW_CANCELLED(#[from] Cancelled),
+    E3019_INSTALL_JOURNAL_WRITE_FAILED {
+        #[source]
+        source: std::io::Error,
+    },
+    E3020_INSTALL_VERIFY_FAILED {
+        #[source]
+        error: IncrementalError,
+    },
+    E3021_INSTALL_IN_PROGRESS,
 }
 
 impl DCLError {
@@ -157,6 +257,14 @@ impl DCLError {
         }
     }
 
+    /// Download failures keep their own codes.
+    pub fn from_verify(error: IncrementalError) -> Self {
+        match error {
+            IncrementalError::Download(e) => e.into(),
+            error => Self::E3020_INSTALL_VERIFY_FAILED { error },
+        }
+    }
+
     pub fn from_rename_back(path: &Path, source: std::io::Error) -> Self {
         Self::E3006_RENAME_BACK_FAILED {
             path: path.to_string_lossy().into_owned(),
@@ -164,6 +272,36 @@ impl DCLError {
         }
     }
 
+    pub fn from_disk_space(required: u64, available: u64, reclaimable: u64) -> Self {
+        let prune_hint = if reclaimable > 0 {
+            format!(
+                " Removing the older Decentraland versions kept as a backup frees {}.",
+                human_size(reclaimable)
+            )
+        } else {
+            String::new()
+        };
+        let user_message = format!(
+            "There isn't enough free space on your computer to install Decentraland. Please free up at least {} and try again.{}",
+            human_size(required.saturating_sub(available)),
+            prune_hint
+        );
+
+        Self::E1009_NOT_ENOUGH_DISK_SPACE {
+            required,
+            available,
+            reclaimable,
+            user_message,
+        }
+    }
+
+    pub fn from_manifest(url: &str, error: ManifestSignatureError) -> Self {
+        Self::E2008_RELEASE_MANIFEST_UNVERIFIED {
+            url: url.to_owned(),
+            error,
+        }
+    }
+
     pub fn from_launch_failure(path: &Path, inner_error: anyhow::Error) -> Self {
         Self::E3010_EXPLORER_LAUNCH_FAILED {
             path: path.to_string_lossy().into_owned(),
@@ -171,7 +309,7 @@ impl DCLError {
         }
     }
 
-    #[cfg(windows)]
+    #[cfg(any(windows, target_os = "linux"))]
     pub fn from_binary_access(path: &Path, source: std::io::Error) -> Self {
         Self::E3013_EXPLORER_BINARY_ACCESS_FAILED {
             path: path.to_string_lossy().into_owned(),
@@ -187,6 +325,13 @@ impl DCLError {
         self.into()
     }
 
+    /// Exit code of the headless CLI: the group digit of the code times 50
+    /// plus its number, e.g. 59 for E1009 and 162 for E3012. Generic errors
+    /// exit with 1.
+    pub fn exit_code(&self) -> u8 {
+        exit_code(self.code())
+    }
+
     #[must_use]
     pub fn apply_user_message_if_needed(self, new_user_message: &str) -> Self {
         match self {
@@ -208,6 +353,7 @@ impl DCLError {
     }
 
     // migrate to json config for i18n later
+    #[allow(clippy::too_many_lines)]
     pub fn user_message(&self) -> &str {
         #[allow(clippy::match_same_arms)]
         match self {
@@ -241,6 +387,10 @@ impl DCLError {
             Self::E1007_FILE_CREATE_FAILED { .. } => {
                 "We couldn't save the download to your computer. Please close the launcher and open it again. If the problem continues, try running it as administrator."
             }
+            Self::E1008_UNSAFE_ARCHIVE_ENTRY { .. } => {
+                "The downloaded files look damaged, so we stopped installing them. Please try again later."
+            }
+            Self::E1009_NOT_ENOUGH_DISK_SPACE { user_message, .. } => user_message,
             Self::E2001_DOWNLOAD_FAILED { .. } => {
                 "The download couldn't finish. Please check your internet connection and try again."
             }
@@ -259,6 +409,21 @@ impl DCLError {
             Self::E2006_DOWNLOAD_FAILED_NETWORK_TIMEOUT => {
                 "The download is taking too long. Please check your internet connection and try again."
             }
+            Self::E2007_DOWNLOAD_CHECKSUM_MISMATCH { .. } => {
+                "The downloaded files were damaged on the way. Please check your internet connection and try again."
+            }
+            Self::E2008_RELEASE_MANIFEST_UNVERIFIED { .. } => {
+                "We couldn't verify that this Decentraland update is authentic, so it wasn't installed. Please try again later."
+            }
+            Self::E2009_LAUNCHER_UPDATE_REQUIRED { .. } => {
+                "This Decentraland update needs a newer launcher. Please download the latest launcher from decentraland.org and try again."
+            }
+            Self::E2010_LAUNCHER_VERSION_UNSUPPORTED { .. } => {
+                "This launcher version isn't supported by the current Decentraland release yet. Please try again later."
+            }
+            Self::E2011_RELEASE_MANIFEST_NOT_PUBLISHED { .. } => {
+                "This Decentraland version was released before signed releases and can't be installed. Please choose a newer version."
+            }
             Self::E3001_OPEN_DEEPLINK_TIMEOUT => {
                 "We couldn't open the deeplink in Decentraland. Please close Decentraland and try again."
             }
@@ -298,8 +463,44 @@ impl DCLError {
             Self::E3013_EXPLORER_BINARY_ACCESS_FAILED { .. } => {
                 "We couldn't access the Decentraland files. If Decentraland is open, please close it and try again."
             }
+            Self::E3014_PATCH_APPLY_FAILED { .. }
+            | Self::E3015_INCREMENTAL_UPDATE_FAILED { .. } => {
+                "We couldn't update your Decentraland installation. Please try again."
+            }
+            Self::E3016_NO_ROLLBACK_VERSION { .. } => {
+                "There is no previous Decentraland version to go back to."
+            }
+            Self::E3017_ROLLBACK_VERSION_NOT_RETAINED { .. } => {
+                "This Decentraland version isn't kept on your computer anymore, so we can't go back to it."
+            }
+            Self::E3018_FLOW_CANCELLED(_) => "The update was cancelled.",
+            Self::E3019_INSTALL_JOURNAL_WRITE_FAILED { .. } => {
+                "We couldn't save the installation progress. Please check your disk and try again."
+            }
+            Self::E3020_INSTALL_VERIFY_FAILED { .. } => {
+                "We couldn't check your Decentraland installation. Please try again."
+            }
+            Self::E3021_INSTALL_IN_PROGRESS => {
+                "Decentraland is being installed by another launcher window. Please wait for it to finish and try again."
+            }
+        }
+    }
+}
+
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    #[allow(clippy::cast_precision_loss)]
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = "KB";
+    for next in UNITS.iter().skip(1) {
+        if size < 1024.0 {
+            break;
         }
+        size /= 1024.0;
+        unit = next;
     }
+    format!("{:.1} {}", size, unit)
 }
 
 impl Display for DCLError {
@@ -308,6 +509,18 @@ impl Display for DCLError {
     }
 }
 
+impl MirrorFailure for DCLError {
+    fn is_mirror_failure(&self) -> bool {
+        match self {
+            Self::E2001_DOWNLOAD_FAILED { .. }
+            | Self::E2005_DOWNLOAD_FAILED_FILE_INCOMPLETE(_)
+            | Self::E2006_DOWNLOAD_FAILED_NETWORK_TIMEOUT => true,
+            Self::E2004_DOWNLOAD_FAILED_HTTP_CODE { code, .. } => is_mirror_http_failure(*code),
+            _ => false,
+        }
+    }
+}
+
 impl From<anyhow::Error> for DCLError {
     fn from(value: anyhow::Error) -> Self {
         Self::E0000_GENERIC_ERROR {
@@ -364,6 +577,22 @@ impl From<zip::result::ZipError> for DCLError {
     }
 }
 
+/// A group can hold 49 codes before they collide with the next one, the test
+/// below catches that.
+fn exit_code(code: &str) -> u8 {
+    let number: u16 = code
+        .get(1..5)
+        .and_then(|digits| digits.parse().ok())
+        .unwrap_or_default();
+    let exit_code = (number / 1000)
+        .saturating_mul(50)
+        .saturating_add(number % 1000);
+    u8::try_from(exit_code)
+        .ok()
+        .filter(|code| *code != 0)
+        .unwrap_or(1)
+}
+
 impl From<DownloadFileError> for DCLError {
     fn from(value: DownloadFileError) -> Self {
         use DownloadFileError::*;
@@ -376,10 +605,23 @@ impl From<DownloadFileError> for DCLError {
                 url,
                 response_headers: HashMap::new(),
             },
+            HttpCode { url, code } => Self::E2004_DOWNLOAD_FAILED_HTTP_CODE { url, code },
             FileCreateFailed { source, file_path } => {
                 Self::E1007_FILE_CREATE_FAILED { file_path, source }
             }
             NetworkTimeout => Self::E2006_DOWNLOAD_FAILED_NETWORK_TIMEOUT,
+            ChecksumMismatch {
+                url,
+                expected,
+                actual,
+            } => Self::E2007_DOWNLOAD_CHECKSUM_MISMATCH {
+                url,
+                expected,
+                actual,
+            },
+            Cancelled(e) => e.into(),
+            // Handled by the download, which waits to be resumed
+            Paused => anyhow::anyhow!("Download paused").into(),
         }
     }
 }
@@ -390,3 +632,20 @@ impl From<reqwest::Error> for DCLError {
         Self::E2001_DOWNLOAD_FAILED { url, error: value }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn exit_codes_are_unique() {
+        let mut seen = HashSet::new();
+        for variant in DCLErrorDiscriminants::iter() {
+            let code: &'static str = variant.into();
+            let exit = exit_code(code);
+            assert!(seen.insert(exit), "{code} reuses exit code {exit}");
+        }
+    }
+}
@@ -1,30 +1,55 @@
 use crate::channel::EventChannel;
 use crate::deeplink_bridge::{execute_passthrough, should_use_deeplink_bridge_for};
+use crate::environment::AppEnvironment;
 use crate::errors::{AttemptError, DCLError, DCLErrorTyped};
+use crate::flow_control::{self, FlowControl};
 use crate::instances::RunningInstances;
 use crate::logs::LogDestination;
 use crate::protocols::{DeepLink, Protocol};
+use crate::release_channel::ReleaseChannel;
+use crate::rollout::RolloutAssignment;
 use crate::{
     analytics::{Analytics, event::Event},
-    environment::AppEnvironment,
     errors::{FlowError, DCLErrorResult},
-    installs::{self, InstallsHub},
-    s3::{self, ReleaseResponse},
-    types::{BuildType, Status, Step},
+    installs::{
+        self, BuildSource, InstallsHub,
+        compression::ExtractProgress,
+        disk_space::SpaceRequirement,
+        downloads::{DownloadFileError, DownloadFileResult},
+        incremental::{self, FileManifest, IncrementalError},
+        verify::VerifyReport,
+    },
+    s3::{ReleaseFileManifest, ReleasePatch, ReleaseResponse},
+    types::{BuildType, LauncherUpdate, Status, Step},
+    utils,
 };
 use anyhow::{Context, Ok, Result, anyhow};
 use log::info;
-use regex::Regex;
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use tokio::sync::Mutex;
 
 const SILENT_ATTEMPTS_COUNT: u8 = 3;
 
+const DEV_VERSION: &str = "dev";
+
 /// Retrying the whole preparation cannot help a deeplink consume-wait timeout: the consumer is
-/// booting, hung, or deferring, and every retry just re-waits the same budget. All other errors
-/// (network, disk) stay retryable.
+/// booting, hung, or deferring, and every retry just re-waits the same budget. Neither can a
+/// launcher the release doesn't support, a version without a signed manifest, nor a flow the
+/// user cancelled. All other errors (network, disk) stay retryable.
 const fn is_retryable_error(error: &DCLError) -> bool {
-    !matches!(error, DCLError::E3001_OPEN_DEEPLINK_TIMEOUT)
+    !matches!(
+        error,
+        DCLError::E3001_OPEN_DEEPLINK_TIMEOUT
+            | DCLError::E1009_NOT_ENOUGH_DISK_SPACE { .. }
+            | DCLError::E2009_LAUNCHER_UPDATE_REQUIRED { .. }
+            | DCLError::E2010_LAUNCHER_VERSION_UNSUPPORTED { .. }
+            | DCLError::E2011_RELEASE_MANIFEST_NOT_PUBLISHED { .. }
+            | DCLError::E3018_FLOW_CANCELLED(_)
+    )
 }
 
 /// An attempt is final when the silent-retry budget is exhausted or the error is not retryable.
@@ -71,16 +96,198 @@ trait WorkflowStep<TState, TOutput> {
     }
 }
 
+/// What the flow prepares and launches, picked from the command line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum LaunchMode {
+    /// Fetch, download and install the latest release, then launch it.
+    #[default]
+    Latest,
+    /// `--downloadedfilepath=<path>`: install the archive as the `dev` build
+    /// and launch it.
+    DownloadedFile(PathBuf),
+    /// `--version=dev` or `--dev`: launch the `dev` build without any checks.
+    Dev,
+    /// `--version=<version>`: launch an installed version without updating.
+    Installed(String),
+}
+
+impl LaunchMode {
+    #[allow(clippy::implicit_hasher)]
+    pub fn from_args(args: &HashMap<String, String>) -> Self {
+        if let Some(path) = utils::downloaded_file_path(args) {
+            return Self::DownloadedFile(PathBuf::from(path));
+        }
+        if utils::should_run_dev_version(args) {
+            return Self::Dev;
+        }
+        match utils::get_version(args) {
+            Some(version) => Self::Installed(version.to_owned()),
+            None => Self::Latest,
+        }
+    }
+
+    pub fn from_cmd_args() -> Self {
+        let mode = Self::from_args(&utils::parse_args(AppEnvironment::raw_cmd_args()));
+        info!("Launch mode: {:?}", mode);
+        mode
+    }
+
+    /// Version passed to `InstallsHub::launch_explorer`, `None` for the latest.
+    fn launch_version(&self) -> Option<&str> {
+        match self {
+            Self::Latest => None,
+            Self::DownloadedFile(_) | Self::Dev => Some(DEV_VERSION),
+            Self::Installed(version) => Some(version),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct LaunchFlowState {
+    mode: LaunchMode,
+    release_channel: ReleaseChannel,
     latest_release: Option<ReleaseResponse>,
+    /// The release is outside the launcher bounds, the installed build is
+    /// launched instead of updated.
+    keep_installed: bool,
     recent_download: Option<RecentDownload>,
+    /// Version whose patch or file-level update couldn't be applied, the
+    /// full build is used instead.
+    partial_update_failed_for: Option<String>,
+}
+
+impl LaunchFlowState {
+    pub fn new(mode: LaunchMode) -> Self {
+        Self {
+            mode,
+            ..Self::default()
+        }
+    }
+
+    /// Installed version a patch or file-level update to `version` can be
+    /// applied on top of. Builds of another channel are replaced entirely.
+    fn partial_update_base(&self, version: &str) -> Option<String> {
+        let partial_update_failed = self.partial_update_failed_for.as_deref() == Some(version);
+        let same_channel =
+            installs::installed_release_channel().as_ref() == Some(&self.release_channel);
+        installs::installed_latest_version().filter(|_| same_channel && !partial_update_failed)
+    }
+}
+
+#[derive(Clone)]
+enum DownloadKind {
+    Full,
+    /// A patch on top of this installed version.
+    Patch {
+        from_version: String,
+    },
+    /// A file manifest, the files are already in `installs::file_store_path`.
+    Files,
+    /// An archive given with `--downloadedfilepath`, installed as the `dev` build.
+    Dev,
+    /// A build kept for rollback, it is swapped with "latest".
+    Retained,
+}
+
+impl DownloadKind {
+    const fn label(&self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Patch { .. } => "patch",
+            Self::Files => "files",
+            Self::Dev => "dev",
+            Self::Retained => "retained",
+        }
+    }
+}
+
+/// What [`LaunchFlow::download`] left in the downloads directory.
+pub struct DownloadedBuild {
+    pub version: String,
+    pub path: PathBuf,
+    /// "full", "patch", "files" or "retained", only a full build can be
+    /// installed with [`LaunchFlow::install_archive`]. A retained build is
+    /// kept for rollback and isn't downloaded.
+    pub kind: &'static str,
+}
+
+/// What [`LaunchFlow::verify`] found in the installed build.
+pub struct Verification {
+    pub version: String,
+    pub channel: ReleaseChannel,
+    /// `None` when the release publishes no file manifest, the build can't
+    /// be verified then and only the executable is checked.
+    pub report: Option<VerifyReport>,
+    pub executable_found: bool,
+    release: ReleaseResponse,
+    manifest: Option<FileManifest>,
+}
+
+impl Verification {
+    /// `false` as well for a build that can't be verified, a found
+    /// executable doesn't make the rest of the build intact.
+    pub fn is_intact(&self) -> bool {
+        self.executable_found && self.report.as_ref().is_some_and(VerifyReport::is_intact)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepairKind {
+    /// Nothing was damaged.
+    NotNeeded,
+    /// Only the damaged files were downloaded and replaced.
+    Files,
+    /// The whole build was downloaded and installed again.
+    Full,
+}
+
+impl RepairKind {
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::NotNeeded => "none",
+            Self::Files => "files",
+            Self::Full => "full",
+        }
+    }
+}
+
+/// What [`LaunchFlow::repair`] found and did.
+pub struct Repair {
+    pub verification: Verification,
+    pub kind: RepairKind,
 }
 
 #[derive(Clone)]
 struct RecentDownload {
     version: String,
     downloaded_path: PathBuf,
+    kind: DownloadKind,
+}
+
+impl RecentDownload {
+    /// What was downloaded of `release`, recorded in the install registry.
+    fn source(&self, release: &ReleaseResponse) -> Option<BuildSource> {
+        if release.version != self.version {
+            return None;
+        }
+        match &self.kind {
+            DownloadKind::Full => Some(BuildSource {
+                url: release.browser_download_url.clone(),
+                sha256: release.checksum.as_ref().map(|c| c.sha256.clone()),
+            }),
+            DownloadKind::Patch { from_version } => {
+                release.patch_from(from_version).map(|patch| BuildSource {
+                    url: patch.url.clone(),
+                    sha256: Some(patch.checksum.sha256.clone()),
+                })
+            }
+            DownloadKind::Files => release.file_manifest.as_ref().map(|manifest| BuildSource {
+                url: manifest.url.clone(),
+                sha256: Some(manifest.checksum.sha256.clone()),
+            }),
+            DownloadKind::Dev | DownloadKind::Retained => None,
+        }
+    }
 }
 
 #[allow(clippy::struct_field_names)]
@@ -92,6 +299,7 @@ pub struct LaunchFlow {
     app_launch_step: AppLaunchStep,
 
     analytics: Arc<Mutex<Analytics>>,
+    control: FlowControl,
 }
 
 impl LaunchFlow {
@@ -99,6 +307,7 @@ impl LaunchFlow {
         installs_hub: Arc<Mutex<InstallsHub>>,
         analytics: Arc<Mutex<Analytics>>,
         running_instances: Arc<Mutex<RunningInstances>>,
+        control: FlowControl,
     ) -> Self {
         let app_launch_step = AppLaunchStep {
             installs_hub,
@@ -111,16 +320,19 @@ impl LaunchFlow {
             },
             download_step: DownloadStep {
                 analytics: analytics.clone(),
+                control: control.clone(),
             },
             install_step: InstallStep {
                 analytics: analytics.clone(),
                 running_instances: running_instances.clone(),
+                control: control.clone(),
             },
             deeplink_passthrough_step: DeeplinkPassthroughStep {
                 running_instances,
             },
             app_launch_step,
             analytics,
+            control,
         }
     }
 
@@ -129,6 +341,11 @@ impl LaunchFlow {
         channel: &T,
         state: Arc<Mutex<LaunchFlowState>>,
     ) -> std::result::Result<(), FlowError> {
+        // Cancelled before the flow started, e.g. during the launcher update
+        if let Err(e) = self.control.check() {
+            return std::result::Result::Err(FlowError::from(&DCLError::from(e)));
+        }
+
         let handled_by_passthrough = self.prepare_with_retries(channel, state.clone()).await?;
         if handled_by_passthrough {
             return std::result::Result::Ok(());
@@ -137,6 +354,225 @@ impl LaunchFlow {
         self.launch_once(channel, state).await
     }
 
+    /// Fetches the latest release and downloads it without installing it.
+    /// `None` when the installed build is already the latest one.
+    pub async fn download<T: EventChannel>(
+        &self,
+        channel: &T,
+        state: Arc<Mutex<LaunchFlowState>>,
+    ) -> DCLErrorTyped<Option<DownloadedBuild>> {
+        self.fetch_step
+            .execute_if_needed(channel, state.clone(), "fetch")
+            .await?;
+        self.download_step
+            .execute_if_needed(channel, state.clone(), "download")
+            .await?;
+
+        let guard = state.lock().await;
+        let downloaded = guard
+            .recent_download
+            .as_ref()
+            .map(|download| DownloadedBuild {
+                version: download.version.clone(),
+                path: download.downloaded_path.clone(),
+                kind: download.kind.label(),
+            });
+        DCLErrorTyped::Ok(downloaded)
+    }
+
+    /// Installs the full build archive at `path` as `version`. The archive is
+    /// removed once it is installed.
+    pub async fn install_archive<T: EventChannel>(
+        &self,
+        channel: &T,
+        state: Arc<Mutex<LaunchFlowState>>,
+        version: String,
+        path: PathBuf,
+    ) -> DCLErrorResult {
+        let mut guard = state.lock().await;
+        guard.release_channel = ReleaseChannel::selected();
+        guard.recent_download = Some(RecentDownload {
+            version,
+            downloaded_path: path,
+            kind: DownloadKind::Full,
+        });
+        drop(guard);
+        self.install_step
+            .execute_if_needed(channel, state, "install")
+            .await?;
+        DCLErrorResult::Ok(())
+    }
+
+    /// Compares the installed build with the file manifest of its release.
+    pub async fn verify<T: EventChannel>(&self, channel: &T) -> DCLErrorTyped<Verification> {
+        let Some(version) = installs::installed_latest_version() else {
+            return Err(DCLError::E3009_EXPLORER_NOT_INSTALLED {
+                expected_path: installs::explorer_latest_version_path()
+                    .to_string_lossy()
+                    .into_owned(),
+                version: None,
+            });
+        };
+        let release_channel =
+            installs::installed_release_channel().unwrap_or_else(ReleaseChannel::selected);
+        let release = installed_release(&release_channel, &version).await?;
+
+        let manifest = match &release.file_manifest {
+            Some(file_manifest) => Some(
+                self.download_step
+                    .fetch_file_manifest(
+                        channel,
+                        &BuildType::Update,
+                        &version,
+                        file_manifest,
+                        &installs::repair_manifest_path(),
+                    )
+                    .await
+                    .map_err(DCLError::from_verify)?,
+            ),
+            None => None,
+        };
+        channel.send(Status::State {
+            step: Step::Verifying,
+        })?;
+        let report = manifest
+            .as_ref()
+            .map(|m| installs::verify::verify(m, &installs::explorer_latest_version_path()))
+            .transpose()
+            .map_err(DCLError::from_verify);
+        installs::remove_repair_leftovers();
+        let report = report?;
+
+        let verification = Verification {
+            executable_found: installs::is_explorer_installed(None),
+            version,
+            channel: release_channel,
+            report,
+            release,
+            manifest,
+        };
+        let report = verification.report.as_ref();
+        self.analytics
+            .lock()
+            .await
+            .track_and_flush_silent(Event::INSTALL_VERSION_VERIFIED {
+                version: verification.version.clone(),
+                verifiable: report.is_some(),
+                files_missing: report.map_or(0, |r| r.missing.len() as u64),
+                files_modified: report.map_or(0, |r| r.modified.len() as u64),
+            })
+            .await;
+        DCLErrorTyped::Ok(verification)
+    }
+
+    /// Verifies the installed build and repairs it: only the damaged files
+    /// are downloaded again when the release publishes a file manifest, the
+    /// whole build otherwise, or when `full` is set. A build whose "latest"
+    /// directory is gone is installed again without verifying.
+    pub async fn repair<T: EventChannel>(
+        &self,
+        channel: &T,
+        state: Arc<Mutex<LaunchFlowState>>,
+        full: bool,
+    ) -> DCLErrorTyped<Repair> {
+        self.install_step.check_explorer_not_running().await?;
+        // "latest" was removed, there is nothing to verify or repair file by file
+        let missing = installs::installed_latest_version()
+            .is_none()
+            .then(installs::registered_latest_version)
+            .flatten();
+        let full = full || missing.is_some();
+        let verification = match missing {
+            Some(version) => Self::missing_build(version).await?,
+            None => self.verify(channel).await?,
+        };
+        if !full && verification.is_intact() {
+            return DCLErrorTyped::Ok(Repair {
+                verification,
+                kind: RepairKind::NotNeeded,
+            });
+        }
+
+        let mut kind = RepairKind::Full;
+        let mut files = 0;
+        if !full {
+            // Files of "latest" are replaced, no install or prune may run meanwhile
+            let lock = installs::lock_install()?;
+            let repaired = self
+                .download_step
+                .repair_files(channel, &verification)
+                .await;
+            drop(lock);
+            installs::remove_repair_leftovers();
+            match repaired {
+                std::result::Result::Ok(Some(repaired)) => {
+                    kind = RepairKind::Files;
+                    files = repaired;
+                }
+                std::result::Result::Ok(None) => {}
+                Err(_) if self.control.is_cancelled() => {
+                    return Err(flow_control::Cancelled.into());
+                }
+                Err(e) => log::warn!("Cannot repair the files, reinstalling the build: {}", e),
+            }
+        }
+        if kind == RepairKind::Full {
+            let release = verification.release.clone();
+            self.reinstall(channel, state, release, verification.channel.clone())
+                .await?;
+        }
+
+        self.analytics
+            .lock()
+            .await
+            .track_and_flush_silent(Event::INSTALL_VERSION_REPAIRED {
+                version: verification.version.clone(),
+                kind: kind.label().to_owned(),
+                files,
+            })
+            .await;
+        DCLErrorTyped::Ok(Repair { verification, kind })
+    }
+
+    /// Verification of a registered build whose "latest" directory is gone.
+    async fn missing_build(version: String) -> DCLErrorTyped<Verification> {
+        let release_channel =
+            installs::installed_release_channel().unwrap_or_else(ReleaseChannel::selected);
+        let release = installed_release(&release_channel, &version).await?;
+        DCLErrorTyped::Ok(Verification {
+            version,
+            channel: release_channel,
+            report: None,
+            executable_found: false,
+            release,
+            manifest: None,
+        })
+    }
+
+    /// Downloads and installs `release` again, it replaces the installed
+    /// build of the same version.
+    async fn reinstall<T: EventChannel>(
+        &self,
+        channel: &T,
+        state: Arc<Mutex<LaunchFlowState>>,
+        release: ReleaseResponse,
+        release_channel: ReleaseChannel,
+    ) -> DCLErrorResult {
+        let mut guard = state.lock().await;
+        // A patch or a file-level update would reuse the damaged files
+        guard.partial_update_failed_for = Some(release.version.clone());
+        guard.latest_release = Some(release);
+        guard.release_channel = release_channel;
+        drop(guard);
+
+        channel.send(self.download_step.start_label()?)?;
+        self.download_step.execute(channel, state.clone()).await?;
+        self.install_step
+            .execute_if_needed(channel, state, "install")
+            .await?;
+        DCLErrorResult::Ok(())
+    }
+
     async fn prepare_with_retries<T: EventChannel>(
         &self,
         channel: &T,
@@ -149,6 +585,11 @@ impl LaunchFlow {
                 std::result::Result::Ok(handled_by_passthrough) => {
                     return std::result::Result::Ok(handled_by_passthrough);
                 }
+                // Not a failure, nothing is reported
+                std::result::Result::Err(e @ DCLError::E3018_FLOW_CANCELLED(_)) => {
+                    info!("Flow stopped: {:?}", e);
+                    return std::result::Result::Err(FlowError::from(&e));
+                }
                 std::result::Result::Err(e) => {
                     let final_attempt = is_final_attempt(attempt, &e);
                     last_error =
@@ -160,11 +601,11 @@ impl LaunchFlow {
             }
         }
 
-        std::result::Result::Err(FlowError {
-            user_message: last_error
-                .map(|e| e.error.user_message().to_owned())
+        std::result::Result::Err(
+            last_error
+                .map(|e| FlowError::from(&e.error))
                 .unwrap_or_default(),
-        })
+        )
     }
 
     async fn launch_once<T: EventChannel>(
@@ -195,9 +636,7 @@ impl LaunchFlow {
                         sentry::capture_error(&e);
                     },
                 );
-                std::result::Result::Err(FlowError {
-                    user_message: e.user_message().to_owned(),
-                })
+                std::result::Result::Err(FlowError::from(&e))
             }
         }
     }
@@ -266,24 +705,155 @@ impl LaunchFlow {
             return DCLErrorTyped::Ok(true);
         }
 
+        let mode = state.lock().await.mode.clone();
+        match mode {
+            LaunchMode::Latest => {}
+            LaunchMode::DownloadedFile(path) => {
+                info!("Installing {} as the dev build", path.display());
+                state.lock().await.recent_download = Some(RecentDownload {
+                    version: DEV_VERSION.to_owned(),
+                    downloaded_path: path,
+                    kind: DownloadKind::Dev,
+                });
+                self.install_step
+                    .execute_if_needed(channel, state.clone(), "install")
+                    .await?;
+                return DCLErrorTyped::Ok(false);
+            }
+            LaunchMode::Dev | LaunchMode::Installed(_) => {
+                info!("Launching an installed build, skipping the update");
+                return DCLErrorTyped::Ok(false);
+            }
+        }
+
         self.fetch_step
             .execute_if_needed(channel, state.clone(), "fetch")
             .await?;
         self.download_step
             .execute_if_needed(channel, state.clone(), "download")
             .await?;
-        self.install_step
+        let installed = self
+            .install_step
             .execute_if_needed(channel, state.clone(), "install")
-            .await?;
+            .await;
+        if let Err(
+            DCLError::E3014_PATCH_APPLY_FAILED { .. }
+            | DCLError::E3015_INCREMENTAL_UPDATE_FAILED { .. },
+        ) = &installed
+        {
+            // The failed version is recorded in the state, so this time the
+            // full build is downloaded.
+            info!("Partial update failed, falling back to the full build");
+            self.download_step
+                .execute_if_needed(channel, state.clone(), "download")
+                .await?;
+            self.install_step
+                .execute_if_needed(channel, state.clone(), "install")
+                .await?;
+        } else {
+            installed?;
+        }
 
         DCLErrorTyped::Ok(false)
     }
 }
 
+/// Release of the installed `version`, with the file manifest published
+/// for that version.
+async fn installed_release(
+    channel: &ReleaseChannel,
+    version: &str,
+) -> DCLErrorTyped<ReleaseResponse> {
+    let latest = crate::s3::get_latest_explorer_release(channel).await?;
+    if latest.version == version {
+        return DCLErrorTyped::Ok(latest);
+    }
+    crate::s3::get_explorer_release(channel, version).await
+}
+
 struct FetchStep {
     analytics: Arc<Mutex<Analytics>>,
 }
 
+impl FetchStep {
+    /// The latest release of the channel, unless a version is pinned, the
+    /// channel asks every launcher to roll back or the release is staged and
+    /// this launcher isn't in its cohort.
+    async fn fetch_target_release(
+        &self,
+        channel: &ReleaseChannel,
+    ) -> DCLErrorTyped<ReleaseResponse> {
+        if let Some(pinned) = installs::pinned_version() {
+            info!(
+                "Version {} is pinned, the latest release is not fetched",
+                pinned
+            );
+            return crate::s3::get_explorer_release(channel, &pinned).await;
+        }
+
+        let latest = crate::s3::get_latest_explorer_release(channel).await?;
+        if let Some(target) = &latest.rollback_to
+            && *target != latest.version
+        {
+            log::warn!("Release {} is rolled back to {}", latest.version, target);
+            return crate::s3::get_explorer_release(channel, target).await;
+        }
+
+        let Some(rollout) = &latest.rollout else {
+            return DCLErrorTyped::Ok(latest);
+        };
+        // The "none" fallback id would put every such launcher in one bucket
+        let in_cohort = match crate::config::user_id() {
+            std::result::Result::Ok(user_id) => {
+                let assignment =
+                    RolloutAssignment::new(&user_id, &latest.version, rollout.percentage);
+                self.analytics.lock().await.set_rollout(assignment);
+                info!(
+                    "Release {} is rolled out to {}%, bucket {} is in the {} cohort",
+                    latest.version,
+                    rollout.percentage,
+                    assignment.bucket,
+                    assignment.cohort()
+                );
+                assignment.in_cohort
+            }
+            Err(e) => {
+                log::warn!(
+                    "No user id to place in the rollout of {}, staying on {}: {:#}",
+                    latest.version,
+                    rollout.previous_version,
+                    e
+                );
+                false
+            }
+        };
+
+        // Players who already got the release keep it
+        let installed = installs::installed_latest_version();
+        if in_cohort || installed.as_deref() == Some(latest.version.as_str()) {
+            return DCLErrorTyped::Ok(latest);
+        }
+        crate::s3::get_explorer_release(channel, &rollout.previous_version).await
+    }
+
+    /// A release this launcher can't install only fails the flow when no
+    /// build is installed, a failed self-update shouldn't stop the player
+    /// from launching the build they have. The player is told that the
+    /// launcher needs an update with `LauncherUpdate::Required`.
+    fn keep_installed(release: &ReleaseResponse) -> DCLErrorTyped<bool> {
+        let Err(e) = release.check_launcher_version() else {
+            return DCLErrorTyped::Ok(false);
+        };
+        match installs::installed_latest_version() {
+            Some(installed) => {
+                log::warn!("{}, launching the installed {} instead", e, installed);
+                DCLErrorTyped::Ok(true)
+            }
+            None => Err(e),
+        }
+    }
+}
+
 impl WorkflowStep<LaunchFlowState, ()> for FetchStep {
     async fn is_complete(&self, _state: Arc<Mutex<LaunchFlowState>>) -> Result<bool> {
         // always refetch the origin
@@ -299,16 +869,18 @@ impl WorkflowStep<LaunchFlowState, ()> for FetchStep {
 
     async fn execute<T: EventChannel>(
         &self,
-        _channel: &T,
+        channel: &T,
         state: Arc<Mutex<LaunchFlowState>>,
     ) -> DCLErrorResult {
-        self.analytics
-            .lock()
-            .await
+        let release_channel = ReleaseChannel::selected();
+        let mut analytics = self.analytics.lock().await;
+        analytics.set_release_channel(release_channel.name());
+        analytics
             .track_and_flush_silent(Event::FETCH_VERSION_START)
             .await;
+        drop(analytics);
 
-        let fetch_result = crate::s3::get_latest_explorer_release().await;
+        let fetch_result = self.fetch_target_release(&release_channel).await;
         if let Err(e) = &fetch_result {
             self.analytics
                 .lock()
@@ -320,7 +892,15 @@ impl WorkflowStep<LaunchFlowState, ()> for FetchStep {
         }
         let latest_release = fetch_result?;
         let version = latest_release.version.clone();
-        state.lock().await.latest_release = Some(latest_release);
+        let keep_installed = Self::keep_installed(&latest_release)?;
+        if keep_installed {
+            channel.send(LauncherUpdate::Required.into())?;
+        }
+        let mut guard = state.lock().await;
+        guard.latest_release = Some(latest_release);
+        guard.keep_installed = keep_installed;
+        guard.release_channel = release_channel;
+        drop(guard);
 
         self.analytics
             .lock()
@@ -334,6 +914,7 @@ impl WorkflowStep<LaunchFlowState, ()> for FetchStep {
 
 struct DownloadStep {
     analytics: Arc<Mutex<Analytics>>,
+    control: FlowControl,
 }
 
 impl DownloadStep {
@@ -346,77 +927,297 @@ impl DownloadStep {
         }
     }
 
-    async fn version_from_url(&self, url: &str) -> Result<String> {
-        let pattern = format!(
-            r"(^{}\/{}\/(v?\d+\.\d+\.\d+-?\w*)\/(\w+.zip))",
-            AppEnvironment::bucket_url(),
-            s3::RELEASE_PREFIX
-        );
-        let re = Regex::new(&pattern)?;
+    /// A build kept for rollback is swapped in instead of downloaded again.
+    fn retained_build(version: &str) -> Option<RecentDownload> {
+        if installs::installed_latest_version().as_deref() == Some(version) {
+            return None;
+        }
+        installs::retained_version_path(version).map(|path| RecentDownload {
+            version: version.to_owned(),
+            downloaded_path: path,
+            kind: DownloadKind::Retained,
+        })
+    }
 
-        let captures = re
-            .captures(url)
-            .context(format!("cannot find matches in the url: {}", url))?;
-        let version = captures.get(2).map(|m| m.as_str());
+    /// Fails before anything is downloaded when the build won't fit on disk.
+    /// The whole build is accounted for, even if a partial update is used.
+    /// Only enforced when the manifest publishes the unpacked size, a guess
+    /// would block players who do have the room.
+    async fn check_disk_space(&self, release: &ReleaseResponse) -> DCLErrorResult {
+        let Some(installed_size) = release.installed_size else {
+            log::warn!("Installed size is not published, skipping the free space check");
+            return DCLErrorResult::Ok(());
+        };
+        let download_size = match release.checksum.as_ref().and_then(|c| c.size) {
+            Some(size) => Some(size),
+            None => installs::downloads::remote_size(&release.browser_download_url).await,
+        };
+        let Some(download_size) = download_size else {
+            log::warn!("Download size is unknown, skipping the free space check");
+            return DCLErrorResult::Ok(());
+        };
 
-        match version {
-            Some(v) => Ok(v.to_owned()),
-            None => {
+        let requirement = SpaceRequirement {
+            download_size,
+            installed_size,
+        };
+        let checked = installs::check_disk_space(requirement);
+        if let Err(DCLError::E1009_NOT_ENOUGH_DISK_SPACE {
+            required,
+            available,
+            reclaimable,
+            ..
+        }) = &checked
+        {
+            self.analytics
+                .lock()
+                .await
+                .track_and_flush_silent(Event::DOWNLOAD_VERSION_NOT_ENOUGH_SPACE {
+                    version: release.version.clone(),
+                    required: *required,
+                    available: *available,
+                    reclaimable: *reclaimable,
+                })
+                .await;
+        }
+        checked
+    }
+
+    /// Returns `None` when the patch cannot be downloaded, the caller falls
+    /// back to the full build.
+    async fn download_patch<T: EventChannel>(
+        &self,
+        channel: &T,
+        mode: &BuildType,
+        version: &str,
+        patch: &ReleasePatch,
+    ) -> Option<RecentDownload> {
+        self.analytics
+            .lock()
+            .await
+            .track_and_flush_silent(Event::DOWNLOAD_VERSION_PATCH {
+                version: version.to_owned(),
+                from_version: patch.from_version.clone(),
+            })
+            .await;
+
+        let target_path = installs::patch_download_path();
+        let result = installs::downloads::download_file(
+            &patch.url,
+            target_path.to_str()?,
+            channel,
+            mode,
+            Some(&patch.checksum),
+            self.analytics.clone(),
+            &self.control,
+        )
+        .await;
+
+        match result {
+            std::result::Result::Ok(()) => Some(RecentDownload {
+                version: version.to_owned(),
+                downloaded_path: target_path,
+                kind: DownloadKind::Patch {
+                    from_version: patch.from_version.clone(),
+                },
+            }),
+            Err(_) if self.control.is_cancelled() => None,
+            Err(e) => {
+                log::warn!(
+                    "Cannot download the patch, falling back to the full build: {}",
+                    e
+                );
                 self.analytics
                     .lock()
                     .await
-                    .track_and_flush_silent(Event::DOWNLOAD_VERSION_ERROR {
-                        version: None,
-                        error: "No version provided".to_owned(),
+                    .track_and_flush_silent(Event::INSTALL_VERSION_PATCH_FALLBACK {
+                        version: version.to_owned(),
+                        from_version: patch.from_version.clone(),
+                        error: e.to_string(),
                     })
                     .await;
-                Err(anyhow!("url doesn't contain version"))
+                None
             }
         }
     }
-}
 
-impl WorkflowStep<LaunchFlowState, ()> for DownloadStep {
-    async fn is_complete(&self, state: Arc<Mutex<LaunchFlowState>>) -> Result<bool> {
-        let guard = state.lock().await;
-        match &guard.latest_release {
-            Some(release) => {
-                let version = release.version.as_str();
-                let updated = crate::installs::is_explorer_updated(version);
-                Ok(updated)
+    /// Downloads the file manifest and the files that differ from the
+    /// installed version. Returns `None` on failure, the caller falls back to
+    /// the full build.
+    async fn download_files<T: EventChannel>(
+        &self,
+        channel: &T,
+        mode: &BuildType,
+        version: &str,
+        file_manifest: &ReleaseFileManifest,
+    ) -> Option<RecentDownload> {
+        let manifest_path = installs::file_manifest_download_path();
+        let result = self
+            .download_files_internal(channel, mode, version, file_manifest, &manifest_path)
+            .await;
+
+        match result {
+            std::result::Result::Ok(()) => Some(RecentDownload {
+                version: version.to_owned(),
+                downloaded_path: manifest_path,
+                kind: DownloadKind::Files,
+            }),
+            Err(_) if self.control.is_cancelled() => None,
+            Err(e) => {
+                log::warn!(
+                    "Cannot download the files, falling back to the full build: {}",
+                    e
+                );
+                self.analytics
+                    .lock()
+                    .await
+                    .track_and_flush_silent(Event::INSTALL_VERSION_FILES_FALLBACK {
+                        version: version.to_owned(),
+                        error: e.to_string(),
+                    })
+                    .await;
+                None
             }
-            None => Err(anyhow!("Latest release is not found in the state")),
         }
     }
 
-    async fn on_skipped(&self, state: Arc<Mutex<LaunchFlowState>>) {
-        let version = state
+    async fn download_files_internal<T: EventChannel>(
+        &self,
+        channel: &T,
+        mode: &BuildType,
+        version: &str,
+        file_manifest: &ReleaseFileManifest,
+        manifest_path: &Path,
+    ) -> std::result::Result<(), IncrementalError> {
+        let manifest = self
+            .fetch_file_manifest(channel, mode, version, file_manifest, manifest_path)
+            .await?;
+
+        let store_path = installs::file_store_path();
+        let missing = incremental::missing_files(
+            &manifest,
+            &installs::explorer_latest_version_path(),
+            &store_path,
+        )?;
+        self.analytics
             .lock()
             .await
-            .latest_release
-            .as_ref()
-            .map(|r| r.version.clone());
-        if let Some(version) = version {
-            self.analytics
-                .lock()
-                .await
-                .track_and_flush_silent(Event::DOWNLOAD_VERSION_SKIPPED { version })
-                .await;
+            .track_and_flush_silent(Event::DOWNLOAD_VERSION_FILES {
+                version: version.to_owned(),
+                files_total: manifest.files.len() as u64,
+                files_missing: missing.len() as u64,
+                size_missing: missing.iter().map(|f| f.size).sum(),
+            })
+            .await;
+
+        incremental::download_files(
+            &missing,
+            &file_manifest.files_base_url,
+            &store_path,
+            channel,
+            mode,
+            self.analytics.clone(),
+            &self.control,
+        )
+        .await
+    }
+
+    /// Downloads the file manifest of `version` to `manifest_path`.
+    async fn fetch_file_manifest<T: EventChannel>(
+        &self,
+        channel: &T,
+        mode: &BuildType,
+        version: &str,
+        file_manifest: &ReleaseFileManifest,
+        manifest_path: &Path,
+    ) -> std::result::Result<FileManifest, IncrementalError> {
+        installs::downloads::download_file(
+            &file_manifest.url,
+            &manifest_path.to_string_lossy(),
+            channel,
+            mode,
+            Some(&file_manifest.checksum),
+            self.analytics.clone(),
+            &self.control,
+        )
+        .await?;
+
+        let manifest = FileManifest::load(manifest_path)?;
+        if manifest.version != version {
+            return Err(IncrementalError::Malformed(format!(
+                "manifest is for {} instead of {}",
+                manifest.version, version
+            )));
         }
+        std::result::Result::Ok(manifest)
     }
 
-    fn start_label(&self) -> Result<Status> {
-        let mode = Self::mode();
-        let status = Status::State {
-            step: Step::Downloading {
-                progress: 0,
-                build_type: mode,
+    /// Downloads the damaged files of `verification` and replaces them, with
+    /// the install lock held. `None` when the release publishes no file
+    /// manifest.
+    async fn repair_files<T: EventChannel>(
+        &self,
+        channel: &T,
+        verification: &Verification,
+    ) -> std::result::Result<Option<u64>, IncrementalError> {
+        let (Some(manifest), Some(report), Some(file_manifest)) = (
+            &verification.manifest,
+            &verification.report,
+            &verification.release.file_manifest,
+        ) else {
+            return std::result::Result::Ok(None);
+        };
+
+        let store_path = installs::repair_store_path();
+        let damaged = report.damaged_files(manifest);
+        incremental::download_files(
+            &damaged,
+            &file_manifest.files_base_url,
+            &store_path,
+            channel,
+            &BuildType::Update,
+            self.analytics.clone(),
+            &self.control,
+        )
+        .await?;
+
+        let latest_path = installs::explorer_latest_version_path();
+        installs::verify::repair(manifest, report, &latest_path, &store_path)?;
+        let repaired = report.missing.len().saturating_add(report.modified.len());
+        std::result::Result::Ok(Some(repaired as u64))
+    }
+
+    async fn track_full_download(&self, version: &str, result: &DownloadFileResult) {
+        let mut analytics = self.analytics.lock().await;
+        if let Err(DownloadFileError::ChecksumMismatch {
+            expected, actual, ..
+        }) = result
+        {
+            analytics
+                .track_and_flush_silent(Event::DOWNLOAD_VERSION_CHECKSUM_MISMATCH {
+                    version: version.to_owned(),
+                    expected: expected.clone(),
+                    actual: actual.clone(),
+                })
+                .await;
+        }
+
+        let event = match result {
+            // Tracked by `execute`, the same as cancelled partial updates
+            Err(DownloadFileError::Cancelled(_)) => return,
+            Err(e) => Event::DOWNLOAD_VERSION_ERROR {
+                version: Some(version.to_owned()),
+                error: e.to_string(),
+            },
+            std::result::Result::Ok(()) => Event::DOWNLOAD_VERSION_SUCCESS {
+                version: version.to_owned(),
+                download_rate_limit: installs::downloads::download_rate_limit(),
             },
         };
-        Ok(status)
+        analytics.track_and_flush_silent(event).await;
     }
 
-    async fn execute<T: EventChannel>(
+    async fn download_release<T: EventChannel>(
         &self,
         channel: &T,
         state: Arc<Mutex<LaunchFlowState>>,
@@ -428,9 +1229,41 @@ impl WorkflowStep<LaunchFlowState, ()> for DownloadStep {
         let release = &guard.latest_release;
         match release {
             Some(r) => {
+                if let Some(download) = Self::retained_build(&r.version) {
+                    guard.recent_download = Some(download);
+                    return DCLErrorResult::Ok(());
+                }
+                self.check_disk_space(r).await?;
+
                 let url = &r.browser_download_url;
-                let version = self.version_from_url(url).await?;
+                let version = r.version.clone();
+
+                let installed_version = guard.partial_update_base(&version);
+                let release_patch = installed_version
+                    .as_deref()
+                    .and_then(|installed| r.patch_from(installed))
+                    .cloned();
+                if let Some(release_patch) = release_patch
+                    && let Some(download) = self
+                        .download_patch(channel, &mode, &version, &release_patch)
+                        .await
+                {
+                    guard.recent_download = Some(download);
+                    return DCLErrorResult::Ok(());
+                }
 
+                self.control.check()?;
+                let file_manifest = installed_version.and(r.file_manifest.clone());
+                if let Some(file_manifest) = file_manifest
+                    && let Some(download) = self
+                        .download_files(channel, &mode, &version, &file_manifest)
+                        .await
+                {
+                    guard.recent_download = Some(download);
+                    return DCLErrorResult::Ok(());
+                }
+
+                self.control.check()?;
                 let target_path = installs::target_download_path();
                 let path: &str = target_path
                     .to_str()
@@ -442,6 +1275,7 @@ impl WorkflowStep<LaunchFlowState, ()> for DownloadStep {
                         .await
                         .track_and_flush_silent(Event::DOWNLOAD_VERSION {
                             version: version.clone(),
+                            download_rate_limit: installs::downloads::download_rate_limit(),
                         })
                         .await;
                 }
@@ -451,33 +1285,19 @@ impl WorkflowStep<LaunchFlowState, ()> for DownloadStep {
                     path,
                     channel,
                     &mode,
+                    r.checksum.as_ref(),
                     self.analytics.clone(),
+                    &self.control,
                 )
                 .await;
 
-                if let Err(e) = &result {
-                    self.analytics
-                        .lock()
-                        .await
-                        .track_and_flush_silent(Event::DOWNLOAD_VERSION_ERROR {
-                            version: Some(version.clone()),
-                            error: e.to_string(),
-                        })
-                        .await;
-                } else {
-                    self.analytics
-                        .lock()
-                        .await
-                        .track_and_flush_silent(Event::DOWNLOAD_VERSION_SUCCESS {
-                            version: version.clone(),
-                        })
-                        .await;
-                }
+                self.track_full_download(&version, &result).await;
                 result?;
 
                 guard.recent_download = Some(RecentDownload {
                     version,
                     downloaded_path: target_path,
+                    kind: DownloadKind::Full,
                 });
 
                 DCLErrorResult::Ok(())
@@ -487,19 +1307,209 @@ impl WorkflowStep<LaunchFlowState, ()> for DownloadStep {
     }
 }
 
+impl WorkflowStep<LaunchFlowState, ()> for DownloadStep {
+    async fn is_complete(&self, state: Arc<Mutex<LaunchFlowState>>) -> Result<bool> {
+        let guard = state.lock().await;
+        if guard.keep_installed {
+            return Ok(true);
+        }
+        match &guard.latest_release {
+            Some(release) => {
+                let version = release.version.as_str();
+                let updated = crate::installs::is_explorer_updated(version, &guard.release_channel);
+                Ok(updated)
+            }
+            None => Err(anyhow!("Latest release is not found in the state")),
+        }
+    }
+
+    async fn on_skipped(&self, state: Arc<Mutex<LaunchFlowState>>) {
+        let version = state
+            .lock()
+            .await
+            .latest_release
+            .as_ref()
+            .map(|r| r.version.clone());
+        if let Some(version) = version {
+            self.analytics
+                .lock()
+                .await
+                .track_and_flush_silent(Event::DOWNLOAD_VERSION_SKIPPED { version })
+                .await;
+        }
+    }
+
+    fn start_label(&self) -> Result<Status> {
+        let mode = Self::mode();
+        let status = Status::State {
+            step: Step::Downloading {
+                progress: 0,
+                build_type: mode,
+            },
+        };
+        Ok(status)
+    }
+
+    async fn execute<T: EventChannel>(
+        &self,
+        channel: &T,
+        state: Arc<Mutex<LaunchFlowState>>,
+    ) -> DCLErrorResult {
+        let result = self.download_release(channel, state.clone()).await;
+        if let Err(DCLError::E3018_FLOW_CANCELLED(_)) = &result {
+            let version = state
+                .lock()
+                .await
+                .latest_release
+                .as_ref()
+                .map(|r| r.version.clone());
+            if let Some(version) = version {
+                self.analytics
+                    .lock()
+                    .await
+                    .track_and_flush_silent(Event::DOWNLOAD_VERSION_CANCELLED { version })
+                    .await;
+            }
+        }
+        result
+    }
+}
+
+const INSTALL_PROGRESS_REPORT_STEP: u8 = 25;
+
+async fn track_install_progress(
+    analytics: Arc<Mutex<Analytics>>,
+    version: String,
+    progress: ExtractProgress,
+) {
+    let progress_event = Event::INSTALL_VERSION_PROGRESS {
+        version,
+        size_extracted: progress.bytes_processed,
+        size_remaining: progress
+            .total_bytes
+            .saturating_sub(progress.bytes_processed),
+        entries_extracted: progress.entries_processed,
+    };
+    analytics
+        .lock()
+        .await
+        .track_and_flush_silent(progress_event)
+        .await;
+}
+
+/// Installs what was downloaded and switches "latest" to it, blocking.
+fn install_download(
+    recent_download: RecentDownload,
+    release_channel: &ReleaseChannel,
+    source: Option<BuildSource>,
+    on_progress: &mut dyn FnMut(ExtractProgress),
+) -> DCLErrorResult {
+    let version = recent_download.version;
+    let installed = match &recent_download.kind {
+        DownloadKind::Patch { from_version } => installs::install_explorer_from_patch(
+            &version,
+            from_version,
+            &recent_download.downloaded_path,
+            on_progress,
+        ),
+        DownloadKind::Files => installs::install_explorer_from_files(
+            &version,
+            &recent_download.downloaded_path,
+            on_progress,
+        ),
+        DownloadKind::Full => {
+            installs::install_explorer(&version, Some(recent_download.downloaded_path), on_progress)
+        }
+        DownloadKind::Dev => {
+            installs::install_dev_explorer(&recent_download.downloaded_path, on_progress)
+        }
+        DownloadKind::Retained => installs::rollback(Some(&version)).map(|_| ()),
+    };
+    // The dev build lives next to "latest" and leaves it alone, a
+    // retained build is already swapped in
+    installed.and_then(|()| match recent_download.kind {
+        DownloadKind::Dev => DCLErrorResult::Ok(()),
+        DownloadKind::Retained => installs::record_installed_build(release_channel, None),
+        _ => installs::rename_explorer_to_latest()
+            .and_then(|()| installs::record_installed_build(release_channel, source)),
+    })
+}
+
 struct InstallStep {
     analytics: Arc<Mutex<Analytics>>,
     running_instances: Arc<Mutex<RunningInstances>>,
+    control: FlowControl,
 }
 
 impl InstallStep {
-    async fn execute_internal(&self, recent_download: RecentDownload) -> DCLErrorResult {
+    async fn execute_internal<T: EventChannel>(
+        &self,
+        recent_download: RecentDownload,
+        release_channel: &ReleaseChannel,
+        source: Option<BuildSource>,
+        channel: &T,
+    ) -> DCLErrorResult {
         self.check_explorer_not_running().await?;
-        installs::install_explorer(
-            &recent_download.version,
-            Some(recent_download.downloaded_path),
-        )
-        .and_then(|()| installs::rename_explorer_to_latest())
+        let lock = installs::lock_install()?;
+
+        let build_type = DownloadStep::mode();
+        let version = recent_download.version.clone();
+        let release_channel = release_channel.clone();
+        let (progress_sender, mut progress_receiver) = tokio::sync::mpsc::unbounded_channel();
+        // Unpacking takes minutes of blocking IO, it runs off the async
+        // workers and reports its progress back here. The task holds the
+        // lock until "latest" is switched, even if this future is dropped.
+        let install = tokio::task::spawn_blocking(move || {
+            let _lock = lock;
+            let mut on_progress = |progress: ExtractProgress| {
+                // Only fails once this future is dropped
+                let _ = progress_sender.send(progress);
+            };
+            install_download(recent_download, &release_channel, source, &mut on_progress)
+        });
+
+        let mut last_sent: Option<u8> = None;
+        // Same as downloads: only every INSTALL_PROGRESS_REPORT_STEP percent
+        // goes to analytics, the UI gets every percent change.
+        let mut next_report_at: u8 = INSTALL_PROGRESS_REPORT_STEP;
+        let mut tasks = Vec::new();
+        while let Some(progress) = progress_receiver.recv().await {
+            let percent = progress.percent();
+            if last_sent.is_some_and(|sent| sent >= percent) {
+                continue;
+            }
+            last_sent = Some(percent);
+
+            let event = Status::State {
+                step: Step::Installing {
+                    progress: percent,
+                    build_type: build_type.clone(),
+                },
+            };
+            if let Err(e) = channel.send(event) {
+                log::error!("Cannot send install progress to channel: {:#}", e);
+            }
+
+            if next_report_at < 100 && percent >= next_report_at {
+                next_report_at = next_report_at.saturating_add(INSTALL_PROGRESS_REPORT_STEP);
+                tasks.push(tokio::spawn(track_install_progress(
+                    self.analytics.clone(),
+                    version.clone(),
+                    progress,
+                )));
+            }
+        }
+        let result = install
+            .await
+            .unwrap_or_else(|e| Err(anyhow!("Install task failed: {e}").into()));
+
+        for task in tasks {
+            if let Err(e) = task.await {
+                log::error!("Failed to await analytics task: {:#}", e);
+            }
+        }
+
+        result
     }
 
     async fn check_explorer_not_running(&self) -> DCLErrorResult {
@@ -558,20 +1568,34 @@ impl WorkflowStep<LaunchFlowState, ()> for InstallStep {
     fn start_label(&self) -> Result<Status> {
         let mode = DownloadStep::mode();
         let status = Status::State {
-            step: Step::Installing { build_type: mode },
+            step: Step::Installing {
+                progress: 0,
+                build_type: mode,
+            },
         };
         Ok(status)
     }
 
     async fn execute<T: EventChannel>(
         &self,
-        _channel: &T,
+        channel: &T,
         state: Arc<Mutex<LaunchFlowState>>,
     ) -> DCLErrorResult {
-        let recent_download = Self::recent_download_and_update_state(state).await;
+        // Unpacking isn't interrupted once started, a cancel only stops it
+        // from starting.
+        self.control.check()?;
+        let recent_download = Self::recent_download_and_update_state(state.clone()).await;
+        let guard = state.lock().await;
+        let release_channel = guard.release_channel.clone();
+        let source = recent_download
+            .as_ref()
+            .zip(guard.latest_release.as_ref())
+            .and_then(|(download, release)| download.source(release));
+        drop(guard);
 
         if let Some(download) = recent_download {
             let version = download.version.clone();
+            let kind = download.kind.clone();
             self.analytics
                 .lock()
                 .await
@@ -579,7 +1603,39 @@ impl WorkflowStep<LaunchFlowState, ()> for InstallStep {
                     version: version.clone(),
                 })
                 .await;
-            let result = self.execute_internal(download).await;
+            let result = self
+                .execute_internal(download, &release_channel, source, channel)
+                .await;
+            let fallback_event = match (&result, kind) {
+                (
+                    Err(DCLError::E3014_PATCH_APPLY_FAILED { error, .. }),
+                    DownloadKind::Patch { from_version },
+                ) => {
+                    log::warn!("Cannot apply the patch: {}", error);
+                    Some(Event::INSTALL_VERSION_PATCH_FALLBACK {
+                        version: version.clone(),
+                        from_version,
+                        error: error.to_string(),
+                    })
+                }
+                (Err(DCLError::E3015_INCREMENTAL_UPDATE_FAILED { error }), DownloadKind::Files) => {
+                    log::warn!("Cannot assemble the file-level update: {}", error);
+                    Some(Event::INSTALL_VERSION_FILES_FALLBACK {
+                        version: version.clone(),
+                        error: error.to_string(),
+                    })
+                }
+                _ => None,
+            };
+            if let Some(event) = fallback_event {
+                state.lock().await.partial_update_failed_for = Some(version);
+                self.analytics
+                    .lock()
+                    .await
+                    .track_and_flush_silent(event)
+                    .await;
+                return result;
+            }
             if let Err(e) = &result {
                 self.analytics
                     .lock()
@@ -688,8 +1744,9 @@ impl WorkflowStep<LaunchFlowState, ()> for AppLaunchStep {
     async fn execute<T: EventChannel>(
         &self,
         channel: &T,
-        _state: Arc<Mutex<LaunchFlowState>>,
+        state: Arc<Mutex<LaunchFlowState>>,
     ) -> DCLErrorResult {
+        let version = state.lock().await.mode.launch_version().map(str::to_owned);
         match Protocol::value() {
             Some(deeplink) => {
                 if self.should_use_deeplink_bridge_for(&deeplink).await? {
@@ -698,17 +1755,16 @@ impl WorkflowStep<LaunchFlowState, ()> for AppLaunchStep {
                     self.installs_hub
                         .lock()
                         .await
-                        .launch_explorer(Some(deeplink), None)
+                        .launch_explorer(Some(deeplink), version.as_deref())
                         .await?;
                     DCLErrorResult::Ok(())
                 }
             }
             None => {
-                //TODO passed version if specified manually from upper flow
                 self.installs_hub
                     .lock()
                     .await
-                    .launch_explorer(None, None)
+                    .launch_explorer(None, version.as_deref())
                     .await?;
                 DCLErrorResult::Ok(())
             }
@@ -729,10 +1785,53 @@ mod tests {
         assert!(!is_retryable_error(&DCLError::E3001_OPEN_DEEPLINK_TIMEOUT));
     }
 
+    // Retrying can't free disk space, the user has to.
+    #[test]
+    fn not_enough_disk_space_is_not_retryable() {
+        let error = DCLError::from_disk_space(10 * 1024 * 1024, 1024 * 1024, 0);
+        assert!(!is_retryable_error(&error));
+        assert!(error.user_message().contains("at least 9.0 MB"));
+        assert!(!FlowError::from(&error).can_free_space);
+    }
+
+    // The error screen offers a repair when the installed build is broken.
+    #[test]
+    fn broken_install_can_be_repaired() {
+        let error = DCLError::E3009_EXPLORER_NOT_INSTALLED {
+            expected_path: "latest".to_owned(),
+            version: None,
+        };
+        assert!(FlowError::from(&error).can_repair);
+        assert!(!FlowError::from(&DCLError::E3003_CANT_GET_VERSION).can_repair);
+    }
+
+    // Only a launcher update can help, see `ReleaseResponse::check_launcher_version`.
+    #[test]
+    fn launcher_update_required_is_not_retryable() {
+        let error = DCLError::E2009_LAUNCHER_UPDATE_REQUIRED {
+            version: "v2.0.0".to_owned(),
+            launcher_version: "1.0.0".to_owned(),
+            min_launcher_version: "1.5.0".to_owned(),
+        };
+        assert!(!is_retryable_error(&error));
+    }
+
+    #[test]
+    fn cancelled_flow_is_not_retried() {
+        let error = DCLError::from(crate::flow_control::Cancelled);
+        assert!(!is_retryable_error(&error));
+        assert!(is_final_attempt(1, &error));
+    }
+
     #[rstest]
     #[case(DCLError::E3003_CANT_GET_VERSION)]
     #[case(DCLError::E3004_CANT_RENAME_LATEST)]
     #[case(DCLError::E2006_DOWNLOAD_FAILED_NETWORK_TIMEOUT)]
+    #[case(DCLError::E2007_DOWNLOAD_CHECKSUM_MISMATCH {
+        url: String::new(),
+        expected: String::new(),
+        actual: String::new(),
+    })]
     fn other_errors_stay_retryable(#[case] error: DCLError) {
         assert!(is_retryable_error(&error));
     }
@@ -762,39 +1861,29 @@ mod tests {
     fn silent_attempts_budget_is_three() {
         assert_eq!(SILENT_ATTEMPTS_COUNT, 3);
     }
-}
-
-/*
-
-//TODO handle fork flow:
-//  useEffect(() => {
-    const fetchReleaseData = async () => {
-      if (!initialized.current) {
-        initialized.current = true;
-        // When running with the param --downloadedfilepath={{PATH}}, skip the download step and try to install the .zip provided
-        if (customDownloadedFilePath) {
-          handleInstall('dev', customDownloadedFilePath);
-        }
-        // When running with the param --version=dev, skip all the checks and launch the app
-        else if (shouldRunDevVersion) {
-          handleLaunch();
-        }
-        // Fetch the latest available version of Decentraland from the github repo releases
-        else {
-          await handleFetch();
-        }
-      }
-    };
-
-    fetchReleaseData();
-  }, []);
-
-
-  const [retry, setRetry] = useState(0);
-  const [error, setError] = useState<string | undefined>(undefined);
-
-// TODO catch these 2 params
-  const shouldRunDevVersion = getRunDevVersion();
-  const customDownloadedFilePath = getDownloadedFilePath();
-*/
 
+    #[rstest]
+    #[case(&["app"], LaunchMode::Latest)]
+    #[case(&["app", "decentraland://?position=0,0"], LaunchMode::Latest)]
+    #[case(&["app", "--version=dev"], LaunchMode::Dev)]
+    #[case(&["app", "--dev"], LaunchMode::Dev)]
+    #[case(&["app", "--version=v1.2.3"], LaunchMode::Installed("v1.2.3".to_owned()))]
+    #[case(&["app", "--version=latest"], LaunchMode::Latest)]
+    #[case(
+        &["app", "--downloadedfilepath=/tmp/build.zip", "--version=v1.2.3"],
+        LaunchMode::DownloadedFile(PathBuf::from("/tmp/build.zip"))
+    )]
+    fn launch_mode_from_args(#[case] args: &[&str], #[case] expected: LaunchMode) {
+        let args = utils::parse_args(args.iter().map(|a| (*a).to_owned()));
+        let mode = LaunchMode::from_args(&args);
+        assert_eq!(mode, expected);
+        assert_eq!(
+            mode.launch_version(),
+            match &expected {
+                LaunchMode::Latest => None,
+                LaunchMode::Installed(version) => Some(version.as_str()),
+                LaunchMode::Dev | LaunchMode::DownloadedFile(_) => Some(DEV_VERSION),
+            }
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! Cancel, pause and resume of the launch flow, requested by the UI while the
+//! flow is running.
+//!
+//! Downloads check the control between received chunks: a pause closes the
+//! connection until the flow is resumed, the download then continues with a
+//! `Range` request. A cancel fails the download and removes the partial file.
+//! The install step checks it before unpacking, an unpacking already in
+//! progress is completed so that no half installed build is left behind.
+
+use std::sync::{Arc, Mutex};
+
+use log::{error, info};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("cancelled by the user")]
+pub struct Cancelled;
+
+/// Shared by the app and the running flow, clones control the same flow.
+#[derive(Clone)]
+pub struct FlowControl {
+    token: Arc<Mutex<CancellationToken>>,
+    paused: Arc<watch::Sender<bool>>,
+}
+
+impl Default for FlowControl {
+    fn default() -> Self {
+        Self {
+            token: Arc::default(),
+            paused: Arc::new(watch::Sender::new(false)),
+        }
+    }
+}
+
+impl FlowControl {
+    /// Starts a new run, unpaused. The token of a cancelled run is replaced,
+    /// so a retry isn't cancelled right away.
+    pub fn begin(&self) {
+        self.paused.send_replace(false);
+        match self.token.lock() {
+            Ok(mut token) => {
+                if token.is_cancelled() {
+                    *token = CancellationToken::new();
+                }
+            }
+            Err(e) => error!("cannot acquire mutex of flow control token: {}", e),
+        }
+    }
+
+    /// Token of the current run.
+    pub fn token(&self) -> CancellationToken {
+        match self.token.lock() {
+            Ok(token) => token.clone(),
+            Err(e) => {
+                error!("cannot acquire mutex of flow control token: {}", e);
+                CancellationToken::new()
+            }
+        }
+    }
+
+    pub fn cancel(&self) {
+        info!("Flow cancelled");
+        self.token().cancel();
+    }
+
+    pub fn pause(&self) {
+        info!("Flow paused");
+        self.paused.send_replace(true);
+    }
+
+    pub fn resume(&self) {
+        info!("Flow resumed");
+        self.paused.send_replace(false);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token().is_cancelled()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// `Err` once cancelled.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Runs `future` until the current run is cancelled.
+    pub async fn until_cancelled<F: Future>(&self, future: F) -> Result<F::Output, Cancelled> {
+        self.token()
+            .run_until_cancelled(future)
+            .await
+            .ok_or(Cancelled)
+    }
+
+    /// Waits while the flow is paused. `Err` once cancelled, also while
+    /// paused.
+    pub async fn checkpoint(&self) -> Result<(), Cancelled> {
+        self.check()?;
+        if !self.is_paused() {
+            return Ok(());
+        }
+
+        let token = self.token();
+        let mut paused = self.paused.subscribe();
+        tokio::select! {
+            biased;
+            () = token.cancelled() => Err(Cancelled),
+            _ = paused.wait_for(|paused| !paused) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    #[tokio::test]
+    async fn pause_waits_until_resumed_or_cancelled() {
+        let control = FlowControl::default();
+        control.begin();
+        assert_eq!(control.checkpoint().await, Ok(()));
+
+        control.pause();
+        let waiting = timeout(Duration::from_millis(50), control.checkpoint()).await;
+        assert!(waiting.is_err());
+
+        let resumer = control.clone();
+        tokio::spawn(async move { resumer.resume() });
+        assert_eq!(control.checkpoint().await, Ok(()));
+
+        control.pause();
+        let canceller = control.clone();
+        tokio::spawn(async move { canceller.cancel() });
+        assert_eq!(control.checkpoint().await, Err(Cancelled));
+    }
+
+    #[test]
+    fn new_run_is_not_cancelled() {
+        let control = FlowControl::default();
+        control.begin();
+        let first = control.token();
+        control.pause();
+        control.cancel();
+        assert!(first.is_cancelled());
+
+        control.begin();
+        assert!(!control.token().is_cancelled());
+        assert!(!control.is_paused());
+        assert_eq!(control.check(), Ok(()));
+    }
+}
@@ -8,15 +8,16 @@ use crate::download_origin_metadata::startup_location_storage::StartupDeeplinkSt
 use crate::environment::AppEnvironment;
 use crate::errors::{DCLError, DCLErrorResult, DCLErrorTyped};
 use crate::instances::RunningInstances;
-#[cfg(target_os = "windows")]
+#[cfg(any(target_os = "windows", target_os = "linux"))]
 use crate::processes::CommandExtDetached;
 use crate::protocols::DeepLink;
+use crate::release_channel::ReleaseChannel;
 use anyhow::{Context, Result, anyhow};
 use semver::Version;
-use serde_json::{Map, Value};
 use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Display;
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
@@ -24,7 +25,7 @@ use std::sync::Arc;
 use std::{fs, fs::create_dir_all};
 use tokio::sync::Mutex;
 
-#[cfg(target_os = "macos")]
+#[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
 #[cfg(windows)]
@@ -33,14 +34,29 @@ use std::os::windows::process::ExitStatusExt;
 use std::process::ExitStatus;
 #[cfg(windows)]
 use std::thread;
-#[cfg(windows)]
+#[cfg(any(windows, target_os = "linux"))]
 use std::time::Duration;
 
 pub mod compression;
+pub mod disk_space;
 pub mod downloads;
+pub mod incremental;
+pub mod journal;
+pub mod patches;
+pub mod registry;
+pub mod verify;
+
+use journal::{InstallJournal, InstallLock, InstallPhase};
+use registry::{InstallRegistry, InstalledBuild};
 
 const APP_NAME: &str = "DecentralandLauncherLight";
 const EXPLORER_DOWNLOADED_FILENAME: &str = "decentraland.zip";
+const EXPLORER_PATCH_FILENAME: &str = "decentraland.patch";
+const EXPLORER_FILE_MANIFEST_FILENAME: &str = "files.json";
+const EXPLORER_FILE_STORE_DIRNAME: &str = "files";
+const EXPLORER_REPAIR_MANIFEST_FILENAME: &str = "repair-files.json";
+const EXPLORER_REPAIR_STORE_DIRNAME: &str = "repair-files";
+const EXPLORER_LATEST_DIRNAME: &str = "latest";
 
 #[cfg(target_os = "macos")]
 pub const EXPLORER_MAC_APP_NAME: &str = "Decentraland";
@@ -50,6 +66,9 @@ const EXPLORER_MAC_APP_PATH: &str = concat!("Decentraland", ".app");
 #[cfg(target_os = "windows")]
 const EXPLORER_WIN_BIN_PATH: &str = "Decentraland.exe";
 
+#[cfg(target_os = "linux")]
+const EXPLORER_LINUX_BIN_PATH: &str = "Decentraland.x86_64";
+
 pub fn log_file_path() -> Result<PathBuf> {
     let mut path = PathBuf::new();
     if let Some(dir) = dirs::home_dir() {
@@ -65,6 +84,12 @@ pub fn log_file_path() -> Result<PathBuf> {
         let dir = std::env::var("APPDATA")?;
         path.push(dir);
     }
+    #[cfg(target_os = "linux")]
+    {
+        // $XDG_STATE_HOME, ~/.local/state by default
+        let dir = dirs::state_dir().context("Cannot resolve the XDG state directory")?;
+        path.push(dir);
+    }
 
     path.push(APP_NAME);
     fs::create_dir_all(&path)?;
@@ -145,59 +170,46 @@ fn explorer_downloads_path() -> PathBuf {
     dir
 }
 
-fn explorer_version_path() -> PathBuf {
-    explorer_path().join("version.json")
-}
-
 pub fn explorer_latest_version_path() -> PathBuf {
-    explorer_path().join("latest")
+    explorer_path().join(EXPLORER_LATEST_DIRNAME)
 }
 
 fn explorer_dev_version_path() -> PathBuf {
     explorer_path().join("dev")
 }
 
-fn get_version_data() -> Result<Map<String, Value>> {
-    let path = explorer_version_path();
-    if path.exists() {
-        let data = fs::read_to_string(path).context("Failed to read version.json")?;
-        let value =
-            serde_json::from_str::<serde_json::Value>(&data).context("Failed to parse JSON")?;
-
-        return match value {
-            Value::Object(obj) => Ok(obj),
-            _ => return Err(anyhow!("Expected JSON object")),
-        };
-    }
-
-    Err(anyhow!(format!(
-        "File doesn't exists: {}",
-        path.to_str().unwrap_or("no path")
-    )))
+fn get_registry() -> DCLErrorTyped<InstallRegistry> {
+    InstallRegistry::load(&explorer_path()).ok_or(DCLError::E3003_CANT_GET_VERSION)
 }
 
-fn get_version_data_or_empty() -> Map<String, Value> {
-    get_version_data().unwrap_or_else(|_e| {
-        log::error!("Cannot get version data, fallback to new empty");
+fn get_registry_or_empty() -> InstallRegistry {
+    get_registry().unwrap_or_else(|_e| {
+        log::error!("Cannot get the install registry, fallback to new empty");
 
-        Map::new()
+        InstallRegistry::default()
     })
 }
 
-fn get_latest_version(version_data: &Map<String, Value>) -> DCLErrorTyped<&str> {
-    version_data
-        .get("version")
-        .and_then(|v| v.as_str())
+fn get_latest_version(registry: &InstallRegistry) -> DCLErrorTyped<&str> {
+    registry
+        .current
+        .as_deref()
         .ok_or(DCLError::E3003_CANT_GET_VERSION)
 }
 
+fn write_registry(registry: &InstallRegistry) -> DCLErrorResult {
+    registry
+        .write(&explorer_path())
+        .map_err(|source| DCLError::E3007_VERSION_DATA_WRITE_FAILED { source })
+}
+
 pub(crate) fn get_explorer_launch_path(version: Option<&str>) -> DCLErrorTyped<PathBuf> {
     let base_path = match version {
         None => explorer_latest_version_path(),
         Some("dev") => explorer_dev_version_path(),
         Some(v) => {
-            let version_data = get_version_data()?;
-            let latest_version = get_latest_version(&version_data)?;
+            let registry = get_registry()?;
+            let latest_version = get_latest_version(&registry)?;
             if v == latest_version {
                 explorer_latest_version_path()
             } else {
@@ -215,6 +227,23 @@ pub(crate) fn get_explorer_launch_path(version: Option<&str>) -> DCLErrorTyped<P
     {
         Ok(base_path.join(EXPLORER_WIN_BIN_PATH))
     }
+
+    #[cfg(target_os = "linux")]
+    {
+        Ok(base_path.join(EXPLORER_LINUX_BIN_PATH))
+    }
+}
+
+/// Zip archives don't always carry the unix mode, so the Explorer binary is
+/// marked executable explicitly.
+#[cfg(target_os = "linux")]
+fn mark_executable(path: &Path) -> std::io::Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    if permissions.mode() & 0o111 != 0o111 {
+        permissions.set_mode(0o755);
+        fs::set_permissions(path, permissions)?;
+    }
+    Ok(())
 }
 
 #[cfg(target_os = "macos")]
@@ -248,6 +277,69 @@ fn move_recursive(src: &PathBuf, dst: &PathBuf) -> Result<()> {
     Ok(())
 }
 
+/// Writes `path` through a temporary file next to it that is synced and
+/// renamed over, a crash or a power loss leaves the old or the new content
+/// but never a torn file.
+pub(crate) fn atomic_write(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&temp_path, path)?;
+    match path.parent() {
+        Some(parent) => sync_dir(parent),
+        None => Ok(()),
+    }
+}
+
+/// Persists the entries of `path`, e.g. a rename into it. Windows cannot
+/// open a directory as a file, its renames are journaled by NTFS.
+#[cfg(unix)]
+pub(crate) fn sync_dir(path: &Path) -> std::io::Result<()> {
+    fs::File::open(path)?.sync_all()
+}
+
+#[cfg(not(unix))]
+#[allow(clippy::unnecessary_wraps)]
+pub(crate) const fn sync_dir(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Persists every file and directory under `path`, then its entry in the
+/// parent directory.
+fn sync_tree(path: &Path) -> std::io::Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            sync_tree(&entry.path())?;
+        } else if file_type.is_file() {
+            sync_file(&entry.path())?;
+        }
+    }
+    sync_dir(path)?;
+    match path.parent() {
+        Some(parent) => sync_dir(parent),
+        None => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn sync_file(path: &Path) -> std::io::Result<()> {
+    fs::File::open(path)?.sync_all()
+}
+
+/// Windows only flushes a file opened for writing.
+#[cfg(not(unix))]
+fn sync_file(path: &Path) -> std::io::Result<()> {
+    fs::OpenOptions::new().write(true).open(path)?.sync_all()
+}
+
 struct EntryVersion {
     version: Version,
     v_prefixed: bool,
@@ -315,81 +407,342 @@ fn remove_version_if_exists(version: &EntryVersion) {
     }
 }
 
+/// Keeps the installed build and the newest older one for rollbacks, the
+/// other builds of the registry are removed along with their entries.
 fn cleanup_versions(current_version: &EntryVersion) -> DCLErrorResult {
     const KEEP_VERSIONS_FOR_ROLLBACK_AMOUNT: usize = 2;
 
-    let explorer_path = explorer_path();
-    let entries =
-        fs::read_dir(&explorer_path).map_err(|e| DCLError::from_cleanup(&explorer_path, e))?;
-
-    let mut installations: Vec<EntryVersion> = Vec::new();
-
-    for entry in entries {
-        let Ok(entry) = entry else { continue };
-        let file_name = entry.file_name();
-        let entry_name = file_name.to_str().ok_or_else(|| {
-            DCLError::from_cleanup(
-                &entry.path(),
-                std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "entry name is not valid UTF-8",
-                ),
-            )
-        })?;
-
-        if let Some(version) = EntryVersion::from_str(entry_name) {
-            installations.push(version);
+    let mut registry = get_registry_or_empty();
+    let current = registry.current.clone();
+    // Builds removed from the disk are forgotten, directories the registry
+    // doesn't know, e.g. of an install that failed to record, count as well
+    registry.builds.retain(|build| {
+        current.as_ref() == Some(&build.version) || explorer_path().join(&build.version).is_dir()
+    });
+    for path in old_version_paths() {
+        if let Some(version) = path.file_name().and_then(|name| name.to_str()) {
+            registry.build_mut(version);
         }
     }
 
-    if installations.is_empty() {
-        return Ok(());
-    }
+    let mut installations: Vec<(EntryVersion, String)> = registry
+        .builds
+        .iter()
+        .filter_map(|build| {
+            Some((
+                EntryVersion::from_str(&build.version)?,
+                build.version.clone(),
+            ))
+        })
+        .collect();
 
-    installations.retain(|i| {
+    installations.retain(|(i, version)| {
         // remove versions above the current version in a case of rollback
         let should_be_removed = i > current_version;
         if should_be_removed {
             remove_version_if_exists(i);
+            registry.remove_build(version);
         }
         !should_be_removed
     });
 
     // Sort versions
-    installations.sort();
+    installations.sort_by(|a, b| a.0.cmp(&b.0));
 
-    if installations.len() <= KEEP_VERSIONS_FOR_ROLLBACK_AMOUNT {
-        // Don't need to uninstall anything
-        return Ok(());
+    // Keep the latest 2 versions and delete the rest
+    let removed = installations
+        .len()
+        .saturating_sub(KEEP_VERSIONS_FOR_ROLLBACK_AMOUNT);
+    for (entry, version) in installations.iter().take(removed) {
+        remove_version_if_exists(entry);
+        registry.remove_build(version);
     }
 
-    // Keep the latest 2 versions and delete the rest
-    // Arithmetic boundaries are solved on the line above
-    #[allow(clippy::arithmetic_side_effects)]
-    for version in installations
+    write_registry(&registry)
+}
+
+/// Version set with `--pin-version` or the `pinned-version` key of
+/// `config.json`, the launcher stays on it instead of the latest release.
+pub fn pinned_version() -> Option<String> {
+    let pinned = AppEnvironment::cmd_args()
+        .pin_version
+        .or_else(config::pinned_version)?;
+    // "dev" is valid but names no release
+    if EntryVersion::from_str(&pinned).is_some() && crate::utils::is_valid_version(&pinned) {
+        Some(pinned)
+    } else {
+        log::warn!("Ignoring invalid pinned version {:?}", pinned);
+        None
+    }
+}
+
+/// Version directories kept by `cleanup_versions` for rollbacks.
+fn old_version_paths() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(explorer_path()) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(EntryVersion::from_str)
+                .is_some()
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+/// Old versions [`prune_old_versions`] removes. The pinned version and the
+/// newest one before the installed build stay, so a pin or rollback works
+/// without a download.
+fn prunable_version_paths() -> Vec<PathBuf> {
+    let kept: Vec<String> = [
+        pinned_version(),
+        installed_latest_version().and_then(|current| previous_version(&current)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    old_version_paths()
+        .into_iter()
+        .filter(|path| !kept.iter().any(|version| path.ends_with(version)))
+        .collect()
+}
+
+/// Space that [`prune_old_versions`] would free.
+pub fn reclaimable_space() -> u64 {
+    prunable_version_paths()
         .iter()
-        .take(installations.len() - KEEP_VERSIONS_FOR_ROLLBACK_AMOUNT)
+        .map(|path| disk_space::directory_size(path))
+        .fold(0, u64::saturating_add)
+}
+
+/// Removes the old versions [`prunable_version_paths`] lists. Returns the
+/// freed space.
+///
+/// Fails while any Explorer runs from the installation, `--version`
+/// launches straight from a kept version.
+pub fn prune_old_versions() -> DCLErrorTyped<u64> {
+    let running = RunningInstances::explorer_processes_under(&explorer_path());
+    if !running.is_empty() {
+        return Err(DCLError::E3008_EXPLORER_ALREADY_RUNNING { processes: running });
+    }
+    let _lock = lock_install()?;
+
+    let mut registry = get_registry().ok();
+    let freed = prunable_version_paths()
+        .iter()
+        .map(|path| {
+            let size = disk_space::directory_size(path);
+            match fs::remove_dir_all(path) {
+                Ok(()) => {
+                    log::info!("Removed old version to free space: {}", path.display());
+                    if let (Some(registry), Some(version)) =
+                        (registry.as_mut(), path.file_name().and_then(|n| n.to_str()))
+                    {
+                        registry.remove_build(version);
+                    }
+                    size
+                }
+                Err(e) => {
+                    log::error!("Failed to remove {}: {}", path.display(), e);
+                    0
+                }
+            }
+        })
+        .fold(0, u64::saturating_add);
+
+    if let Some(registry) = registry
+        && let Err(e) = write_registry(&registry)
     {
-        remove_version_if_exists(version);
+        log::error!("Cannot forget the removed versions: {}", e);
     }
+    Ok(freed)
+}
 
-    Ok(())
+/// Removes downloaded builds, patches and partial downloads. Returns the
+/// freed space.
+pub fn remove_downloads() -> u64 {
+    let path = explorer_downloads_path();
+    let size = disk_space::directory_size(&path);
+    match fs::remove_dir_all(&path) {
+        Ok(()) => size,
+        Err(e) => {
+            log::error!("Failed to remove {}: {}", path.display(), e);
+            0
+        }
+    }
 }
 
-fn is_app_updated(version: &str) -> bool {
-    let result = get_version_data();
-    match result {
-        Ok(data) => {
-            if let Some(v) = data.get("version") {
-                v == version
-            } else {
-                false
-            }
+pub struct InstalledVersion {
+    pub version: String,
+    /// Unix time in seconds.
+    pub installed_at: Option<u64>,
+    /// Unix time in seconds.
+    pub last_launched_at: Option<u64>,
+    pub current: bool,
+    pub path: PathBuf,
+}
+
+/// The installed build and the ones kept for rollback, newest first.
+pub fn installed_versions() -> Vec<InstalledVersion> {
+    let registry = get_registry_or_empty();
+    let installed_at = |version: &str| registry.build(version).and_then(|b| b.installed_at);
+    let last_launched_at = |version: &str| registry.build(version).and_then(|b| b.last_launched_at);
+
+    let mut versions: Vec<InstalledVersion> = old_version_paths()
+        .into_iter()
+        .filter_map(|path| {
+            let version = path.file_name()?.to_str()?.to_owned();
+            Some(InstalledVersion {
+                installed_at: installed_at(&version),
+                last_launched_at: last_launched_at(&version),
+                version,
+                current: false,
+                path,
+            })
+        })
+        .collect();
+    versions.sort_by_cached_key(|v| std::cmp::Reverse(EntryVersion::from_str(&v.version)));
+
+    if let Some(version) = installed_latest_version() {
+        versions.insert(
+            0,
+            InstalledVersion {
+                installed_at: installed_at(&version),
+                last_launched_at: last_launched_at(&version),
+                version,
+                current: true,
+                path: explorer_latest_version_path(),
+            },
+        );
+    }
+    versions
+}
+
+/// Makes a build kept for rollback the installed one again and returns the
+/// versions rolled back from and to.
+///
+/// `version` defaults to the newest build older than the installed one.
+/// Either "latest" and `version.json` are both switched or nothing is.
+pub fn rollback(version: Option<&str>) -> DCLErrorTyped<(String, String)> {
+    let mut registry = get_registry()?;
+    let current = get_latest_version(&registry)?.to_owned();
+    let latest_path = explorer_latest_version_path();
+    if !latest_path.exists() {
+        return Err(DCLError::E3009_EXPLORER_NOT_INSTALLED {
+            expected_path: latest_path.to_string_lossy().into_owned(),
+            version: Some(current),
+        });
+    }
+
+    let previous = match version {
+        Some(version) if version != current && retained_version_path(version).is_some() => {
+            version.to_owned()
         }
-        Err(_) => false,
+        Some(version) => {
+            return Err(DCLError::E3017_ROLLBACK_VERSION_NOT_RETAINED {
+                version: version.to_owned(),
+            });
+        }
+        None => previous_version(&current).ok_or_else(|| DCLError::E3016_NO_ROLLBACK_VERSION {
+            current: current.clone(),
+        })?,
+    };
+
+    let current_path = explorer_path().join(&current);
+    let previous_path = explorer_path().join(&previous);
+    write_install_journal(&previous, Some(current.clone()), InstallPhase::Switching)?;
+    if let Err(e) = rename_latest_back_to_version(&latest_path, &current_path, &previous_path) {
+        InstallJournal::remove(&explorer_path());
+        return Err(e);
+    }
+    if fs::rename(&previous_path, &latest_path).is_err() {
+        // Put the installed build back, there is nothing to launch otherwise
+        restore_latest(&current_path, &latest_path);
+        return Err(DCLError::E3004_CANT_RENAME_LATEST);
+    }
+
+    registry.current = Some(previous.clone());
+    if let Err(e) = write_registry(&registry) {
+        if let Err(e) = fs::rename(&latest_path, &previous_path) {
+            log::error!("Cannot move {} back: {}", previous, e);
+        }
+        restore_latest(&current_path, &latest_path);
+        return Err(e);
+    }
+    InstallJournal::remove(&explorer_path());
+    log::info!("Rolled back from {} to {}", current, previous);
+    Ok((current, previous))
+}
+
+/// The journal is kept when "latest" cannot be restored, the rollback is
+/// completed on the next start then.
+fn restore_latest(current_path: &Path, latest_path: &Path) {
+    match fs::rename(current_path, latest_path) {
+        Ok(()) => InstallJournal::remove(&explorer_path()),
+        Err(e) => log::error!("Cannot restore {}: {}", current_path.display(), e),
     }
 }
 
+fn previous_version(current: &str) -> Option<String> {
+    let current_entry = EntryVersion::from_str(current);
+    old_version_paths()
+        .iter()
+        .filter_map(|path| path.file_name()?.to_str().and_then(EntryVersion::from_str))
+        .filter(|version| {
+            current_entry
+                .as_ref()
+                .is_none_or(|current| version < current)
+        })
+        .max()
+        .map(|version| version.to_restored())
+}
+
+/// Directory of `version` when it is one of the builds kept by `cleanup_versions`.
+pub fn retained_version_path(version: &str) -> Option<PathBuf> {
+    let path = explorer_path().join(version);
+    (EntryVersion::from_str(version).is_some() && path.is_dir()).then_some(path)
+}
+
+/// Fails with [`DCLError::E1009_NOT_ENOUGH_DISK_SPACE`] when the volume of
+/// the installation has less free space than `requirement`.
+pub fn check_disk_space(requirement: disk_space::SpaceRequirement) -> DCLErrorResult {
+    let path = explorer_path();
+    let Some(available) = disk_space::available_space(&path) else {
+        log::warn!(
+            "Cannot get the free space of {}, skipping the check",
+            path.display()
+        );
+        return Ok(());
+    };
+
+    let required = requirement.total();
+    log::info!(
+        "Free space check: {} bytes required, {} available",
+        required,
+        available
+    );
+    if available >= required {
+        return Ok(());
+    }
+
+    Err(DCLError::from_disk_space(
+        required,
+        available,
+        reclaimable_space(),
+    ))
+}
+
+fn is_app_updated(version: &str) -> bool {
+    get_registry().is_ok_and(|registry| registry.current.as_deref() == Some(version))
+}
+
 pub fn is_explorer_installed(version: Option<&str>) -> bool {
     let path = get_explorer_launch_path(version);
     match path {
@@ -398,14 +751,108 @@ pub fn is_explorer_installed(version: Option<&str>) -> bool {
     }
 }
 
-pub fn is_explorer_updated(version: &str) -> bool {
-    is_explorer_installed(Some(version)) && is_app_updated(version)
+/// A build of another release channel is never up to date, even with the
+/// same version, switching channels reinstalls.
+pub fn is_explorer_updated(version: &str, channel: &ReleaseChannel) -> bool {
+    is_explorer_installed(Some(version))
+        && is_app_updated(version)
+        && installed_release_channel().as_ref() == Some(channel)
 }
 
 pub fn target_download_path() -> PathBuf {
     explorer_downloads_path().join(EXPLORER_DOWNLOADED_FILENAME)
 }
 
+pub fn patch_download_path() -> PathBuf {
+    explorer_downloads_path().join(EXPLORER_PATCH_FILENAME)
+}
+
+pub fn file_manifest_download_path() -> PathBuf {
+    explorer_downloads_path().join(EXPLORER_FILE_MANIFEST_FILENAME)
+}
+
+/// Content-addressed store of the files downloaded for a file-level update.
+pub fn file_store_path() -> PathBuf {
+    explorer_downloads_path().join(EXPLORER_FILE_STORE_DIRNAME)
+}
+
+/// File manifest fetched to verify the installed build, kept apart from the
+/// one of a pending file-level update.
+pub fn repair_manifest_path() -> PathBuf {
+    explorer_downloads_path().join(EXPLORER_REPAIR_MANIFEST_FILENAME)
+}
+
+/// Store of the files downloaded to repair the installed build.
+pub fn repair_store_path() -> PathBuf {
+    explorer_downloads_path().join(EXPLORER_REPAIR_STORE_DIRNAME)
+}
+
+/// Version currently installed as "latest", if any.
+pub fn installed_latest_version() -> Option<String> {
+    let registry = get_registry().ok()?;
+    let version = get_latest_version(&registry).ok()?;
+    explorer_latest_version_path()
+        .exists()
+        .then(|| version.to_owned())
+}
+
+/// Version the registry holds as "latest", also when its directory is gone.
+pub fn registered_latest_version() -> Option<String> {
+    let registry = get_registry().ok()?;
+    get_latest_version(&registry).ok().map(ToOwned::to_owned)
+}
+
+/// Release channel of the build installed as "latest". Installs made before
+/// channels existed come from stable.
+pub fn installed_release_channel() -> Option<ReleaseChannel> {
+    let registry = get_registry().ok()?;
+    match registry.current_build().and_then(|b| b.channel.as_deref()) {
+        Some(name) => ReleaseChannel::parse(name),
+        None => Some(ReleaseChannel::Stable),
+    }
+}
+
+/// Where the build in "latest" was downloaded from.
+pub struct BuildSource {
+    pub url: String,
+    pub sha256: Option<String>,
+}
+
+/// Records the channel of the build in "latest" and, unless it was already
+/// installed, where it came from.
+pub fn record_installed_build(
+    channel: &ReleaseChannel,
+    source: Option<BuildSource>,
+) -> DCLErrorResult {
+    let mut registry = get_registry()?;
+    let current = get_latest_version(&registry)?.to_owned();
+    let build = registry.build_mut(&current);
+    build.channel = Some(channel.name().to_owned());
+    if let Some(source) = source {
+        build.source_url = Some(source.url);
+        build.sha256 = source.sha256;
+    }
+    write_registry(&registry)
+}
+
+/// Records the launch of `version`, "latest" when `None`.
+fn record_launch(version: Option<&str>) {
+    let Ok(mut registry) = get_registry() else {
+        return;
+    };
+    let Some(version) = version.or(registry.current.as_deref()).map(str::to_owned) else {
+        return;
+    };
+    if registry.build(&version).is_none() {
+        // e.g. the dev build
+        return;
+    }
+    registry.build_mut(&version).last_launched_at = registry::unix_time();
+    if let Err(e) = write_registry(&registry) {
+        log::error!("Cannot record the launch of {}: {}", version, e);
+    }
+}
+
 fn rename_latest_back_to_version(
     latest_path: &Path,
     target: &Path,
@@ -420,14 +867,44 @@ fn rename_latest_back_to_version(
     fs::rename(latest_path, target).map_err(|e| DCLError::from_rename_back(latest_path, e))
 }
 
-pub fn install_explorer(version: &str, downloaded_file_path: Option<PathBuf>) -> DCLErrorResult {
+pub fn install_explorer(
+    version: &str,
+    downloaded_file_path: Option<PathBuf>,
+    on_progress: &mut dyn FnMut(compression::ExtractProgress),
+) -> DCLErrorResult {
     let current_version: EntryVersion = EntryVersion::from_str(version)
         .ok_or_else(|| anyhow!("Version value cannot be parsed: {version}"))?;
 
-    let explorer_path = explorer_path();
-    let branch_path = explorer_path.join(version);
+    let branch_path = explorer_path().join(version);
     let file_path = downloaded_file_path.unwrap_or_else(target_download_path);
+    begin_install(version)?;
+    unpack_build(&file_path, &branch_path, on_progress)?;
+
+    register_installed_version(version, &branch_path)?;
+
+    // Remove the downloaded file
+    fs::remove_file(&file_path).map_err(|source| DCLError::E1006_FILE_DELETE_FAILED {
+        file_path: file_path.to_string_lossy().into_owned(),
+        source,
+    })?;
+
+    cleanup_versions(&current_version)
+}
 
+/// Unpacks the archive given with `--downloadedfilepath` as the `dev` build.
+/// `version.json` isn't touched and the archive is kept.
+pub fn install_dev_explorer(
+    file_path: &Path,
+    on_progress: &mut dyn FnMut(compression::ExtractProgress),
+) -> DCLErrorResult {
+    unpack_build(file_path, &explorer_dev_version_path(), on_progress)
+}
+
+fn unpack_build(
+    file_path: &Path,
+    branch_path: &Path,
+    on_progress: &mut dyn FnMut(compression::ExtractProgress),
+) -> DCLErrorResult {
     if !file_path.exists() {
         return DCLError::E1001_FILE_NOT_FOUND {
             expected_path: Some(file_path.to_string_lossy().into_owned()),
@@ -436,21 +913,21 @@ pub fn install_explorer(version: &str, downloaded_file_path: Option<PathBuf>) ->
     }
 
     if branch_path.exists() {
-        fs::remove_dir_all(&branch_path).map_err(|source| {
+        fs::remove_dir_all(branch_path).map_err(|source| {
             DCLError::E3005_STALE_BUILD_CLEANUP_FAILED {
                 path: branch_path.to_string_lossy().into_owned(),
                 source,
             }
         })?;
     }
-    compression::decompress_file(&file_path, &branch_path)?;
+    compression::decompress_file(file_path, branch_path, on_progress)?;
 
     #[cfg(target_os = "macos")]
     {
         const EXPLORER_MAC_BIN_PATH: &str = "Decentraland.app/Contents/MacOS/Explorer";
 
         let from = &branch_path.join("build");
-        let to = &branch_path;
+        let to = &branch_path.to_path_buf();
         move_recursive(from, to).context("Cannot move build folder")?;
 
         let explorer_bin_path = branch_path.join(EXPLORER_MAC_BIN_PATH);
@@ -462,60 +939,189 @@ pub fn install_explorer(version: &str, downloaded_file_path: Option<PathBuf>) ->
         }
     }
 
-    let mut version_data = get_version_data_or_empty();
+    #[cfg(target_os = "linux")]
+    {
+        let explorer_bin_path = branch_path.join(EXPLORER_LINUX_BIN_PATH);
+        if explorer_bin_path.exists() {
+            mark_executable(&explorer_bin_path)
+                .map_err(|e| DCLError::from_binary_access(&explorer_bin_path, e))?;
+        }
+    }
 
-    let install_time = Value::from(
-        std::time::SystemTime::now()
-            .duration_since(std::time::SystemTime::UNIX_EPOCH)
-            .context("Cannot convert time")?
-            .as_secs()
-            .to_string(),
-    );
-    version_data.insert(version.to_owned(), install_time);
+    Ok(())
+}
 
-    let latest_version = get_latest_version(&version_data).map(String::from);
-    let latest_path = explorer_latest_version_path();
+/// Builds the `version` branch directory from the installed "latest" build
+/// (which must be `from_version`) and the downloaded patch.
+pub fn install_explorer_from_patch(
+    version: &str,
+    from_version: &str,
+    patch_path: &Path,
+    on_progress: &mut dyn FnMut(compression::ExtractProgress),
+) -> DCLErrorResult {
+    let current_version: EntryVersion = EntryVersion::from_str(version)
+        .ok_or_else(|| anyhow!("Version value cannot be parsed: {version}"))?;
 
-    // Rename latest back to its version so that cleanup_versions can do its
-    // job. InstallStep will rename the new newest build to "latest".
-    if let Ok(v) = latest_version
-        && latest_path.exists()
-    {
-        let target = explorer_path.join(&v);
-        rename_latest_back_to_version(&latest_path, &target, &branch_path)?;
+    if !patch_path.exists() {
+        return DCLError::E1001_FILE_NOT_FOUND {
+            expected_path: Some(patch_path.to_string_lossy().into_owned()),
+        }
+        .into();
     }
 
-    if version != "dev" {
-        version_data.insert("version".to_owned(), Value::String(version.to_owned()));
+    begin_install(version)?;
+    let installed_version = installed_latest_version().unwrap_or_default();
+    let branch_path = explorer_path().join(version);
+    if branch_path.exists() {
+        fs::remove_dir_all(&branch_path).map_err(|e| DCLError::from_cleanup(&branch_path, e))?;
     }
 
-    // The path to the latest explorer build will be just "latest" from now on.
-    // Remove the path value from version data to not confuse people. You can
-    // remove this line in like 2027.
-    version_data.remove("path");
+    let applied = patches::apply_patch(
+        patch_path,
+        &installed_version,
+        version,
+        &explorer_latest_version_path(),
+        &branch_path,
+        on_progress,
+    );
+    if let Err(error) = applied {
+        // Neither the half built branch nor the patch are of any use now, the
+        // caller falls back to the full build.
+        if branch_path.exists()
+            && let Err(e) = fs::remove_dir_all(&branch_path)
+        {
+            log::error!("Cannot remove the partially patched build: {}", e);
+        }
+        if let Err(e) = fs::remove_file(patch_path) {
+            log::error!("Cannot remove the failed patch: {}", e);
+        }
+        return Err(DCLError::E3014_PATCH_APPLY_FAILED {
+            from_version: from_version.to_owned(),
+            error,
+        });
+    }
 
-    // Write version data to file
-    let version_data_str =
-        serde_json::to_string(&version_data).context("Cannot serialize version_data")?;
-    let version_path = explorer_version_path();
-    fs::write(version_path, version_data_str)
-        .map_err(|source| DCLError::E3007_VERSION_DATA_WRITE_FAILED { source })?;
+    register_installed_version(version, &branch_path)?;
 
-    // Remove the downloaded file
-    fs::remove_file(&file_path).map_err(|source| DCLError::E1006_FILE_DELETE_FAILED {
-        file_path: file_path.to_string_lossy().into_owned(),
+    fs::remove_file(patch_path).map_err(|source| DCLError::E1006_FILE_DELETE_FAILED {
+        file_path: patch_path.to_string_lossy().into_owned(),
         source,
     })?;
-    
+
     cleanup_versions(&current_version)
 }
 
-pub fn rename_explorer_to_latest() -> DCLErrorResult {
-    let Ok(version_data) = get_version_data() else {
-        return Err(DCLError::E3003_CANT_GET_VERSION);
+/// Builds the `version` branch directory from the installed "latest" build
+/// and the files downloaded into [`file_store_path`] for the manifest.
+pub fn install_explorer_from_files(
+    version: &str,
+    manifest_path: &Path,
+    on_progress: &mut dyn FnMut(compression::ExtractProgress),
+) -> DCLErrorResult {
+    let current_version: EntryVersion = EntryVersion::from_str(version)
+        .ok_or_else(|| anyhow!("Version value cannot be parsed: {version}"))?;
+
+    if !manifest_path.exists() {
+        return DCLError::E1001_FILE_NOT_FOUND {
+            expected_path: Some(manifest_path.to_string_lossy().into_owned()),
+        }
+        .into();
+    }
+
+    begin_install(version)?;
+    let store_path = file_store_path();
+    let branch_path = explorer_path().join(version);
+    if branch_path.exists() {
+        fs::remove_dir_all(&branch_path).map_err(|e| DCLError::from_cleanup(&branch_path, e))?;
+    }
+
+    let assembled = incremental::FileManifest::load(manifest_path).and_then(|manifest| {
+        incremental::assemble(
+            &manifest,
+            &explorer_latest_version_path(),
+            &store_path,
+            &branch_path,
+            on_progress,
+        )
+    });
+    if let Err(error) = assembled {
+        // Same as for patches, the caller falls back to the full build.
+        if branch_path.exists()
+            && let Err(e) = fs::remove_dir_all(&branch_path)
+        {
+            log::error!("Cannot remove the partially assembled build: {}", e);
+        }
+        remove_file_update_leftovers(manifest_path, &store_path);
+        return Err(DCLError::E3015_INCREMENTAL_UPDATE_FAILED { error });
+    }
+
+    register_installed_version(version, &branch_path)?;
+    remove_file_update_leftovers(manifest_path, &store_path);
+
+    cleanup_versions(&current_version)
+}
+
+/// Removes the file manifest and the files downloaded to verify or repair
+/// the installed build.
+pub fn remove_repair_leftovers() {
+    remove_file_update_leftovers(&repair_manifest_path(), &repair_store_path());
+}
+
+fn remove_file_update_leftovers(manifest_path: &Path, store_path: &Path) {
+    if manifest_path.exists()
+        && let Err(e) = fs::remove_file(manifest_path)
+    {
+        log::error!("Cannot remove the file manifest: {}", e);
+    }
+    if store_path.exists()
+        && let Err(e) = fs::remove_dir_all(store_path)
+    {
+        log::error!("Cannot remove the downloaded files: {}", e);
+    }
+}
+
+/// Records `version` as the latest one and moves the previous "latest" back
+/// to its own version directory.
+fn register_installed_version(version: &str, branch_path: &Path) -> DCLErrorResult {
+    let mut registry = get_registry_or_empty();
+
+    let installed_at = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .context("Cannot convert time")?
+        .as_secs();
+    // A reinstall starts over, the source is recorded by the caller
+    *registry.build_mut(version) = InstalledBuild {
+        version: version.to_owned(),
+        installed_at: Some(installed_at),
+        size: Some(disk_space::directory_size(branch_path)),
+        ..InstalledBuild::default()
     };
 
-    let latest_version = get_latest_version(&version_data)?;
+    let latest_version = get_latest_version(&registry).map(String::from);
+    let latest_path = explorer_latest_version_path();
+
+    // Rename latest back to its version so that cleanup_versions can do its
+    // job. InstallStep will rename the new newest build to "latest".
+    let previous_version = latest_version.ok().filter(|_| latest_path.exists());
+    // Rolled forward from Switching on, the build must be complete on disk
+    sync_tree(branch_path)
+        .map_err(|source| DCLError::E3019_INSTALL_JOURNAL_WRITE_FAILED { source })?;
+    write_install_journal(version, previous_version.clone(), InstallPhase::Switching)?;
+    if let Some(v) = previous_version {
+        let target = explorer_path().join(&v);
+        rename_latest_back_to_version(&latest_path, &target, branch_path)?;
+    }
+
+    if version != "dev" {
+        registry.current = Some(version.to_owned());
+    }
+
+    write_registry(&registry)
+}
+
+pub fn rename_explorer_to_latest() -> DCLErrorResult {
+    let registry = get_registry()?;
+    let latest_version = get_latest_version(&registry)?;
 
     let Ok(()) = fs::rename(
         explorer_path().join(latest_version),
@@ -524,9 +1130,75 @@ pub fn rename_explorer_to_latest() -> DCLErrorResult {
         return Err(DCLError::E3004_CANT_RENAME_LATEST);
     };
 
+    InstallJournal::remove(&explorer_path());
     Ok(())
 }
 
+fn write_install_journal(
+    version: &str,
+    previous: Option<String>,
+    phase: InstallPhase,
+) -> DCLErrorResult {
+    let journal = InstallJournal {
+        version: version.to_owned(),
+        previous,
+        phase,
+    };
+    journal
+        .write(&explorer_path())
+        .map_err(|source| DCLError::E3019_INSTALL_JOURNAL_WRITE_FAILED { source })
+}
+
+/// An install interrupted earlier in this session, e.g. by a failure after
+/// "latest" was moved back, is recovered before the next one starts. The
+/// caller holds the lock of [`lock_install`].
+fn begin_install(version: &str) -> DCLErrorResult {
+    recover_locked();
+    write_install_journal(version, installed_latest_version(), InstallPhase::Unpacking)
+}
+
+/// Held from the start of an install until "latest" is switched, or while
+/// builds are rolled back or removed.
+pub fn lock_install() -> DCLErrorTyped<InstallLock> {
+    let root = explorer_path();
+    match InstallLock::acquire(&root) {
+        Ok(Some(lock)) => Ok(lock),
+        Ok(None) => Err(DCLError::E3021_INSTALL_IN_PROGRESS),
+        Err(source) => Err(DCLError::E1007_FILE_CREATE_FAILED {
+            file_path: root.to_string_lossy().into_owned(),
+            source,
+        }),
+    }
+}
+
+/// Recovers an install interrupted by a crash or a power loss, see
+/// [`journal`], and points the registry at the build left in "latest".
+/// Skipped while another process installs.
+pub fn recover_interrupted_install() -> Option<journal::Recovery> {
+    let _lock = match lock_install() {
+        Ok(lock) => lock,
+        Err(e) => {
+            log::info!("Not recovering interrupted installs: {}", e);
+            return None;
+        }
+    };
+    recover_locked()
+}
+
+fn recover_locked() -> Option<journal::Recovery> {
+    let recovery = journal::recover(&explorer_path())?;
+    if let Some(latest) = &recovery.latest {
+        let mut registry = get_registry_or_empty();
+        if registry.current.as_ref() != Some(latest) {
+            registry.current = Some(latest.clone());
+            if let Err(e) = write_registry(&registry) {
+                log::error!("Cannot point version.json at the recovered build: {}", e);
+            }
+        }
+    }
+    Some(recovery)
+}
+
 pub struct InstallsHub {
     analytics: Arc<Mutex<Analytics>>,
     running_instances: Arc<Mutex<RunningInstances>>,
@@ -584,16 +1256,9 @@ impl InstallsHub {
     fn readable_version(version: Option<&str>) -> String {
         match version {
             Some(v) => v.to_owned(),
-            None => {
-                let map = get_version_data_or_empty();
-                if let Some(v) = map.get("version") {
-                    if let Some(str_version) = v.as_str() {
-                        return str_version.to_owned();
-                    }
-                }
-
-                "latest".to_owned()
-            }
+            None => get_registry_or_empty()
+                .current
+                .unwrap_or_else(|| "latest".to_owned()),
         }
     }
 
@@ -636,6 +1301,8 @@ impl InstallsHub {
             // Consume the referrer on success, it only applies to the first launch after installation.
             ReferrerStorage::delete();
 
+            record_launch(preferred_version);
+
             self.send_analytics_event(Event::LAUNCH_CLIENT_SUCCESS {
                 version: readable_version,
             })
@@ -666,16 +1333,20 @@ impl InstallsHub {
             });
         }
 
-        // Ensure binary is executable, windows only, macOS doesn't use direct launch due
-        // the permissions issue
+        // Ensure binary is executable, windows and linux only, macOS doesn't use direct
+        // launch due the permissions issue
         #[cfg(windows)]
         fs::metadata(&explorer_launch_path)
             .map_err(|e| DCLError::from_binary_access(&explorer_launch_path, e))?;
+        // Patched and file-by-file updates may have dropped the executable bit
+        #[cfg(target_os = "linux")]
+        mark_executable(&explorer_launch_path)
+            .map_err(|e| DCLError::from_binary_access(&explorer_launch_path, e))?;
 
         // Prepare explorer parameters
         #[cfg(target_os = "macos")]
         let mut explorer_params = self.explorer_params(deeplink).await;
-        #[cfg(target_os = "windows")]
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
         let explorer_params = self.explorer_params(deeplink).await;
 
         log::info!(
@@ -697,12 +1368,12 @@ impl InstallsHub {
                 .map_err(|e| DCLError::from_launch_failure(&explorer_launch_path, e))?;
         }
 
-        #[cfg(target_os = "windows")]
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
         let mut child =
             Self::launch_command(&explorer_launch_path, explorer_launch_dir, &explorer_params)
                 .map_err(|e| DCLError::from_launch_failure(&explorer_launch_path, e))?;
 
-        #[cfg(target_os = "windows")]
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
         {
             let guard = self.running_instances.lock().await;
             guard.register_instance(child.id());
@@ -764,6 +1435,38 @@ impl InstallsHub {
             }
         }
 
+        #[cfg(target_os = "linux")]
+        Self::check_early_exit(&mut child).await?;
+
+        Ok(())
+    }
+
+    /// The child is spawned directly, so an exit right after start is seen here.
+    /// Exiting with 0 means the Explorer handed the launch over, e.g. to an
+    /// instance that was already running.
+    #[cfg(target_os = "linux")]
+    async fn check_early_exit(child: &mut std::process::Child) -> DCLErrorResult {
+        const WAIT_TIMEOUT: Duration = Duration::from_secs(3);
+        const CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+        let wait = async {
+            loop {
+                if let Some(exit_status) = child.try_wait()? {
+                    return std::io::Result::Ok(exit_status);
+                }
+                tokio::time::sleep(CHECK_INTERVAL).await;
+            }
+        };
+
+        // A timeout means the Explorer is still running
+        if let Ok(exited) = tokio::time::timeout(WAIT_TIMEOUT, wait).await {
+            let exit_status = exited?;
+            if !exit_status.success() {
+                return Err(DCLError::E3012_EXPLORER_EXITED_ON_LAUNCH {
+                    exit_code: exit_status.to_string(),
+                });
+            }
+        }
         Ok(())
     }
 
@@ -783,18 +1486,22 @@ impl InstallsHub {
         Ok(())
     }
 
-    #[cfg(target_os = "windows")]
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
     fn launch_command<S: AsRef<std::ffi::OsStr> + std::fmt::Debug>(
         command: S,
         dir: &Path,
         args: &[String],
     ) -> Result<std::process::Child> {
-        Command::new(&command)
-            .current_dir(dir)
-            .args(args.iter())
-            .detached()
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
+        let mut process = Command::new(&command);
+        process.current_dir(dir).args(args.iter()).detached();
+        // Nobody reads the output once the launcher quits, and on Linux writing
+        // to the closed pipe would kill the Explorer with SIGPIPE
+        #[cfg(target_os = "linux")]
+        process.stdout(Stdio::null()).stderr(Stdio::null());
+        #[cfg(target_os = "windows")]
+        process.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+        process
             .spawn()
             .map_err(|e| anyhow!("Failed to start explorer process: {}", e))
             .with_context(|| {
@@ -1,13 +1,124 @@
-use crate::errors::{DCLError, DCLErrorResult};
+use crate::errors::{DCLError, DCLErrorResult, DCLErrorTyped};
+use anyhow::anyhow;
+use flate2::read::GzDecoder;
 use std::{
-    fs,
-    io::{Cursor, Read, Write},
-    path::{Path, PathBuf},
+    cell::Cell,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Seek, Write},
+    path::{Component, Path, PathBuf},
 };
 use tar::Archive;
-use zip::read::ZipArchive;
+use zip::read::{ZipArchive, ZipFile};
 
-pub fn decompress_file(source_path: &PathBuf, destination_path: &PathBuf) -> DCLErrorResult {
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExtractProgress {
+    pub bytes_processed: u64,
+    pub total_bytes: u64,
+    pub entries_processed: u64,
+}
+
+impl ExtractProgress {
+    pub fn percent(&self) -> u8 {
+        if self.total_bytes == 0 {
+            return 0;
+        }
+        let percent = u128::from(self.bytes_processed.min(self.total_bytes))
+            .saturating_mul(100)
+            .checked_div(u128::from(self.total_bytes))
+            .unwrap_or_default();
+        u8::try_from(percent).unwrap_or(100)
+    }
+}
+
+/// Counts the bytes pulled through a reader, the archive streams themselves
+/// have no notion of how far they are.
+struct CountingReader<'a, R> {
+    inner: R,
+    count: &'a Cell<u64>,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get().saturating_add(n as u64));
+        Ok(n)
+    }
+}
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+const ARCHIVE_EXTENSIONS: &[(&str, ArchiveFormat)] = &[
+    (".zip", ArchiveFormat::Zip),
+    (".tar.gz", ArchiveFormat::TarGz),
+    (".tgz", ArchiveFormat::TarGz),
+    (".tar.zst", ArchiveFormat::TarZst),
+    (".tzst", ArchiveFormat::TarZst),
+];
+
+/// Archive formats the Explorer builds are published in. A zip may wrap a
+/// single `.tar`, that is handled inside the `Zip` format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+    TarZst,
+}
+
+impl ArchiveFormat {
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        ARCHIVE_EXTENSIONS
+            .iter()
+            .find(|(extension, _)| name.ends_with(extension))
+            .map(|(_, format)| *format)
+    }
+
+    fn from_magic(header: &[u8]) -> Option<Self> {
+        if header.starts_with(ZIP_MAGIC) {
+            Some(Self::Zip)
+        } else if header.starts_with(GZIP_MAGIC) {
+            Some(Self::TarGz)
+        } else if header.starts_with(ZSTD_MAGIC) {
+            Some(Self::TarZst)
+        } else {
+            None
+        }
+    }
+
+    /// Magic bytes win over the extension: the download is always stored
+    /// under the same file name whatever the published format is.
+    pub fn detect(path: &Path) -> DCLErrorTyped<Self> {
+        let mut header = Vec::with_capacity(ZSTD_MAGIC.len());
+        File::open(path)?
+            .take(ZSTD_MAGIC.len() as u64)
+            .read_to_end(&mut header)?;
+
+        Self::from_magic(&header)
+            .or_else(|| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(Self::from_file_name)
+            })
+            .ok_or_else(|| DCLError::E1002_CORRUPTED_ARCHIVE {
+                file_path: path.to_string_lossy().into_owned(),
+                inner_error: anyhow!("Unknown archive format"),
+            })
+    }
+}
+
+/// Extracts the downloaded build into `destination_path`.
+///
+/// The archive is read from disk and streamed straight into the tar reader
+/// (when there is one), so memory usage stays bounded by the IO buffers no
+/// matter how large the build is. `on_progress` is called after every
+/// extracted entry, throttling is up to the caller.
+pub fn decompress_file(
+    source_path: &Path,
+    destination_path: &Path,
+    on_progress: &mut dyn FnMut(ExtractProgress),
+) -> DCLErrorResult {
     if !source_path.exists() {
         return DCLError::E1001_FILE_NOT_FOUND {
             expected_path: Some(source_path.to_string_lossy().into_owned()),
@@ -16,60 +127,516 @@ pub fn decompress_file(source_path: &PathBuf, destination_path: &PathBuf) -> DCL
     }
 
     fs::create_dir_all(destination_path)?;
+    let destination_path = destination_path.canonicalize()?;
 
-    let zip_data = fs::read(source_path)?;
-    let cursor = Cursor::new(zip_data);
+    let format = ArchiveFormat::detect(source_path)?;
+    log::info!("Extracting {} as {:?}", source_path.display(), format);
 
-    let mut zip = ZipArchive::new(cursor)?;
+    let bytes_read = Cell::new(0);
+    match format {
+        ArchiveFormat::Zip => {
+            let zip_file = BufReader::new(File::open(source_path)?);
+            let mut zip = ZipArchive::new(zip_file)?;
 
-    let mut tar_file_data: Option<Vec<u8>> = None;
+            match find_tar_index(&mut zip)? {
+                Some(index) => {
+                    let tar_file = zip.by_index(index)?;
+                    let total_bytes = tar_file.size();
+                    let tar_data = CountingReader {
+                        inner: tar_file,
+                        count: &bytes_read,
+                    };
+                    extract_tar(
+                        tar_data,
+                        &bytes_read,
+                        total_bytes,
+                        &destination_path,
+                        on_progress,
+                    )
+                }
+                // If no TAR file found, extract the other files
+                None => extract_zip(&mut zip, &destination_path, on_progress),
+            }
+        }
+        // Progress of compressed tars is measured on the compressed bytes,
+        // the uncompressed size isn't known upfront.
+        ArchiveFormat::TarGz => {
+            let file = File::open(source_path)?;
+            let total_bytes = file.metadata()?.len();
+            let compressed = BufReader::new(CountingReader {
+                inner: file,
+                count: &bytes_read,
+            });
+            extract_tar(
+                GzDecoder::new(compressed),
+                &bytes_read,
+                total_bytes,
+                &destination_path,
+                on_progress,
+            )
+        }
+        ArchiveFormat::TarZst => {
+            let file = File::open(source_path)?;
+            let total_bytes = file.metadata()?.len();
+            let compressed = CountingReader {
+                inner: file,
+                count: &bytes_read,
+            };
+            extract_tar(
+                zstd::stream::read::Decoder::new(compressed)?,
+                &bytes_read,
+                total_bytes,
+                &destination_path,
+                on_progress,
+            )
+        }
+    }
+}
 
-    // Iterate through the ZIP files to find the tar file
+fn find_tar_index<R: Read + Seek>(zip: &mut ZipArchive<R>) -> DCLErrorTyped<Option<usize>> {
     for i in 0..zip.len() {
-        let mut file = zip.by_index(i)?;
+        let file = zip.by_index_raw(i)?;
         if file.name().to_lowercase().ends_with(".tar") {
-            let mut tar_data = Vec::new();
-            file.read_to_end(&mut tar_data)?;
-            tar_file_data = Some(tar_data);
-            break;
+            return Ok(Some(i));
         }
     }
+    Ok(None)
+}
 
-    // If a TAR file was found inside the ZIP, extract it
-    if let Some(tar_file_data) = tar_file_data {
-        let mut archive = Archive::new(tar_file_data.as_slice());
+/// `destination_path` must be canonicalized.
+/// `bytes_read` is advanced by whichever `CountingReader` the caller placed
+/// under `tar_data`, out of `total_bytes`.
+fn extract_tar<R: Read>(
+    tar_data: R,
+    bytes_read: &Cell<u64>,
+    total_bytes: u64,
+    destination_path: &Path,
+    on_progress: &mut dyn FnMut(ExtractProgress),
+) -> DCLErrorResult {
+    let mut archive = Archive::new(tar_data);
+    let mut progress = ExtractProgress {
+        total_bytes,
+        ..ExtractProgress::default()
+    };
 
-        // Extract the TAR contents
-        for entry in archive.entries()? {
-            let mut entry = entry?;
-            let path = entry.path()?.to_path_buf();
-            let output_path = Path::new(destination_path).join(path);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_path_buf();
+        let entry_name = path.to_string_lossy().into_owned();
 
-            if entry.header().entry_type().is_dir() {
-                fs::create_dir_all(output_path)?;
+        let relative_path = enclosed_path(&path)
+            .ok_or_else(|| unsafe_entry(&entry_name, "path escapes the install directory"))?;
+
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_symlink() || entry_type.is_hard_link() {
+            let target = entry
+                .link_name()?
+                .ok_or_else(|| unsafe_entry(&entry_name, "link without a target"))?
+                .into_owned();
+            let parent = prepare_parent(destination_path, &relative_path, &entry_name)?;
+            let stays_inside = if entry_type.is_symlink() {
+                symlink_stays_inside(destination_path, &parent, &target)
             } else {
-                let mut output_file = fs::File::create(output_path)?;
-                std::io::copy(&mut entry, &mut output_file)?;
+                // hard link targets are relative to the archive root
+                enclosed_path(&target).is_some()
+            };
+            if !stays_inside {
+                return Err(unsafe_entry(
+                    &entry_name,
+                    "link points outside the install directory",
+                ));
             }
         }
-    } else {
-        // If no TAR file found, extract the other files
-        for i in 0..zip.len() {
-            let mut file = zip.by_index(i)?;
-            let output_path = Path::new(destination_path).join(file.name());
 
-            // Create directory if it's a directory
-            if file.is_dir() {
-                fs::create_dir_all(&output_path)?;
-            } else {
-                let mut content = Vec::new();
-                file.read_to_end(&mut content)?;
+        // `unpack_in` refuses to write through symlinks that lead outside
+        // `destination_path` and keeps the permission bits of the entry.
+        if !entry.unpack_in(destination_path)? {
+            return Err(unsafe_entry(
+                &entry_name,
+                "path escapes the install directory",
+            ));
+        }
+
+        progress.bytes_processed = bytes_read.get();
+        progress.entries_processed = progress.entries_processed.saturating_add(1);
+        on_progress(progress);
+    }
+
+    Ok(())
+}
+
+/// `destination_path` must be canonicalized.
+fn extract_zip<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    destination_path: &Path,
+    on_progress: &mut dyn FnMut(ExtractProgress),
+) -> DCLErrorResult {
+    let mut progress = ExtractProgress::default();
+    for i in 0..zip.len() {
+        let size = zip.by_index_raw(i)?.size();
+        progress.total_bytes = progress.total_bytes.saturating_add(size);
+    }
+
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i)?;
+        extract_zip_entry(&mut file, destination_path)?;
+
+        progress.bytes_processed = progress.bytes_processed.saturating_add(file.size());
+        progress.entries_processed = progress.entries_processed.saturating_add(1);
+        on_progress(progress);
+    }
+
+    Ok(())
+}
 
-                let mut output_file = fs::File::create(output_path)?;
-                output_file.write_all(&content)?;
+fn extract_zip_entry<R: Read>(
+    file: &mut ZipFile<'_, R>,
+    destination_path: &Path,
+) -> DCLErrorResult {
+    let entry_name = file.name().to_owned();
+
+    let relative_path = file
+        .enclosed_name()
+        .and_then(|p| enclosed_path(&p))
+        .ok_or_else(|| unsafe_entry(&entry_name, "path escapes the install directory"))?;
+
+    if file.is_dir() {
+        prepare_parent(destination_path, &relative_path, &entry_name)?;
+        fs::create_dir_all(destination_path.join(&relative_path))?;
+        return Ok(());
+    }
+
+    let parent = prepare_parent(destination_path, &relative_path, &entry_name)?;
+    let output_path = destination_path.join(&relative_path);
+
+    if file.is_symlink() {
+        let mut target = String::new();
+        file.by_ref()
+            .take(MAX_SYMLINK_TARGET_LEN)
+            .read_to_string(&mut target)?;
+        let target = PathBuf::from(target);
+        if !symlink_stays_inside(destination_path, &parent, &target) {
+            return Err(unsafe_entry(
+                &entry_name,
+                "link points outside the install directory",
+            ));
+        }
+        return create_symlink(&target, &output_path);
+    }
+
+    write_entry(file, &output_path)?;
+
+    #[cfg(unix)]
+    if let Some(mode) = file.unix_mode() {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&output_path, fs::Permissions::from_mode(mode & 0o777))?;
+    }
+
+    Ok(())
+}
+
+const MAX_SYMLINK_TARGET_LEN: u64 = 4096;
+
+fn unsafe_entry(entry: &str, reason: &'static str) -> DCLError {
+    DCLError::E1008_UNSAFE_ARCHIVE_ENTRY {
+        entry: entry.to_owned(),
+        reason,
+    }
+}
+
+/// Relative form of an archive path, or `None` when it is absolute or any
+/// component climbs above the archive root.
+pub(crate) fn enclosed_path(path: &Path) -> Option<PathBuf> {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => result.push(name),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(result)
+}
+
+/// Creates the parent directory of the entry and returns its real location,
+/// which must stay inside `destination_path` even if earlier entries created
+/// symlinked directories on the way.
+fn prepare_parent(
+    destination_path: &Path,
+    relative_path: &Path,
+    entry_name: &str,
+) -> DCLErrorTyped<PathBuf> {
+    let parent = destination_path
+        .join(relative_path)
+        .parent()
+        .map_or_else(|| destination_path.to_path_buf(), Path::to_path_buf);
+    fs::create_dir_all(&parent)?;
+
+    let parent = parent.canonicalize()?;
+    if !parent.starts_with(destination_path) {
+        return Err(unsafe_entry(
+            entry_name,
+            "path escapes the install directory",
+        ));
+    }
+    Ok(parent)
+}
+
+/// Resolves a relative symlink `target` from the real `parent` directory.
+/// Going up (`..`) is only allowed across real directories: stepping back
+/// out of another symlink could land anywhere.
+fn symlink_stays_inside(destination_path: &Path, parent: &Path, target: &Path) -> bool {
+    let mut current = parent.to_path_buf();
+    let mut through_unknown = false;
+
+    for component in target.components() {
+        match component {
+            Component::Normal(name) => {
+                current.push(name);
+                if !current.symlink_metadata().is_ok_and(|m| m.is_dir()) {
+                    through_unknown = true;
+                }
+            }
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if through_unknown || !current.pop() {
+                    return false;
+                }
             }
+            Component::RootDir | Component::Prefix(_) => return false,
+        }
+        if !current.starts_with(destination_path) {
+            return false;
         }
     }
 
+    true
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path) -> DCLErrorResult {
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn create_symlink(target: &Path, link: &Path) -> DCLErrorResult {
+    log::warn!(
+        "Skipping symlink {} -> {}, symlinks are not supported on this platform",
+        link.display(),
+        target.display()
+    );
     Ok(())
 }
+
+fn write_entry<R: Read>(entry: &mut R, output_path: &Path) -> DCLErrorResult {
+    let mut output_file = BufWriter::new(File::create(output_path)?);
+    io::copy(entry, &mut output_file)?;
+    output_file.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_path;
+    use rstest::rstest;
+    use zip::write::{SimpleFileOptions, ZipWriter};
+
+    fn tar_with(files: &[(&str, &[u8])]) -> anyhow::Result<Vec<u8>> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *content)?;
+        }
+        Ok(builder.into_inner()?)
+    }
+
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) -> anyhow::Result<()> {
+        let mut zip = ZipWriter::new(File::create(path)?);
+        for (name, content) in files {
+            zip.start_file(*name, SimpleFileOptions::default())?;
+            zip.write_all(content)?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+
+    #[test]
+    fn extracts_the_inner_tar() -> anyhow::Result<()> {
+        let root = temp_path("dcl-decompress");
+        fs::create_dir_all(&root)?;
+        let tar = tar_with(&[
+            ("build/Explorer.exe", b"binary"),
+            ("build/data.bin", b"data"),
+        ])?;
+        let source = root.join("decentraland.zip");
+        write_zip(&source, &[("build.tar", &tar)])?;
+
+        let destination = root.join("out");
+        let mut reported = Vec::new();
+        decompress_file(&source, &destination, &mut |p| reported.push(p))?;
+
+        assert_eq!(reported.len(), 2);
+        assert!(reported.windows(2).all(|w| {
+            w.first().map(|p| p.bytes_processed) <= w.last().map(|p| p.bytes_processed)
+        }));
+        assert!(reported.iter().all(|p| p.total_bytes == tar.len() as u64));
+        assert_eq!(fs::read(destination.join("build/Explorer.exe"))?, b"binary");
+        assert_eq!(fs::read(destination.join("build/data.bin"))?, b"data");
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn extracts_plain_zip_entries() -> anyhow::Result<()> {
+        let root = temp_path("dcl-decompress");
+        fs::create_dir_all(&root)?;
+        let source = root.join("decentraland.zip");
+        write_zip(&source, &[("Explorer.exe", b"binary")])?;
+
+        let destination = root.join("out");
+        let mut last = ExtractProgress::default();
+        decompress_file(&source, &destination, &mut |p| last = p)?;
+
+        assert_eq!(last.entries_processed, 1);
+        assert_eq!(last.percent(), 100);
+        assert_eq!(fs::read(destination.join("Explorer.exe"))?, b"binary");
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    fn tar_with_symlink(file: &str, link: &str, target: &str) -> anyhow::Result<Vec<u8>> {
+        let mut builder = tar::Builder::new(Vec::new());
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(6);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder.append_data(&mut header, file, &b"binary"[..])?;
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder.append_link(&mut header, link, target)?;
+
+        Ok(builder.into_inner()?)
+    }
+
+    #[test]
+    fn extracts_compressed_tars() -> anyhow::Result<()> {
+        let root = temp_path("dcl-decompress");
+        fs::create_dir_all(&root)?;
+        let tar = tar_with(&[("build/Explorer.exe", b"binary")])?;
+
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        gz.write_all(&tar)?;
+        let zst = zstd::stream::encode_all(tar.as_slice(), 0)?;
+
+        // the download keeps the `.zip` name whatever the format is
+        for (name, content) in [("gz.zip", gz.finish()?), ("zst.zip", zst)] {
+            let source = root.join(name);
+            fs::write(&source, content)?;
+
+            let destination = root.join(format!("{}-out", name));
+            let mut last = ExtractProgress::default();
+            decompress_file(&source, &destination, &mut |p| last = p)?;
+
+            assert_eq!(last.entries_processed, 1);
+            assert_eq!(fs::read(destination.join("build/Explorer.exe"))?, b"binary");
+        }
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("Decentraland_windows64.zip", Some(ArchiveFormat::Zip))]
+    #[case("Decentraland_macos.tar.gz", Some(ArchiveFormat::TarGz))]
+    #[case("Decentraland_macos.TAR.ZST", Some(ArchiveFormat::TarZst))]
+    #[case("Decentraland_macos.tar", None)]
+    fn detects_format_by_file_name(#[case] name: &str, #[case] expected: Option<ArchiveFormat>) {
+        assert_eq!(ArchiveFormat::from_file_name(name), expected);
+    }
+
+    #[test]
+    fn unknown_format_is_a_corrupted_archive() -> anyhow::Result<()> {
+        let path = temp_path("dcl-unknown.bin");
+        fs::write(&path, b"not an archive")?;
+
+        let result = ArchiveFormat::detect(&path);
+
+        assert!(matches!(
+            result,
+            Err(DCLError::E1002_CORRUPTED_ARCHIVE { .. })
+        ));
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_zip_entries_outside_the_destination() -> anyhow::Result<()> {
+        let root = temp_path("dcl-decompress");
+        fs::create_dir_all(&root)?;
+        let source = root.join("decentraland.zip");
+        write_zip(&source, &[("../evil.txt", b"evil")])?;
+
+        let result = decompress_file(&source, &root.join("out"), &mut |_| {});
+
+        assert!(matches!(
+            result,
+            Err(DCLError::E1008_UNSAFE_ARCHIVE_ENTRY { .. })
+        ));
+        assert!(!root.join("evil.txt").exists());
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_tar_symlinks_outside_the_destination() -> anyhow::Result<()> {
+        let root = temp_path("dcl-decompress");
+        fs::create_dir_all(&root)?;
+        let tar = tar_with_symlink("build/Explorer", "build/escape", "../../..")?;
+        let source = root.join("decentraland.zip");
+        write_zip(&source, &[("build.tar", &tar)])?;
+
+        let destination = root.join("out");
+        let result = decompress_file(&source, &destination, &mut |_| {});
+
+        assert!(matches!(
+            result,
+            Err(DCLError::E1008_UNSAFE_ARCHIVE_ENTRY { .. })
+        ));
+        assert!(destination.join("build/escape").symlink_metadata().is_err());
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_executable_bits_and_inner_symlinks() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = temp_path("dcl-decompress");
+        fs::create_dir_all(&root)?;
+        let tar = tar_with_symlink("build/Explorer", "build/Current", "Explorer")?;
+        let source = root.join("decentraland.zip");
+        write_zip(&source, &[("build.tar", &tar)])?;
+
+        let destination = root.join("out");
+        decompress_file(&source, &destination, &mut |_| {})?;
+
+        let mode = fs::metadata(destination.join("build/Explorer"))?
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o111, 0o111);
+        assert_eq!(
+            fs::read_link(destination.join("build/Current"))?,
+            PathBuf::from("Explorer")
+        );
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+}
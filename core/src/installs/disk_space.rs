@@ -0,0 +1,82 @@
+//! Free space checks done before a build is downloaded, so that a full disk
+//! is reported up front instead of halfway through the download or the
+//! extraction.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sysinfo::Disks;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpaceRequirement {
+    pub download_size: u64,
+    pub installed_size: u64,
+}
+
+impl SpaceRequirement {
+    /// The archive stays on disk until it is fully extracted.
+    pub const fn total(&self) -> u64 {
+        self.download_size.saturating_add(self.installed_size)
+    }
+}
+
+/// Free space of the volume `path` is on, `None` if it cannot be determined.
+pub fn available_space(path: &Path) -> Option<u64> {
+    let path = existing_ancestor(path)?;
+    let disks = Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().components().count())
+        .map(sysinfo::Disk::available_space)
+}
+
+fn existing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find_map(|ancestor| fs::canonicalize(ancestor).ok())
+}
+
+/// Size of the files under `path`, symlinks are not followed.
+pub fn directory_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => directory_size(&entry.path()),
+            Ok(file_type) if file_type.is_file() => entry.metadata().map_or(0, |m| m.len()),
+            _ => 0,
+        })
+        .fold(0, u64::saturating_add)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_path;
+
+    #[test]
+    fn archive_is_counted_with_the_build() {
+        let requirement = SpaceRequirement {
+            download_size: 100,
+            installed_size: 250,
+        };
+        assert_eq!(requirement.total(), 350);
+    }
+
+    #[test]
+    fn directory_size_counts_nested_files() -> std::io::Result<()> {
+        let root = temp_path("dcl-disk-space");
+        fs::create_dir_all(root.join("nested"))?;
+        fs::write(root.join("a.bin"), [0u8; 10])?;
+        fs::write(root.join("nested/b.bin"), [0u8; 5])?;
+
+        assert_eq!(directory_size(&root), 15);
+        assert!(available_space(&root.join("not/created/yet")).is_some());
+
+        fs::remove_dir_all(root)
+    }
+}
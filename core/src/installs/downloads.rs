@@ -1,20 +1,35 @@
 use futures_util::StreamExt;
-use reqwest::Client;
+use reqwest::header::{
+    CONTENT_LENGTH, CONTENT_RANGE, ETAG, HeaderMap, IF_RANGE, LAST_MODIFIED, RANGE,
+};
+use reqwest::{Client, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::cmp::min;
 use std::fmt::{Display, Formatter};
-use std::fs::File;
-use std::io::Write;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use crate::analytics::Analytics;
 use crate::analytics::event::Event;
 use crate::channel::EventChannel;
+use crate::flow_control::{Cancelled, FlowControl};
+use crate::mirrors::{self, MirrorFailure, is_mirror_http_failure};
 use crate::types::{BuildType, Status, Step};
 use anyhow::Context;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::time::timeout;
 
+mod parallel;
+mod throttle;
+
+use parallel::ParallelDownload;
+use throttle::RateLimiter;
+pub use throttle::download_rate_limit;
+
 pub type DownloadFileResult = std::result::Result<(), DownloadFileError>;
 
 #[derive(Debug, thiserror::Error)]
@@ -42,6 +57,10 @@ pub enum DownloadFileError {
     ContentLengthNotFound {
         url: String,
     },
+    HttpCode {
+        url: String,
+        code: u16,
+    },
     FileCreateFailed {
         #[source]
         source: std::io::Error,
@@ -49,6 +68,15 @@ pub enum DownloadFileError {
     },
     FileIncomplete(#[from] FileIncompleteError),
     NetworkTimeout,
+    ChecksumMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+    Cancelled(#[from] Cancelled),
+    /// The connection was closed for a pause, the download continues with a
+    /// `Range` request once resumed.
+    Paused,
 }
 
 impl Display for DownloadFileError {
@@ -63,18 +91,364 @@ impl Display for DownloadFileError {
                 "Download failed due missing content length from url: {}",
                 url
             ),
+            Self::HttpCode { url, code } => write!(
+                f,
+                "Download failed due unexpected http code {} from url: {}",
+                code, url
+            ),
             Self::FileCreateFailed { source, file_path } => write!(
                 f,
                 "Download failed due file creation failed: {}, source {}",
                 file_path, source
             ),
             Self::NetworkTimeout => write!(f, "Download failed due network timeout"),
+            Self::ChecksumMismatch {
+                url,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Download failed due checksum mismatch from url: {}, expected {}, actual {}",
+                url, expected, actual
+            ),
+            Self::Cancelled(e) => write!(f, "Download {}", e),
+            Self::Paused => write!(f, "Download paused"),
         }
     }
 }
 
+impl MirrorFailure for DownloadFileError {
+    fn is_mirror_failure(&self) -> bool {
+        match self {
+            Self::Network(_)
+            | Self::NetworkTimeout
+            | Self::FileIncomplete(_)
+            | Self::ContentLengthNotFound { .. } => true,
+            Self::HttpCode { code, .. } => is_mirror_http_failure(*code),
+            _ => false,
+        }
+    }
+}
+
+/// Integrity data published alongside a release artifact.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedChecksum {
+    /// Lowercase hex encoded SHA-256 of the whole artifact.
+    pub sha256: String,
+    pub size: Option<u64>,
+}
+
 const PROGRESS_REPORT_STEP: u8 = 25;
 
+// How often the resume sidecar is rewritten while streaming. Losing up to this
+// many bytes on a crash is cheaper than rewriting the sidecar for every chunk.
+const RESUME_PERSIST_STEP: u64 = 4 * 1024 * 1024;
+
+/// Sidecar stored next to a partially downloaded file. It allows the next
+/// attempt to continue with a `Range` request instead of starting from zero,
+/// as long as the server still serves the same content for the same url.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct ResumeSidecar {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    total_size: u64,
+    bytes_written: u64,
+}
+
+impl ResumeSidecar {
+    fn from_response(url: &str, headers: &HeaderMap, total_size: u64) -> Self {
+        let header_value = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(ToOwned::to_owned)
+        };
+
+        Self {
+            url: url.to_owned(),
+            etag: header_value(ETAG),
+            last_modified: header_value(LAST_MODIFIED),
+            total_size,
+            bytes_written: 0,
+        }
+    }
+
+    fn path_for(file_path: &Path) -> PathBuf {
+        let mut name = file_path.as_os_str().to_owned();
+        name.push(".resume.json");
+        PathBuf::from(name)
+    }
+
+    fn load(file_path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(Self::path_for(file_path)).ok()?;
+        serde_json::from_str(&content)
+            .inspect_err(|e| log::warn!("Cannot parse download resume sidecar, ignoring: {e}"))
+            .ok()
+    }
+
+    fn save(&self, file_path: &Path) {
+        let result = serde_json::to_string(self)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| {
+                std::fs::write(Self::path_for(file_path), content).map_err(anyhow::Error::from)
+            });
+        if let Err(e) = result {
+            log::warn!("Cannot write download resume sidecar: {e}");
+        }
+    }
+
+    fn remove(file_path: &Path) {
+        match std::fs::remove_file(Self::path_for(file_path)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("Cannot remove download resume sidecar: {e}"),
+        }
+    }
+
+    /// `If-Range` value; a strong `ETag` is preferred over `Last-Modified`.
+    fn validator(&self) -> Option<&str> {
+        self.etag.as_deref().or(self.last_modified.as_deref())
+    }
+
+    /// Returns the offset to resume from, or `None` when the partial file
+    /// cannot be trusted for `url` and a full download is required.
+    fn resume_offset(&self, url: &str, file_len: u64) -> Option<u64> {
+        if self.url != url || self.validator().is_none() {
+            return None;
+        }
+
+        let offset = min(self.bytes_written, file_len);
+        (offset > 0 && offset < self.total_size).then_some(offset)
+    }
+}
+
+/// Parses the complete length out of `Content-Range: bytes <start>-<end>/<total>`
+/// and checks that the range starts where we asked it to.
+fn content_range_total(headers: &HeaderMap, expected_start: u64) -> Option<u64> {
+    let value = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _end) = range.split_once('-')?;
+    if start.trim().parse::<u64>().ok()? != expected_start {
+        return None;
+    }
+    total.trim().parse().ok()
+}
+
+fn discard_partial(path: &Path) {
+    ResumeSidecar::remove(path);
+    match std::fs::remove_file(path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => log::warn!("Cannot remove partial download: {e}"),
+    }
+}
+
+struct DownloadTarget {
+    file: File,
+    hasher: Sha256,
+    sidecar: Option<ResumeSidecar>,
+    response: Response,
+    total_size: u64,
+    downloaded: u64,
+}
+
+async fn open_download_target(
+    client: &Client,
+    url: &str,
+    path: &Path,
+) -> Result<DownloadTarget, DownloadFileError> {
+    let file_len = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let resumable = ResumeSidecar::load(path)
+        .and_then(|sidecar| Some((sidecar.resume_offset(url, file_len)?, sidecar)));
+
+    if let Some((offset, sidecar)) = resumable {
+        let mut request = client.get(url).header(RANGE, format!("bytes={}-", offset));
+        if let Some(validator) = sidecar.validator() {
+            request = request.header(IF_RANGE, validator);
+        }
+        let res = request.send().await?;
+
+        if res.status() == StatusCode::PARTIAL_CONTENT {
+            if content_range_total(res.headers(), offset) == Some(sidecar.total_size) {
+                log::info!(
+                    "Resuming download of {} from {} of {} bytes",
+                    url,
+                    offset,
+                    sidecar.total_size
+                );
+                let mut file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(path)
+                    .map_err(|source| DownloadFileError::FileCreateFailed {
+                        source,
+                        file_path: path.to_string_lossy().into_owned(),
+                    })?;
+                file.set_len(offset)?;
+
+                // The digest covers the whole artifact, so the bytes kept from
+                // the previous attempt have to be fed in before appending.
+                let mut hasher = Sha256::new();
+                file.seek(SeekFrom::Start(0))?;
+                std::io::copy(&mut (&mut file).take(offset), &mut hasher)?;
+                file.seek(SeekFrom::End(0))?;
+
+                return Ok(DownloadTarget {
+                    file,
+                    hasher,
+                    total_size: sidecar.total_size,
+                    sidecar: Some(ResumeSidecar {
+                        bytes_written: offset,
+                        ..sidecar
+                    }),
+                    response: res,
+                    downloaded: offset,
+                });
+            }
+            log::warn!("Content-Range of the resumed download doesn't match, restarting");
+        } else if res.status().is_success() {
+            // The validator changed or the server ignores ranges: it already
+            // sends the full body, so it can be consumed from scratch.
+            log::info!("Server didn't resume the download of {}, restarting", url);
+            return start_download_target(url, path, res);
+        } else {
+            log::warn!(
+                "Resume request for {} failed with {}, restarting",
+                url,
+                res.status()
+            );
+        }
+    }
+
+    discard_partial(path);
+    let res = client.get(url).send().await?;
+    start_download_target(url, path, res)
+}
+
+fn start_download_target(
+    url: &str,
+    path: &Path,
+    res: Response,
+) -> Result<DownloadTarget, DownloadFileError> {
+    if !res.status().is_success() {
+        return Err(DownloadFileError::HttpCode {
+            url: url.to_owned(),
+            code: res.status().into(),
+        });
+    }
+
+    let total_size =
+        res.content_length()
+            .ok_or_else(|| DownloadFileError::ContentLengthNotFound {
+                url: url.to_owned(),
+            })?;
+
+    let file = File::create(path).map_err(|source| DownloadFileError::FileCreateFailed {
+        source,
+        file_path: path.to_string_lossy().into_owned(),
+    })?;
+
+    // Without a validator there is no way to tell that a later response
+    // belongs to the same build, so such downloads are never resumed.
+    let sidecar = Some(ResumeSidecar::from_response(url, res.headers(), total_size))
+        .filter(|s| s.validator().is_some());
+    match &sidecar {
+        Some(sidecar) => sidecar.save(path),
+        None => ResumeSidecar::remove(path),
+    }
+
+    Ok(DownloadTarget {
+        file,
+        hasher: Sha256::new(),
+        sidecar,
+        response: res,
+        total_size,
+        downloaded: 0,
+    })
+}
+
+/// Size announced by a `HEAD` request, `None` if the server doesn't tell.
+/// The request goes to the mirrors while origins fail.
+pub async fn remote_size(url: &str) -> Option<u64> {
+    mirrors::with_fallback(url, |url| async move { head_size(&url).await })
+        .await
+        .inspect_err(|e| log::warn!("Cannot get the size of {}: {}", url, e))
+        .ok()
+        .flatten()
+}
+
+async fn head_size(url: &str) -> Result<Option<u64>, DownloadFileError> {
+    let res = Client::new().head(url).send().await?;
+    if !res.status().is_success() {
+        return Err(DownloadFileError::HttpCode {
+            url: url.to_owned(),
+            code: res.status().into(),
+        });
+    }
+    // `Response::content_length` is the body size, always 0 for `HEAD`.
+    Ok(res
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok()))
+}
+
+fn verify_expected_size(
+    url: &str,
+    path: &Path,
+    checksum: Option<&ExpectedChecksum>,
+    total_size: u64,
+) -> DownloadFileResult {
+    match checksum.and_then(|c| c.size) {
+        Some(expected_size) if expected_size != total_size => {
+            discard_partial(path);
+            Err(DownloadFileError::ChecksumMismatch {
+                url: url.to_owned(),
+                expected: format!("size {}", expected_size),
+                actual: format!("size {}", total_size),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+fn verify_checksum(
+    url: &str,
+    path: &Path,
+    checksum: Option<&ExpectedChecksum>,
+    hasher: Sha256,
+) -> DownloadFileResult {
+    let Some(expected) = checksum else {
+        return Ok(());
+    };
+
+    let actual = format!("{:x}", hasher.finalize());
+    if !actual.eq_ignore_ascii_case(&expected.sha256) {
+        // A corrupted artifact must not be resumed, the retry starts over.
+        discard_partial(path);
+        return Err(DownloadFileError::ChecksumMismatch {
+            url: url.to_owned(),
+            expected: expected.sha256.clone(),
+            actual,
+        });
+    }
+
+    log::info!("Checksum of {} verified: {}", url, actual);
+    Ok(())
+}
+
+fn progress_percent(downloaded: u64, total_size: u64) -> u8 {
+    #[allow(
+        clippy::arithmetic_side_effects,
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    let progress: u8 = ((downloaded as f64 / total_size as f64) * 100.0) as u8;
+    progress
+}
+
 async fn track_download_progress(
     analytics: Arc<Mutex<Analytics>>,
     url: String,
@@ -93,53 +467,156 @@ async fn track_download_progress(
         .await;
 }
 
+/// A paused download closes its connection and keeps its partial file, a
+/// cancelled one removes the partial file so nothing is resumed from it.
 #[allow(clippy::future_not_send)]
 pub async fn download_file<T: EventChannel>(
     url: &str,
     path: &str,
     channel: &T,
     build_type: &BuildType,
+    checksum: Option<&ExpectedChecksum>,
+    analytics: Arc<Mutex<Analytics>>,
+    control: &FlowControl,
+) -> DownloadFileResult {
+    let result = mirrors::with_fallback(url, |url| {
+        download_file_from(
+            url,
+            path,
+            channel,
+            build_type,
+            checksum,
+            analytics.clone(),
+            control,
+        )
+    })
+    .await;
+
+    if let Err(DownloadFileError::Cancelled(_)) = &result {
+        log::info!("Download of {} cancelled, removing the partial file", url);
+        discard_partial(Path::new(path));
+    }
+    result
+}
+
+#[allow(clippy::future_not_send)]
+async fn download_file_from<T: EventChannel>(
+    url: String,
+    path: &str,
+    channel: &T,
+    build_type: &BuildType,
+    checksum: Option<&ExpectedChecksum>,
     analytics: Arc<Mutex<Analytics>>,
+    control: &FlowControl,
+) -> DownloadFileResult {
+    control.check()?;
+
+    let url = url.as_str();
+    let file_path = Path::new(path);
+    let mut limiter = RateLimiter::from_settings();
+
+    // A partial single stream download is resumed rather than restarted in
+    // chunks.
+    if let Some(options) = ParallelDownload::from_args()
+        && ResumeSidecar::load(file_path).is_none()
+        && let Some(result) = parallel::download(
+            url,
+            file_path,
+            channel,
+            build_type,
+            checksum,
+            analytics.clone(),
+            options,
+            limiter.as_mut(),
+            control,
+        )
+        .await
+    {
+        return result;
+    }
+
+    loop {
+        let result = download_file_single_stream(
+            url,
+            file_path,
+            channel,
+            build_type,
+            checksum,
+            analytics.clone(),
+            limiter.as_mut(),
+            control,
+        )
+        .await;
+        if !matches!(result, Err(DownloadFileError::Paused)) {
+            return result;
+        }
+        // An idle connection would be dropped by the server, and the mirror
+        // taken for a failing one
+        log::info!("Download of {} paused, the connection is closed", url);
+        control.checkpoint().await?;
+        log::info!("Download of {} resumed", url);
+    }
+}
+
+#[allow(clippy::future_not_send, clippy::too_many_arguments)]
+async fn download_file_single_stream<T: EventChannel>(
+    url: &str,
+    file_path: &Path,
+    channel: &T,
+    build_type: &BuildType,
+    checksum: Option<&ExpectedChecksum>,
+    analytics: Arc<Mutex<Analytics>>,
+    mut limiter: Option<&mut RateLimiter>,
+    control: &FlowControl,
 ) -> DownloadFileResult {
     let client = Client::new();
 
-    let res = client.get(url).send().await?;
-    let total_size =
-        res.content_length()
-            .ok_or_else(|| DownloadFileError::ContentLengthNotFound {
-                url: url.to_owned(),
-            })?;
+    let DownloadTarget {
+        mut file,
+        mut hasher,
+        mut sidecar,
+        response: res,
+        total_size,
+        mut downloaded,
+    } = open_download_target(&client, url, file_path).await?;
+
+    verify_expected_size(url, file_path, checksum, total_size)?;
 
     // We don't want to send too many analytics events, so we only report every
     // PROGRESS_REPORT_STEP percent — at most 4 events per download.
     let mut next_report_at: u8 = PROGRESS_REPORT_STEP;
     let mut tasks = Vec::new();
 
-    let mut downloaded: u64 = 0;
-    {
-        let mut file =
-            File::create(path).map_err(|source| DownloadFileError::FileCreateFailed {
-                source,
-                file_path: path.to_owned(),
-            })?;
+    let mut persisted_at = downloaded;
+    let streamed: DownloadFileResult = async {
         let mut stream = res.bytes_stream();
 
         loop {
+            control.check()?;
+            if control.is_paused() {
+                return Err(DownloadFileError::Paused);
+            }
             match timeout(Duration::from_secs(15), stream.next()).await {
                 Ok(Some(item)) => {
                     let chunk = item?;
                     file.write_all(&chunk)?;
+                    hasher.update(&chunk);
+                    if let Some(limiter) = &mut limiter {
+                        limiter.consume(chunk.len() as u64).await;
+                    }
 
                     let new = min(downloaded.saturating_add(chunk.len() as u64), total_size);
                     downloaded = new;
 
-                    #[allow(
-                        clippy::arithmetic_side_effects,
-                        clippy::cast_precision_loss,
-                        clippy::cast_possible_truncation,
-                        clippy::cast_sign_loss
-                    )]
-                    let progress: u8 = ((downloaded as f64 / total_size as f64) * 100.0) as u8;
+                    if let Some(sidecar) = &mut sidecar
+                        && downloaded.saturating_sub(persisted_at) >= RESUME_PERSIST_STEP
+                    {
+                        sidecar.bytes_written = downloaded;
+                        sidecar.save(file_path);
+                        persisted_at = downloaded;
+                    }
+
+                    let progress = progress_percent(downloaded, total_size);
 
                     if next_report_at < 100 && progress >= next_report_at {
                         next_report_at = next_report_at.saturating_add(PROGRESS_REPORT_STEP);
@@ -173,17 +650,38 @@ pub async fn download_file<T: EventChannel>(
         }
 
         file.sync_all()?;
+        Ok(())
     }
+    .await;
+
+    let real_size = std::fs::metadata(file_path)?.len();
+    let incomplete = real_size != total_size;
+
+    if let Some(sidecar) = &mut sidecar {
+        if streamed.is_err() && real_size < total_size {
+            // Keep what we have so the next attempt continues from here.
+            sidecar.bytes_written = min(downloaded, real_size);
+            sidecar.save(file_path);
+        } else if !incomplete || real_size > total_size {
+            ResumeSidecar::remove(file_path);
+        } else {
+            sidecar.bytes_written = real_size;
+            sidecar.save(file_path);
+        }
+    }
+
+    streamed?;
 
-    let metadata = std::fs::metadata(path)?;
-    if metadata.len() != total_size {
+    if incomplete {
         return Err(FileIncompleteError {
             expected_size: total_size,
-            real_size: metadata.len(),
+            real_size,
         }
         .into());
     }
 
+    verify_checksum(url, file_path, checksum, hasher)?;
+
     for task in tasks {
         task.await.context("Failed to await analytics task")?;
     }
@@ -192,3 +690,245 @@ pub async fn download_file<T: EventChannel>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::Analytics;
+    use crate::test_utils::{Reply, StandIn, temp_path};
+
+    struct NullChannel;
+
+    impl EventChannel for NullChannel {
+        fn send(&self, _status: Status) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    const ETAG_VALUE: &str = "\"build-etag\"";
+
+    fn sidecar(url: &str, bytes_written: u64) -> ResumeSidecar {
+        ResumeSidecar {
+            url: url.to_owned(),
+            etag: Some(ETAG_VALUE.to_owned()),
+            last_modified: None,
+            total_size: 100,
+            bytes_written,
+        }
+    }
+
+    fn analytics() -> Arc<Mutex<Analytics>> {
+        Arc::new(Mutex::new(Analytics::new(None)))
+    }
+
+    #[test]
+    fn resume_offset_requires_same_url_and_validator() {
+        assert_eq!(sidecar("a", 40).resume_offset("a", 60), Some(40));
+        assert_eq!(sidecar("a", 40).resume_offset("b", 60), None);
+
+        let no_validator = ResumeSidecar {
+            etag: None,
+            ..sidecar("a", 40)
+        };
+        assert_eq!(no_validator.resume_offset("a", 60), None);
+    }
+
+    #[test]
+    fn resume_offset_never_exceeds_the_file_on_disk() {
+        assert_eq!(sidecar("a", 40).resume_offset("a", 25), Some(25));
+        assert_eq!(sidecar("a", 40).resume_offset("a", 0), None);
+        assert_eq!(sidecar("a", 100).resume_offset("a", 100), None);
+    }
+
+    #[test]
+    fn content_range_total_checks_the_start() -> anyhow::Result<()> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_RANGE, "bytes 40-99/100".parse()?);
+        assert_eq!(content_range_total(&headers, 40), Some(100));
+        assert_eq!(content_range_total(&headers, 0), None);
+        Ok(())
+    }
+
+    // The first response drops the connection halfway through the body, the
+    // second one must be a range request continuing from the bytes on disk.
+    #[tokio::test]
+    async fn download_resumes_after_connection_drop() -> anyhow::Result<()> {
+        let body: Vec<u8> = (0..64 * 1024).map(|i: u32| (i % 251) as u8).collect();
+        let half = body.len() / 2;
+
+        let server = StandIn::start(vec![
+            Reply::ranges(body.clone())
+                .header("ETag", ETAG_VALUE)
+                .cut_after(half),
+            Reply::ranges(body.clone()).header("ETag", ETAG_VALUE),
+        ])
+        .await?;
+        let url = server.url("build.zip");
+
+        let path = temp_path("dcl-resume");
+        let path_str = path.to_string_lossy().into_owned();
+        let analytics = analytics();
+
+        let checksum = ExpectedChecksum {
+            sha256: format!("{:x}", Sha256::digest(&body)),
+            size: Some(body.len() as u64),
+        };
+
+        let first = download_file(
+            &url,
+            &path_str,
+            &NullChannel,
+            &BuildType::New,
+            Some(&checksum),
+            analytics.clone(),
+            &FlowControl::default(),
+        )
+        .await;
+        assert!(first.is_err());
+        assert!(ResumeSidecar::load(&path).is_some());
+
+        download_file(
+            &url,
+            &path_str,
+            &NullChannel,
+            &BuildType::New,
+            Some(&checksum),
+            analytics,
+            &FlowControl::default(),
+        )
+        .await?;
+
+        let requests = server.requests().await?;
+        let resumed = requests.get(1).map(String::as_str).unwrap_or_default();
+        assert!(resumed.contains(&format!("range: bytes={}-", half)));
+        assert!(resumed.contains(&format!("if-range: {}", ETAG_VALUE)));
+        assert_eq!(std::fs::read(&path)?, body);
+        assert!(ResumeSidecar::load(&path).is_none());
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    /// Pauses the download on its first progress event, resumed shortly after.
+    struct PausingChannel {
+        control: FlowControl,
+        paused: std::sync::atomic::AtomicBool,
+    }
+
+    impl EventChannel for PausingChannel {
+        fn send(&self, _status: Status) -> anyhow::Result<()> {
+            if !self.paused.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                self.control.pause();
+                let control = self.control.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    control.resume();
+                });
+            }
+            Ok(())
+        }
+    }
+
+    // The paused download closes its connection, once resumed it continues
+    // with a range request from the bytes on disk.
+    #[tokio::test]
+    async fn paused_download_resumes_with_a_range_request() -> anyhow::Result<()> {
+        let body: Vec<u8> = (0..4 * 1024 * 1024).map(|i: u32| (i % 251) as u8).collect();
+        let server = StandIn::start(vec![
+            Reply::ranges(body.clone()).header("ETag", ETAG_VALUE),
+            Reply::ranges(body.clone()).header("ETag", ETAG_VALUE),
+        ])
+        .await?;
+
+        let path = temp_path("dcl-pause");
+        let channel = PausingChannel {
+            control: FlowControl::default(),
+            paused: std::sync::atomic::AtomicBool::new(false),
+        };
+        download_file(
+            &server.url("build.zip"),
+            &path.to_string_lossy(),
+            &channel,
+            &BuildType::New,
+            None,
+            analytics(),
+            &channel.control,
+        )
+        .await?;
+
+        let requests = server.requests().await?;
+        let resumed = requests.get(1).map(String::as_str).unwrap_or_default();
+        assert!(resumed.contains("range: bytes="));
+        assert!(resumed.contains(&format!("if-range: {}", ETAG_VALUE)));
+        assert_eq!(std::fs::read(&path)?, body);
+        assert!(ResumeSidecar::load(&path).is_none());
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cancelled_download_removes_the_partial_file() -> anyhow::Result<()> {
+        let path = temp_path("dcl-cancel");
+        std::fs::write(&path, b"partial")?;
+        ResumeSidecar {
+            url: "http://127.0.0.1:9/build.zip".to_owned(),
+            etag: Some(ETAG_VALUE.to_owned()),
+            last_modified: None,
+            total_size: 100,
+            bytes_written: 7,
+        }
+        .save(&path);
+
+        let control = FlowControl::default();
+        control.cancel();
+        let result = download_file(
+            "http://127.0.0.1:9/build.zip",
+            &path.to_string_lossy(),
+            &NullChannel,
+            &BuildType::New,
+            None,
+            analytics(),
+            &control,
+        )
+        .await;
+
+        assert!(matches!(result, Err(DownloadFileError::Cancelled(_))));
+        assert!(!path.exists());
+        assert!(ResumeSidecar::load(&path).is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checksum_mismatch_discards_the_download() -> anyhow::Result<()> {
+        let body = b"definitely not the published build".to_vec();
+
+        let server = StandIn::start(vec![Reply::ok(body).header("ETag", ETAG_VALUE)]).await?;
+
+        let path = temp_path("dcl-checksum");
+        let checksum = ExpectedChecksum {
+            sha256: format!("{:x}", Sha256::digest(b"the published build")),
+            size: None,
+        };
+
+        let result = download_file(
+            &server.url("build.zip"),
+            &path.to_string_lossy(),
+            &NullChannel,
+            &BuildType::New,
+            Some(&checksum),
+            analytics(),
+            &FlowControl::default(),
+        )
+        .await;
+        server.requests().await?;
+
+        assert!(matches!(
+            result,
+            Err(DownloadFileError::ChecksumMismatch { .. })
+        ));
+        assert!(!path.exists());
+        assert!(ResumeSidecar::load(&path).is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,608 @@
+//! Multi-connection downloads. The file is preallocated and split into ranges
+//! that are fetched concurrently, each one retried on its own. Servers that
+//! don't advertise `Accept-Ranges: bytes` get the single stream download. So
+//! does a download whose chunk keeps failing: the chunks completed from the
+//! start of the file are kept and resumed from.
+
+use std::cell::{Cell, RefCell};
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use futures_util::{StreamExt, TryStreamExt, stream};
+use reqwest::header::{ACCEPT_RANGES, CONTENT_LENGTH, IF_RANGE, RANGE};
+use reqwest::{Client, StatusCode};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+
+use super::throttle::RateLimiter;
+use super::{
+    DownloadFileError, DownloadFileResult, ExpectedChecksum, FileIncompleteError,
+    PROGRESS_REPORT_STEP, ResumeSidecar, content_range_total, discard_partial, progress_percent,
+    track_download_progress, verify_checksum, verify_expected_size,
+};
+use crate::analytics::Analytics;
+use crate::channel::EventChannel;
+use crate::environment::AppEnvironment;
+use crate::flow_control::FlowControl;
+use crate::types::{BuildType, Status, Step};
+
+const DEFAULT_CHUNK_SIZE: u64 = 16 * 1024 * 1024;
+const CHUNK_ATTEMPTS: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParallelDownload {
+    pub connections: usize,
+    pub chunk_size: u64,
+}
+
+impl ParallelDownload {
+    /// Enabled by `--download-connections <n>` with `n` above one.
+    pub fn from_args() -> Option<Self> {
+        AppEnvironment::cmd_args()
+            .download_connections
+            .filter(|connections| *connections > 1)
+            .map(|connections| Self {
+                connections,
+                chunk_size: DEFAULT_CHUNK_SIZE,
+            })
+    }
+
+    fn chunks(&self, total_size: u64) -> Vec<(u64, u64)> {
+        let chunk_size = self.chunk_size.max(1);
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < total_size {
+            let end = start.saturating_add(chunk_size).min(total_size);
+            chunks.push((start, end.saturating_sub(1)));
+            start = end;
+        }
+        chunks
+    }
+}
+
+struct RangeSupport {
+    total_size: u64,
+    /// Validators of the build, its `If-Range` value makes a build replaced
+    /// in the middle of the download fail the chunk instead of mixing two
+    /// builds.
+    resume: ResumeSidecar,
+}
+
+/// `None` when the server doesn't support ranges or refuses `HEAD`, then the
+/// build is downloaded in a single stream. A request that can't be sent is an
+/// error, so that the download moves on to the next mirror.
+async fn probe_ranges(
+    client: &Client,
+    url: &str,
+) -> Result<Option<RangeSupport>, DownloadFileError> {
+    let res = client.head(url).send().await?;
+    if !res.status().is_success() {
+        log::info!(
+            "{} refused to probe ranges: {}, downloading in a single stream",
+            url,
+            res.status()
+        );
+        return Ok(None);
+    }
+
+    let header_value = |name| res.headers().get(name).and_then(|v| v.to_str().ok());
+    if !header_value(ACCEPT_RANGES).is_some_and(|v| v.eq_ignore_ascii_case("bytes")) {
+        log::info!(
+            "{} doesn't accept ranges, downloading in a single stream",
+            url
+        );
+        return Ok(None);
+    }
+
+    // `Response::content_length` is the body size, always 0 for `HEAD`.
+    let Some(total_size) = header_value(CONTENT_LENGTH).and_then(|v| v.parse().ok()) else {
+        return Ok(None);
+    };
+    Ok(Some(RangeSupport {
+        total_size,
+        resume: ResumeSidecar::from_response(url, res.headers(), total_size),
+    }))
+}
+
+/// Progress of all the chunks together.
+struct SharedProgress<'a, T: EventChannel> {
+    url: &'a str,
+    channel: &'a T,
+    build_type: &'a BuildType,
+    analytics: Arc<Mutex<Analytics>>,
+    total_size: u64,
+    downloaded: Cell<u64>,
+    last_sent: Cell<Option<u8>>,
+    next_report_at: Cell<u8>,
+    tasks: RefCell<Vec<JoinHandle<()>>>,
+}
+
+impl<T: EventChannel> SharedProgress<'_, T> {
+    fn add(&self, bytes: u64) -> DownloadFileResult {
+        let downloaded = self
+            .downloaded
+            .get()
+            .saturating_add(bytes)
+            .min(self.total_size);
+        self.downloaded.set(downloaded);
+
+        let progress = progress_percent(downloaded, self.total_size);
+        if self.last_sent.get().is_some_and(|sent| sent >= progress) {
+            return Ok(());
+        }
+        self.last_sent.set(Some(progress));
+
+        let next_report_at = self.next_report_at.get();
+        if next_report_at < 100 && progress >= next_report_at {
+            self.next_report_at
+                .set(next_report_at.saturating_add(PROGRESS_REPORT_STEP));
+            self.tasks
+                .borrow_mut()
+                .push(tokio::spawn(track_download_progress(
+                    self.analytics.clone(),
+                    self.url.to_owned(),
+                    downloaded,
+                    self.total_size,
+                )));
+        }
+
+        let event = Status::State {
+            step: Step::Downloading {
+                progress,
+                build_type: self.build_type.clone(),
+            },
+        };
+        self.channel
+            .send(event)
+            .context("Cannot send event to channel")?;
+        Ok(())
+    }
+}
+
+struct ChunkedDownload<'a, T: EventChannel> {
+    client: Client,
+    path: &'a Path,
+    validator: Option<String>,
+    /// Ranges written entirely.
+    completed: RefCell<Vec<(u64, u64)>>,
+    /// A chunk failed all its attempts.
+    chunk_failed: Cell<bool>,
+    /// Shared by all the chunks, the limit is for the whole download.
+    limiter: Option<Mutex<&'a mut RateLimiter>>,
+    control: &'a FlowControl,
+    progress: SharedProgress<'a, T>,
+}
+
+impl<T: EventChannel> ChunkedDownload<'_, T> {
+    async fn download_chunk(&self, (start, end): (u64, u64)) -> DownloadFileResult {
+        let size = end.saturating_sub(start).saturating_add(1);
+        let mut written = 0;
+        let mut attempt = 1;
+        loop {
+            let result = self
+                .fetch_range(start.saturating_add(written), end, &mut written)
+                .await
+                .and_then(|()| {
+                    if written == size {
+                        Ok(())
+                    } else {
+                        Err(FileIncompleteError {
+                            expected_size: size,
+                            real_size: written,
+                        }
+                        .into())
+                    }
+                });
+            match result {
+                Ok(()) => {
+                    self.completed.borrow_mut().push((start, end));
+                    return Ok(());
+                }
+                Err(e @ DownloadFileError::Cancelled(_)) => return Err(e),
+                // Continues with a new `Range` request once resumed
+                Err(DownloadFileError::Paused) => self.control.checkpoint().await?,
+                Err(e) if attempt < CHUNK_ATTEMPTS => {
+                    log::warn!(
+                        "Chunk {}-{} of {} failed on attempt {}, retrying: {}",
+                        start,
+                        end,
+                        self.progress.url,
+                        attempt,
+                        e
+                    );
+                    attempt = attempt.saturating_add(1);
+                }
+                Err(e) => {
+                    self.chunk_failed.set(true);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Keeps the chunks completed from the start of the file, with the
+    /// sidecar the single stream download resumes from. The file is
+    /// discarded when there is nothing to resume.
+    fn keep_completed(&self, resume: ResumeSidecar) {
+        let mut completed = self.completed.take();
+        completed.sort_unstable();
+        let mut offset: u64 = 0;
+        for (start, end) in completed {
+            if start != offset {
+                break;
+            }
+            offset = end.saturating_add(1);
+        }
+        if offset == 0 || resume.validator().is_none() {
+            discard_partial(self.path);
+            return;
+        }
+
+        let truncated = OpenOptions::new()
+            .write(true)
+            .open(self.path)
+            .and_then(|file| {
+                file.set_len(offset)?;
+                file.sync_all()
+            });
+        match truncated {
+            Ok(()) => ResumeSidecar {
+                bytes_written: offset,
+                ..resume
+            }
+            .save(self.path),
+            Err(e) => {
+                log::warn!("Cannot keep the completed chunks: {}", e);
+                discard_partial(self.path);
+            }
+        }
+    }
+
+    /// Writes `from..=end` at its offset, `written` grows with every piece
+    /// so that a retry continues where this attempt stopped.
+    async fn fetch_range(&self, from: u64, end: u64, written: &mut u64) -> DownloadFileResult {
+        let url = self.progress.url;
+        let mut request = self
+            .client
+            .get(url)
+            .header(RANGE, format!("bytes={}-{}", from, end));
+        if let Some(validator) = &self.validator {
+            request = request.header(IF_RANGE, validator);
+        }
+        let res = request.send().await?;
+
+        if res.status() != StatusCode::PARTIAL_CONTENT {
+            return Err(DownloadFileError::HttpCode {
+                url: url.to_owned(),
+                code: res.status().into(),
+            });
+        }
+        if content_range_total(res.headers(), from) != Some(self.progress.total_size) {
+            return Err(
+                anyhow!("Content-Range of {} doesn't match the requested range", url).into(),
+            );
+        }
+
+        let mut file = OpenOptions::new().write(true).open(self.path)?;
+        file.seek(SeekFrom::Start(from))?;
+
+        let mut remaining = end.saturating_sub(from).saturating_add(1);
+        let mut stream = res.bytes_stream();
+        while remaining > 0 {
+            self.control.check()?;
+            if self.control.is_paused() {
+                file.sync_data()?;
+                return Err(DownloadFileError::Paused);
+            }
+            match timeout(Duration::from_secs(15), stream.next()).await {
+                Ok(Some(item)) => {
+                    let chunk = item?;
+                    let len = usize::try_from(remaining).unwrap_or(usize::MAX);
+                    let piece = chunk.get(..len).unwrap_or(&chunk);
+                    file.write_all(piece)?;
+
+                    let piece_len = piece.len() as u64;
+                    if let Some(limiter) = &self.limiter {
+                        limiter.lock().await.consume(piece_len).await;
+                    }
+                    remaining = remaining.saturating_sub(piece_len);
+                    *written = written.saturating_add(piece_len);
+                    self.progress.add(piece_len)?;
+                }
+                Ok(None) => break,
+                Err(_) => return Err(DownloadFileError::NetworkTimeout),
+            }
+        }
+
+        file.sync_data()?;
+        Ok(())
+    }
+}
+
+/// Returns `None` when the server doesn't support ranges, the file is too
+/// small to be split or a chunk keeps failing, the caller downloads it in a
+/// single stream then.
+#[allow(clippy::future_not_send, clippy::too_many_arguments)]
+pub async fn download<T: EventChannel>(
+    url: &str,
+    path: &Path,
+    channel: &T,
+    build_type: &BuildType,
+    checksum: Option<&ExpectedChecksum>,
+    analytics: Arc<Mutex<Analytics>>,
+    options: ParallelDownload,
+    limiter: Option<&mut RateLimiter>,
+    control: &FlowControl,
+) -> Option<DownloadFileResult> {
+    let client = Client::new();
+    let support = match probe_ranges(&client, url).await {
+        Ok(support) => support?,
+        Err(e) => return Some(Err(e)),
+    };
+    let chunks = options.chunks(support.total_size);
+    if chunks.len() < 2 {
+        return None;
+    }
+
+    log::info!(
+        "Downloading {} in {} chunks over {} connections",
+        url,
+        chunks.len(),
+        options.connections
+    );
+
+    let download = ChunkedDownload {
+        client,
+        path,
+        validator: support.resume.validator().map(ToOwned::to_owned),
+        completed: RefCell::new(Vec::new()),
+        chunk_failed: Cell::new(false),
+        limiter: limiter.map(Mutex::new),
+        control,
+        progress: SharedProgress {
+            url,
+            channel,
+            build_type,
+            analytics: analytics.clone(),
+            total_size: support.total_size,
+            downloaded: Cell::new(0),
+            last_sent: Cell::new(None),
+            next_report_at: Cell::new(PROGRESS_REPORT_STEP),
+            tasks: RefCell::new(Vec::new()),
+        },
+    };
+
+    let result = async {
+        verify_expected_size(url, path, checksum, support.total_size)?;
+
+        // Chunks are written in any order, so a leftover partial download
+        // can't be reused and is replaced by a file of the final size.
+        discard_partial(path);
+        let file = File::create(path).map_err(|source| DownloadFileError::FileCreateFailed {
+            source,
+            file_path: path.to_string_lossy().into_owned(),
+        })?;
+        file.set_len(support.total_size)?;
+        drop(file);
+
+        stream::iter(chunks.into_iter().map(Ok))
+            .try_for_each_concurrent(options.connections, |range| download.download_chunk(range))
+            .await?;
+
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut File::open(path)?, &mut hasher)?;
+        verify_checksum(url, path, checksum, hasher)
+    }
+    .await;
+
+    let fall_back = result.is_err() && download.chunk_failed.get();
+    if fall_back {
+        download.keep_completed(support.resume);
+    } else if result.is_err() {
+        discard_partial(path);
+    }
+
+    let tasks = download.progress.tasks.take();
+    for task in tasks {
+        if let Err(e) = task.await {
+            log::error!("Failed to await analytics task: {:#}", e);
+        }
+    }
+
+    if fall_back {
+        log::warn!(
+            "A chunk of {} keeps failing, downloading the rest in a single stream",
+            url
+        );
+        return None;
+    }
+
+    if result.is_ok() {
+        track_download_progress(
+            analytics,
+            url.to_owned(),
+            support.total_size,
+            support.total_size,
+        )
+        .await;
+    }
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{Reply, StandIn, temp_path};
+
+    struct NullChannel;
+
+    impl EventChannel for NullChannel {
+        fn send(&self, _status: Status) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    const OPTIONS: ParallelDownload = ParallelDownload {
+        connections: 2,
+        chunk_size: 16 * 1024,
+    };
+
+    fn body() -> Vec<u8> {
+        (0..40 * 1024).map(|i: u32| (i % 251) as u8).collect()
+    }
+
+    fn checksum(body: &[u8]) -> ExpectedChecksum {
+        ExpectedChecksum {
+            sha256: format!("{:x}", Sha256::digest(body)),
+            size: Some(body.len() as u64),
+        }
+    }
+
+    async fn run(url: &str, path: &Path, body: &[u8]) -> Option<DownloadFileResult> {
+        run_with(url, path, body, OPTIONS).await
+    }
+
+    async fn run_with(
+        url: &str,
+        path: &Path,
+        body: &[u8],
+        options: ParallelDownload,
+    ) -> Option<DownloadFileResult> {
+        download(
+            url,
+            path,
+            &NullChannel,
+            &BuildType::New,
+            Some(&checksum(body)),
+            Arc::new(Mutex::new(Analytics::new(None))),
+            options,
+            None,
+            &FlowControl::default(),
+        )
+        .await
+    }
+
+    #[test]
+    fn chunks_cover_the_whole_file() {
+        assert_eq!(
+            OPTIONS.chunks(40 * 1024),
+            vec![(0, 16383), (16384, 32767), (32768, 40959)]
+        );
+        assert!(OPTIONS.chunks(0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn downloads_chunks_concurrently() -> anyhow::Result<()> {
+        let body = body();
+        let server = StandIn::start((0..4).map(|_| Reply::ranges(body.clone())).collect()).await?;
+        let path = temp_path("dcl-parallel");
+
+        let result = run(&server.url("build.zip"), &path, &body).await;
+
+        assert!(matches!(result, Some(Ok(()))));
+        let requests = server.requests().await?;
+        assert!(requests.first().is_some_and(|r| r.starts_with("head ")));
+        for range in ["bytes=0-16383", "bytes=16384-32767", "bytes=32768-40959"] {
+            assert!(requests.iter().any(|r| r.contains(range)));
+        }
+        assert_eq!(std::fs::read(&path)?, body);
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_chunk_is_retried_from_where_it_stopped() -> anyhow::Result<()> {
+        let body = body();
+        let server = StandIn::start(vec![
+            Reply::ranges(body.clone()),
+            Reply::ranges(body.clone()).cut_after(1000),
+            Reply::ranges(body.clone()),
+            Reply::ranges(body.clone()),
+            Reply::ranges(body.clone()),
+        ])
+        .await?;
+        let path = temp_path("dcl-parallel");
+
+        let result = run(&server.url("build.zip"), &path, &body).await;
+
+        assert!(matches!(result, Some(Ok(()))));
+        let requests = server.requests().await?;
+        let retried = ["bytes=1000-16383", "bytes=17384-32767", "bytes=33768-40959"];
+        assert!(
+            requests
+                .iter()
+                .any(|r| retried.iter().any(|range| r.contains(range)))
+        );
+        assert_eq!(std::fs::read(&path)?, body);
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    // One connection, so the second chunk fails after the first completed.
+    #[tokio::test]
+    async fn failing_chunk_falls_back_to_a_single_stream() -> anyhow::Result<()> {
+        let body = body();
+        let failure = || Reply::new("500 Internal Server Error", Vec::new());
+        let server = StandIn::start(vec![
+            Reply::ranges(body.clone()).header("ETag", "\"build-etag\""),
+            Reply::ranges(body.clone()),
+            failure(),
+            failure(),
+            failure(),
+        ])
+        .await?;
+        let path = temp_path("dcl-parallel");
+        let options = ParallelDownload {
+            connections: 1,
+            ..OPTIONS
+        };
+
+        let result = run_with(&server.url("build.zip"), &path, &body, options).await;
+
+        assert!(result.is_none());
+        server.requests().await?;
+        assert_eq!(
+            std::fs::read(&path)?,
+            body.get(..16 * 1024).unwrap_or_default()
+        );
+        let sidecar = ResumeSidecar::load(&path);
+        assert_eq!(sidecar.map(|s| s.bytes_written), Some(16 * 1024));
+        discard_partial(&path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn server_without_ranges_is_not_split() -> anyhow::Result<()> {
+        let body = body();
+        let server = StandIn::start(vec![Reply::ok(body.clone())]).await?;
+        let path = temp_path("dcl-parallel");
+
+        let result = run(&server.url("build.zip"), &path, &body).await;
+
+        assert!(result.is_none());
+        assert!(!path.exists());
+        server.requests().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn server_refusing_head_is_not_split() -> anyhow::Result<()> {
+        let body = body();
+        let server = StandIn::start(vec![Reply::new("405 Method Not Allowed", Vec::new())]).await?;
+        let path = temp_path("dcl-parallel");
+
+        let result = run(&server.url("build.zip"), &path, &body).await;
+
+        assert!(result.is_none());
+        assert!(!path.exists());
+        server.requests().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+//! Download rate limit, set with `--download-rate-limit <KiB/s>` or the
+//! `download-rate-limit` key of `config.json`. The flag wins over the config.
+
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::environment::AppEnvironment;
+
+/// Unused budget is kept for at most this long, so a pause doesn't turn
+/// into a burst above the limit afterwards.
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Configured limit in bytes per second, `None` when downloads aren't limited.
+pub fn download_rate_limit() -> Option<u64> {
+    AppEnvironment::cmd_args()
+        .download_rate_limit
+        .or_else(config::download_rate_limit)
+        .filter(|kib| *kib > 0)
+        .map(|kib| kib.saturating_mul(1024))
+}
+
+pub struct RateLimiter {
+    bytes_per_second: u64,
+    window_start: Instant,
+    window_bytes: u64,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second: bytes_per_second.max(1),
+            window_start: Instant::now(),
+            window_bytes: 0,
+        }
+    }
+
+    pub fn from_settings() -> Option<Self> {
+        download_rate_limit().map(Self::new)
+    }
+
+    /// How long to wait after `bytes` more were received at `now`.
+    fn delay_for(&mut self, bytes: u64, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= WINDOW && self.window_bytes <= self.budget_for(elapsed) {
+            self.window_start = now;
+            self.window_bytes = 0;
+        }
+
+        self.window_bytes = self.window_bytes.saturating_add(bytes);
+        #[allow(clippy::cast_precision_loss)]
+        let allowed =
+            Duration::from_secs_f64(self.window_bytes as f64 / self.bytes_per_second as f64);
+        allowed.saturating_sub(now.saturating_duration_since(self.window_start))
+    }
+
+    fn budget_for(&self, elapsed: Duration) -> u64 {
+        let budget = u128::from(self.bytes_per_second).saturating_mul(elapsed.as_millis()) / 1000;
+        u64::try_from(budget).unwrap_or(u64::MAX)
+    }
+
+    pub async fn consume(&mut self, bytes: u64) {
+        let delay = self.delay_for(bytes, Instant::now());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_once_the_budget_is_spent() {
+        let mut limiter = RateLimiter::new(1000);
+        let start = limiter.window_start;
+
+        assert_eq!(limiter.delay_for(500, start), Duration::from_millis(500));
+        assert_eq!(
+            limiter.delay_for(500, start + Duration::from_millis(500)),
+            Duration::from_millis(500)
+        );
+        // A new window starts, the unused second isn't carried over.
+        assert_eq!(
+            limiter.delay_for(1000, start + Duration::from_secs(3)),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn idle_time_doesnt_allow_a_burst() {
+        let mut limiter = RateLimiter::new(1000);
+        let later = limiter.window_start + Duration::from_secs(60);
+
+        assert_eq!(limiter.delay_for(3000, later), Duration::from_secs(3));
+    }
+}
@@ -0,0 +1,445 @@
+//! File-level updates: only the files that differ from the installed version
+//! are downloaded.
+//!
+//! Every Explorer version may publish a file manifest ([`FileManifest`]) that
+//! lists its files with size and SHA-256. Files are stored content-addressed
+//! under `<files base url>/<sha256>`. The new version directory is assembled
+//! from copies of the unchanged files of the installed version plus hard
+//! links (or copies) of the downloaded ones, and fully verified before it is
+//! used.
+
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use super::compression::ExtractProgress;
+use super::downloads::{self, DownloadFileError, ExpectedChecksum};
+use super::patches::{copy_unchanged, file_matches, relative_path};
+use crate::analytics::Analytics;
+use crate::channel::EventChannel;
+use crate::flow_control::FlowControl;
+use crate::types::{BuildType, Status, Step};
+use crate::utils;
+
+#[derive(Deserialize, Debug)]
+pub struct FileManifest {
+    pub version: String,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ManifestFile {
+    /// Relative to the version directory, `/` separated.
+    pub path: String,
+    pub size: u64,
+    /// Lowercase hex encoded SHA-256, also the name of the file in the store.
+    pub sha256: String,
+    #[serde(default)]
+    pub executable: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum IncrementalError {
+    Io(#[from] io::Error),
+    Download(#[from] DownloadFileError),
+    Malformed(String),
+    Mismatch { path: String },
+}
+
+impl Display for IncrementalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "IO error: {}", e),
+            Self::Download(e) => write!(f, "download error: {}", e),
+            Self::Malformed(reason) => write!(f, "file manifest is malformed: {}", reason),
+            Self::Mismatch { path } => {
+                write!(f, "assembled file doesn't match the manifest: {}", path)
+            }
+        }
+    }
+}
+
+impl FileManifest {
+    /// Reads the manifest and rejects paths leaving the version directory and
+    /// hashes that cannot name a file in the store.
+    pub fn load(path: &Path) -> Result<Self, IncrementalError> {
+        let content = fs::read(path)?;
+        let manifest: Self = serde_json::from_slice(&content)
+            .map_err(|e| IncrementalError::Malformed(e.to_string()))?;
+
+        for file in &manifest.files {
+            relative_path(&file.path)
+                .map_err(|_| IncrementalError::Malformed(format!("unsafe path {}", file.path)))?;
+            if !utils::is_sha256_hex(&file.sha256) {
+                return Err(IncrementalError::Malformed(format!(
+                    "invalid sha256 of {}",
+                    file.path
+                )));
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    pub(crate) fn relative(file: &ManifestFile) -> Result<PathBuf, IncrementalError> {
+        relative_path(&file.path)
+            .map_err(|_| IncrementalError::Malformed(format!("unsafe path {}", file.path)))
+    }
+}
+
+pub(crate) fn object_path(store_dir: &Path, file: &ManifestFile) -> PathBuf {
+    store_dir.join(file.sha256.to_lowercase())
+}
+
+/// Files whose content is neither in `base_dir` at the same path nor already
+/// in the store, one per distinct hash.
+pub fn missing_files<'a>(
+    manifest: &'a FileManifest,
+    base_dir: &Path,
+    store_dir: &Path,
+) -> Result<Vec<&'a ManifestFile>, IncrementalError> {
+    let mut seen = HashSet::new();
+    let mut missing = Vec::new();
+
+    for file in &manifest.files {
+        let relative = FileManifest::relative(file)?;
+        if file_matches(&base_dir.join(relative), file.size, &file.sha256)? {
+            continue;
+        }
+        let sha256 = file.sha256.to_lowercase();
+        if seen.contains(&sha256)
+            || file_matches(&object_path(store_dir, file), file.size, &file.sha256)?
+        {
+            continue;
+        }
+        seen.insert(sha256);
+        missing.push(file);
+    }
+
+    Ok(missing)
+}
+
+/// Turns the per file download progress into the progress of the whole
+/// batch, so the UI doesn't restart from 0 on every file.
+struct BatchChannel<'a, T: EventChannel> {
+    inner: &'a T,
+    done_bytes: u64,
+    file_size: u64,
+    total_bytes: u64,
+}
+
+impl<T: EventChannel> EventChannel for BatchChannel<'_, T> {
+    fn send(&self, status: Status) -> anyhow::Result<()> {
+        let Status::State {
+            step:
+                Step::Downloading {
+                    progress,
+                    build_type,
+                },
+        } = status
+        else {
+            return self.inner.send(status);
+        };
+
+        let file_done = u128::from(self.file_size).saturating_mul(u128::from(progress)) / 100;
+        let done = u128::from(self.done_bytes).saturating_add(file_done);
+        let progress = ExtractProgress {
+            bytes_processed: u64::try_from(done).unwrap_or(u64::MAX),
+            total_bytes: self.total_bytes,
+            entries_processed: 0,
+        }
+        .percent();
+
+        self.inner.send(Status::State {
+            step: Step::Downloading {
+                progress,
+                build_type,
+            },
+        })
+    }
+}
+
+/// Downloads `files` into the store, verifying each one against its hash.
+#[allow(clippy::future_not_send)]
+pub async fn download_files<T: EventChannel>(
+    files: &[&ManifestFile],
+    files_base_url: &str,
+    store_dir: &Path,
+    channel: &T,
+    build_type: &BuildType,
+    analytics: Arc<Mutex<Analytics>>,
+    control: &FlowControl,
+) -> Result<(), IncrementalError> {
+    fs::create_dir_all(store_dir)?;
+
+    let mut batch = BatchChannel {
+        inner: channel,
+        done_bytes: 0,
+        file_size: 0,
+        total_bytes: files.iter().map(|f| f.size).fold(0, u64::saturating_add),
+    };
+
+    for file in files {
+        let url = format!(
+            "{}/{}",
+            files_base_url.trim_end_matches('/'),
+            file.sha256.to_lowercase()
+        );
+        let path = object_path(store_dir, file);
+        let path = path
+            .to_str()
+            .ok_or_else(|| IncrementalError::Malformed("store path is not UTF-8".to_owned()))?;
+        let checksum = ExpectedChecksum {
+            sha256: file.sha256.clone(),
+            size: Some(file.size),
+        };
+
+        batch.file_size = file.size;
+        downloads::download_file(
+            &url,
+            path,
+            &batch,
+            build_type,
+            Some(&checksum),
+            analytics.clone(),
+            control,
+        )
+        .await?;
+        batch.done_bytes = batch.done_bytes.saturating_add(file.size);
+    }
+
+    Ok(())
+}
+
+/// Only for objects of the store: a file of another build is copied instead,
+/// an inode shared with it would take every later change to both builds.
+pub(crate) fn link_or_copy(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::hard_link(from, to).is_err() {
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+pub(crate) fn mark_executable(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+#[allow(clippy::unnecessary_wraps)]
+pub(crate) const fn mark_executable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Builds `target_dir` from the objects in `store_dir` and `base_dir`.
+///
+/// Every file is checked against the manifest afterwards. On error
+/// `target_dir` is left as is, the caller removes it.
+pub fn assemble(
+    manifest: &FileManifest,
+    base_dir: &Path,
+    store_dir: &Path,
+    target_dir: &Path,
+    on_progress: &mut dyn FnMut(ExtractProgress),
+) -> Result<(), IncrementalError> {
+    let mut progress = ExtractProgress {
+        total_bytes: manifest
+            .files
+            .iter()
+            .map(|f| f.size)
+            .fold(0, u64::saturating_add),
+        ..ExtractProgress::default()
+    };
+
+    for file in &manifest.files {
+        let relative = FileManifest::relative(file)?;
+        let output_path = target_dir.join(&relative);
+        let object = object_path(store_dir, file);
+
+        if object.exists() {
+            link_or_copy(&object, &output_path)?;
+        } else {
+            copy_unchanged(&base_dir.join(&relative), &output_path)?;
+        }
+        if file.executable {
+            mark_executable(&output_path)?;
+        }
+
+        progress.bytes_processed = progress.bytes_processed.saturating_add(file.size);
+        progress.entries_processed = progress.entries_processed.saturating_add(1);
+        on_progress(progress);
+    }
+
+    for file in &manifest.files {
+        let relative = FileManifest::relative(file)?;
+        if !file_matches(&target_dir.join(relative), file.size, &file.sha256)? {
+            return Err(IncrementalError::Mismatch {
+                path: file.path.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{Reply, StandIn, temp_path};
+    use sha2::{Digest, Sha256};
+
+    struct NullChannel;
+
+    impl EventChannel for NullChannel {
+        fn send(&self, _status: Status) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn sha256(content: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(content))
+    }
+
+    fn manifest(files: &[(&str, &[u8])]) -> FileManifest {
+        FileManifest {
+            version: "v1.1.0".to_owned(),
+            files: files
+                .iter()
+                .map(|(path, content)| ManifestFile {
+                    path: (*path).to_owned(),
+                    size: content.len() as u64,
+                    sha256: sha256(content),
+                    executable: false,
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn downloads_only_changed_files() -> anyhow::Result<()> {
+        let root = temp_path("dcl-incremental");
+        let base = root.join("latest");
+        let store = root.join("store");
+        let target = root.join("v1.1.0");
+        fs::create_dir_all(base.join("data"))?;
+        fs::write(base.join("same.txt"), b"same")?;
+        fs::write(base.join("data/changed.bin"), b"old")?;
+
+        let manifest = manifest(&[
+            ("same.txt", b"same"),
+            ("data/changed.bin", b"new content"),
+            ("data/copy.bin", b"new content"),
+        ]);
+        let missing = missing_files(&manifest, &base, &store)?;
+        assert_eq!(missing.len(), 1);
+
+        let server = StandIn::start(vec![Reply::ok("new content")]).await?;
+        download_files(
+            &missing,
+            &server.url("files"),
+            &store,
+            &NullChannel,
+            &BuildType::Update,
+            Arc::new(Mutex::new(Analytics::new(None))),
+            &FlowControl::default(),
+        )
+        .await?;
+        let requests = server.requests().await?;
+        assert!(
+            requests
+                .iter()
+                .all(|r| r.starts_with(&format!("get /files/{}", sha256(b"new content"))))
+        );
+
+        assemble(&manifest, &base, &store, &target, &mut |_| {})?;
+
+        assert_eq!(fs::read(target.join("same.txt"))?, b"same");
+        assert_eq!(fs::read(target.join("data/changed.bin"))?, b"new content");
+        assert_eq!(fs::read(target.join("data/copy.bin"))?, b"new content");
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn base_files_are_copied() -> anyhow::Result<()> {
+        let root = temp_path("dcl-incremental-copy");
+        let base = root.join("latest");
+        let target = root.join("v1.1.0");
+        fs::create_dir_all(&base)?;
+        fs::write(base.join("same.txt"), b"same")?;
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("same.txt", base.join("link.txt"))?;
+
+        let mut files = vec![("same.txt", b"same".as_slice())];
+        if cfg!(unix) {
+            files.push(("link.txt", b"same"));
+        }
+        assemble(
+            &manifest(&files),
+            &base,
+            &root.join("store"),
+            &target,
+            &mut |_| {},
+        )?;
+        fs::write(target.join("same.txt"), b"edit")?;
+
+        assert_eq!(fs::read(base.join("same.txt"))?, b"same");
+        #[cfg(unix)]
+        assert_eq!(
+            fs::read_link(target.join("link.txt"))?,
+            Path::new("same.txt")
+        );
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn assembled_files_are_verified() -> anyhow::Result<()> {
+        let root = temp_path("dcl-incremental");
+        let base = root.join("latest");
+        fs::create_dir_all(&base)?;
+        // same size, different content
+        fs::write(base.join("same.txt"), b"else")?;
+
+        let result = assemble(
+            &manifest(&[("same.txt", b"same")]),
+            &base,
+            &root.join("store"),
+            &root.join("v1.1.0"),
+            &mut |_| {},
+        );
+
+        assert!(matches!(result, Err(IncrementalError::Mismatch { .. })));
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unsafe_manifests() -> anyhow::Result<()> {
+        let path = temp_path("dcl-files.json");
+        fs::write(
+            &path,
+            serde_json::json!({
+                "version": "v1.1.0",
+                "files": [{ "path": "../escape", "size": 1, "sha256": sha256(b"x") }],
+            })
+            .to_string(),
+        )?;
+
+        let result = FileManifest::load(&path);
+
+        assert!(matches!(result, Err(IncrementalError::Malformed(_))));
+        fs::remove_file(path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,345 @@
+//! Journal of the install in progress, `install.json` next to `version.json`.
+//!
+//! Installing replaces "latest" in several steps: the new build is unpacked
+//! into its version directory, "latest" is moved back to the directory of its
+//! version, `version.json` is updated and the new build is renamed to
+//! "latest". The phase is written before each of them, so an install
+//! interrupted by a crash or a power loss is recovered on the next start:
+//! rolled back while the new build may be incomplete, rolled forward once it
+//! is complete. Either way "latest" holds a complete build again.
+//!
+//! The new build is synced to disk before the phase becomes Switching, and
+//! the process installing holds [`InstallLock`] until "latest" is switched,
+//! so another launcher doesn't recover an install that is still running.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::{EXPLORER_LATEST_DIRNAME, atomic_write};
+use crate::instances::RunningInstances;
+
+const JOURNAL_FILENAME: &str = "install.json";
+const LOCK_FILENAME: &str = "install.lock";
+
+/// A lock without a pid is being created, unless it is older than this.
+const LOCK_CREATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum InstallPhase {
+    /// The version directory is being written and may be incomplete.
+    Unpacking,
+    /// The version directory is complete and replaces "latest".
+    Switching,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallJournal {
+    pub version: String,
+    /// Version in "latest" when the install started.
+    pub previous: Option<String>,
+    pub phase: InstallPhase,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovery {
+    /// Version of the interrupted install.
+    pub version: String,
+    /// The install was completed rather than undone.
+    pub rolled_forward: bool,
+    /// Version in "latest" now, `None` when no build is left to launch.
+    pub latest: Option<String>,
+}
+
+impl InstallJournal {
+    fn path(root: &Path) -> PathBuf {
+        root.join(JOURNAL_FILENAME)
+    }
+
+    pub fn load(root: &Path) -> Option<Self> {
+        let content = fs::read_to_string(Self::path(root)).ok()?;
+        serde_json::from_str(&content)
+            .inspect_err(|e| log::error!("Cannot parse the install journal, ignoring: {e}"))
+            .ok()
+    }
+
+    pub fn write(&self, root: &Path) -> io::Result<()> {
+        let content = serde_json::to_string(self)?;
+        atomic_write(&Self::path(root), content.as_bytes())
+    }
+
+    pub fn remove(root: &Path) {
+        match fs::remove_file(Self::path(root)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => log::error!("Cannot remove the install journal: {e}"),
+        }
+    }
+}
+
+/// Exclusive lock on the installation directory, released on drop. The file
+/// holds the pid of its owner, a lock left by a process that is gone is
+/// taken over.
+#[derive(Debug)]
+pub struct InstallLock {
+    path: PathBuf,
+}
+
+impl InstallLock {
+    /// `None` while another process holds the lock.
+    pub fn acquire(root: &Path) -> io::Result<Option<Self>> {
+        fs::create_dir_all(root)?;
+        let path = root.join(LOCK_FILENAME);
+        // The second attempt follows the removal of a stale lock
+        for _ in 0..2 {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    let lock = Self { path };
+                    write!(file, "{}", std::process::id())?;
+                    file.sync_all()?;
+                    return Ok(Some(lock));
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    if !Self::is_stale(&path) {
+                        return Ok(None);
+                    }
+                    log::warn!("Taking over the install lock of a process that is gone");
+                    match fs::remove_file(&path) {
+                        Ok(()) => {}
+                        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(None)
+    }
+
+    fn is_stale(path: &Path) -> bool {
+        let owner = fs::read_to_string(path)
+            .ok()
+            .and_then(|content| content.trim().parse::<u32>().ok());
+        match owner {
+            Some(pid) => !RunningInstances::is_running(pid),
+            None => fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > LOCK_CREATION_TIMEOUT),
+        }
+    }
+}
+
+impl Drop for InstallLock {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            log::error!("Cannot release the install lock: {e}");
+        }
+    }
+}
+
+/// Completes or undoes the install left in the journal of `root`, `None` if
+/// no install was interrupted. The caller holds the [`InstallLock`].
+pub fn recover(root: &Path) -> Option<Recovery> {
+    let journal = InstallJournal::load(root)?;
+    log::warn!(
+        "Install of {} was interrupted while {:?}, recovering",
+        journal.version,
+        journal.phase
+    );
+
+    let recovered = match journal.phase {
+        InstallPhase::Unpacking => roll_back(root, &journal),
+        InstallPhase::Switching => roll_forward(root, &journal),
+    };
+    match recovered {
+        Ok(recovery) => {
+            log::info!("Recovered the interrupted install: {:?}", recovery);
+            InstallJournal::remove(root);
+            Some(recovery)
+        }
+        Err(e) => {
+            // Kept, the next start tries again
+            log::error!("Cannot recover the install of {}: {}", journal.version, e);
+            None
+        }
+    }
+}
+
+fn roll_back(root: &Path, journal: &InstallJournal) -> io::Result<Recovery> {
+    let branch_path = root.join(&journal.version);
+    if branch_path.exists() {
+        fs::remove_dir_all(&branch_path)?;
+    }
+    let latest_exists = root.join(EXPLORER_LATEST_DIRNAME).exists();
+    Ok(Recovery {
+        version: journal.version.clone(),
+        rolled_forward: false,
+        latest: journal.previous.clone().filter(|_| latest_exists),
+    })
+}
+
+fn roll_forward(root: &Path, journal: &InstallJournal) -> io::Result<Recovery> {
+    let latest_path = root.join(EXPLORER_LATEST_DIRNAME);
+    let branch_path = root.join(&journal.version);
+    let previous_path = journal
+        .previous
+        .as_ref()
+        .map(|previous| root.join(previous));
+
+    if branch_path.is_dir() {
+        if latest_path.exists() {
+            // "latest" still holds the previous build
+            match &previous_path {
+                Some(previous_path) if *previous_path != branch_path => {
+                    if previous_path.exists() {
+                        fs::remove_dir_all(previous_path)?;
+                    }
+                    fs::rename(&latest_path, previous_path)?;
+                }
+                _ => fs::remove_dir_all(&latest_path)?,
+            }
+        }
+        fs::rename(&branch_path, &latest_path)?;
+    } else if !latest_path.exists() {
+        // The new build is gone, the previous one is better than nothing
+        if let Some(previous_path) = previous_path.filter(|path| path.is_dir()) {
+            fs::rename(previous_path, &latest_path)?;
+            return Ok(Recovery {
+                version: journal.version.clone(),
+                rolled_forward: false,
+                latest: journal.previous.clone(),
+            });
+        }
+        return Ok(Recovery {
+            version: journal.version.clone(),
+            rolled_forward: false,
+            latest: None,
+        });
+    }
+
+    Ok(Recovery {
+        version: journal.version.clone(),
+        rolled_forward: true,
+        latest: Some(journal.version.clone()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_path;
+    use rstest::rstest;
+
+    fn build(root: &Path, dir: &str, content: &str) -> io::Result<()> {
+        fs::create_dir_all(root.join(dir))?;
+        fs::write(root.join(dir).join("build.txt"), content)
+    }
+
+    fn content(root: &Path, dir: &str) -> Option<String> {
+        fs::read_to_string(root.join(dir).join("build.txt")).ok()
+    }
+
+    fn journal(root: &Path, phase: InstallPhase) -> io::Result<()> {
+        InstallJournal {
+            version: "v2.0.0".to_owned(),
+            previous: Some("v1.0.0".to_owned()),
+            phase,
+        }
+        .write(root)
+    }
+
+    #[test]
+    fn partial_build_is_rolled_back() -> io::Result<()> {
+        let root = temp_path("dcl-journal-unpacking");
+        build(&root, "latest", "v1")?;
+        build(&root, "v2.0.0", "half")?;
+        journal(&root, InstallPhase::Unpacking)?;
+
+        let recovery = recover(&root);
+
+        assert_eq!(
+            recovery,
+            Some(Recovery {
+                version: "v2.0.0".to_owned(),
+                rolled_forward: false,
+                latest: Some("v1.0.0".to_owned()),
+            })
+        );
+        assert_eq!(content(&root, "latest").as_deref(), Some("v1"));
+        assert!(!root.join("v2.0.0").exists());
+        assert!(InstallJournal::load(&root).is_none());
+        fs::remove_dir_all(root)
+    }
+
+    // Interrupted before "latest" was moved back, after it was moved back and
+    // after the new build was renamed.
+    #[rstest]
+    #[case(&["latest", "v2.0.0"])]
+    #[case(&["v1.0.0", "v2.0.0"])]
+    #[case(&["v1.0.0", "latest"])]
+    fn complete_build_is_rolled_forward(#[case] dirs: &[&str]) -> io::Result<()> {
+        let root = temp_path("dcl-journal-switching");
+        for dir in dirs {
+            let is_new = *dir == "v2.0.0" || (*dir == "latest" && dirs.contains(&"v1.0.0"));
+            build(&root, dir, if is_new { "v2" } else { "v1" })?;
+        }
+        journal(&root, InstallPhase::Switching)?;
+
+        let recovery = recover(&root);
+
+        assert_eq!(recovery.and_then(|r| r.latest), Some("v2.0.0".to_owned()));
+        assert_eq!(content(&root, "latest").as_deref(), Some("v2"));
+        assert_eq!(content(&root, "v1.0.0").as_deref(), Some("v1"));
+        assert!(!root.join("v2.0.0").exists());
+        assert!(InstallJournal::load(&root).is_none());
+        fs::remove_dir_all(root)
+    }
+
+    #[test]
+    fn lock_is_exclusive_until_dropped() -> io::Result<()> {
+        let root = temp_path("dcl-journal-lock");
+
+        let lock = InstallLock::acquire(&root)?;
+        assert!(lock.is_some());
+        assert!(InstallLock::acquire(&root)?.is_none());
+        drop(lock);
+
+        assert!(!root.join(LOCK_FILENAME).exists());
+        assert!(InstallLock::acquire(&root)?.is_some());
+        fs::remove_dir_all(root)
+    }
+
+    #[test]
+    fn lock_of_a_gone_process_is_taken_over() -> io::Result<()> {
+        let root = temp_path("dcl-journal-stale-lock");
+        fs::create_dir_all(&root)?;
+        fs::write(root.join(LOCK_FILENAME), u32::MAX.to_string())?;
+
+        assert!(InstallLock::acquire(&root)?.is_some());
+        fs::remove_dir_all(root)
+    }
+
+    #[test]
+    fn missing_new_build_restores_the_previous_one() -> io::Result<()> {
+        let root = temp_path("dcl-journal-restore");
+        build(&root, "v1.0.0", "v1")?;
+        journal(&root, InstallPhase::Switching)?;
+
+        let recovery = recover(&root);
+
+        assert_eq!(recovery.and_then(|r| r.latest), Some("v1.0.0".to_owned()));
+        assert_eq!(content(&root, "latest").as_deref(), Some("v1"));
+        fs::remove_dir_all(root)
+    }
+}
@@ -0,0 +1,469 @@
+//! Patch updates rebuild a new Explorer version from the installed one.
+//!
+//! A patch is a plain `.tar` published next to the full build. Its first
+//! entry is `patch.json` ([`PatchIndex`]) listing every file of the new
+//! version; the following entries carry the data of the changed files:
+//! `full/<path>` holds the whole file and `zstd/<path>` a zstd frame
+//! compressed with `--patch-from` the same file of the previous version.
+//! Files marked as unchanged are copied from the installed version. Every
+//! file is checked against its size and SHA-256 before the patch is accepted.
+//!
+//! zstd needs the whole previous file in memory, so files larger than
+//! [`MAX_ZSTD_PATCH_LEN`] on either side must be published under `full/`. A
+//! patch that doesn't is rejected and the full build is downloaded instead.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tar::Archive;
+
+use super::compression::{ExtractProgress, enclosed_path};
+
+pub const PATCH_INDEX_NAME: &str = "patch.json";
+const FULL_PREFIX: &str = "full";
+const ZSTD_PREFIX: &str = "zstd";
+const MAX_PATCH_INDEX_LEN: u64 = 64 * 1024 * 1024;
+/// Largest previous or new file rebuilt from a `zstd/` entry, it bounds the
+/// memory taken by the reference of a single file.
+pub const MAX_ZSTD_PATCH_LEN: u64 = 64 * 1024 * 1024;
+/// `zstd --patch-from` needs a window as large as the reference file, one
+/// step above [`MAX_ZSTD_PATCH_LEN`] covers its rounding.
+const ZSTD_WINDOW_LOG_MAX: u32 = 27;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchIndex {
+    pub from_version: String,
+    pub to_version: String,
+    pub files: Vec<PatchFile>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PatchFile {
+    /// Relative to the version directory, `/` separated.
+    pub path: String,
+    pub size: u64,
+    /// Lowercase hex encoded SHA-256 of the file in the new version.
+    pub sha256: String,
+    pub source: PatchSource,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PatchSource {
+    Unchanged,
+    Full,
+    ZstdPatch,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PatchError {
+    Io(#[from] io::Error),
+    MissingIndex,
+    Malformed(String),
+    VersionMismatch { expected: String, actual: String },
+    UnsafePath(String),
+    UnexpectedEntry(String),
+    MissingBase(String),
+    Mismatch { path: String },
+    TooLarge(String),
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "IO error: {}", e),
+            Self::MissingIndex => write!(f, "{} is not the first patch entry", PATCH_INDEX_NAME),
+            Self::Malformed(reason) => write!(f, "patch index is malformed: {}", reason),
+            Self::VersionMismatch { expected, actual } => write!(
+                f,
+                "patch is built for {} but {} is installed",
+                expected, actual
+            ),
+            Self::UnsafePath(path) => {
+                write!(f, "patch path escapes the version directory: {}", path)
+            }
+            Self::UnexpectedEntry(path) => write!(f, "patch entry is not in the index: {}", path),
+            Self::MissingBase(path) => write!(f, "installed version lacks {}", path),
+            Self::Mismatch { path } => write!(f, "patched file doesn't match the index: {}", path),
+            Self::TooLarge(path) => write!(
+                f,
+                "{} is larger than {} bytes and cannot be zstd patched",
+                path, MAX_ZSTD_PATCH_LEN
+            ),
+        }
+    }
+}
+
+/// Lowercase hex encoded SHA-256 of the file.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut BufReader::new(File::open(path)?), &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Whether the file at `path` has the expected size and SHA-256. A missing
+/// file doesn't match.
+pub fn file_matches(path: &Path, size: u64, sha256: &str) -> io::Result<bool> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.len() == size => {
+            Ok(sha256_file(path)?.eq_ignore_ascii_case(sha256))
+        }
+        Ok(_) => Ok(false),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+pub(crate) fn relative_path(path: &str) -> Result<PathBuf, PatchError> {
+    enclosed_path(Path::new(path))
+        .filter(|p| p.components().next().is_some())
+        .ok_or_else(|| PatchError::UnsafePath(path.to_owned()))
+}
+
+fn read_index<R: Read>(entry: &mut tar::Entry<'_, R>) -> Result<PatchIndex, PatchError> {
+    let is_index = entry
+        .path()
+        .is_ok_and(|p| p.as_ref() == Path::new(PATCH_INDEX_NAME));
+    if !is_index {
+        return Err(PatchError::MissingIndex);
+    }
+
+    let mut content = Vec::new();
+    entry.take(MAX_PATCH_INDEX_LEN).read_to_end(&mut content)?;
+    serde_json::from_slice(&content).map_err(|e| PatchError::Malformed(e.to_string()))
+}
+
+fn create_output(path: &Path) -> io::Result<BufWriter<File>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(BufWriter::new(File::create(path)?))
+}
+
+fn write_full<R: Read>(entry: &mut R, output_path: &Path) -> io::Result<()> {
+    let mut output = create_output(output_path)?;
+    io::copy(entry, &mut output)?;
+    output.flush()
+}
+
+fn write_zstd_patch<R: Read>(
+    entry: &mut R,
+    base_path: &Path,
+    output_path: &Path,
+) -> io::Result<()> {
+    // zstd takes the reference as a single slice, so it can't be streamed,
+    // the caller keeps it below MAX_ZSTD_PATCH_LEN.
+    let base = fs::read(base_path)?;
+    let mut decoder = zstd::stream::read::Decoder::with_ref_prefix(BufReader::new(entry), &base)?;
+    decoder.window_log_max(ZSTD_WINDOW_LOG_MAX)?;
+
+    let mut output = create_output(output_path)?;
+    io::copy(&mut decoder, &mut output)?;
+    output.flush()?;
+    fs::set_permissions(output_path, fs::metadata(base_path)?.permissions())
+}
+
+#[cfg(unix)]
+pub(crate) fn copy_unchanged(base_path: &Path, output_path: &Path) -> io::Result<()> {
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Symlinks were validated when the installed version was extracted.
+    if fs::symlink_metadata(base_path)?.is_symlink() {
+        return std::os::unix::fs::symlink(fs::read_link(base_path)?, output_path);
+    }
+    fs::copy(base_path, output_path).map(|_| ())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn copy_unchanged(base_path: &Path, output_path: &Path) -> io::Result<()> {
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(base_path, output_path).map(|_| ())
+}
+
+/// Builds `target_dir` for `to_version` from `base_dir` holding `from_version`
+/// and the patch at `patch_path`. On error `target_dir` is left as is, the
+/// caller is expected to remove it.
+pub fn apply_patch(
+    patch_path: &Path,
+    from_version: &str,
+    to_version: &str,
+    base_dir: &Path,
+    target_dir: &Path,
+    on_progress: &mut dyn FnMut(ExtractProgress),
+) -> Result<(), PatchError> {
+    let mut archive = Archive::new(BufReader::new(File::open(patch_path)?));
+    let mut entries = archive.entries()?;
+
+    let mut first = entries.next().ok_or(PatchError::MissingIndex)??;
+    let index = read_index(&mut first)?;
+    drop(first);
+
+    if index.from_version != from_version {
+        return Err(PatchError::VersionMismatch {
+            expected: index.from_version,
+            actual: from_version.to_owned(),
+        });
+    }
+    if index.to_version != to_version {
+        return Err(PatchError::Malformed(format!(
+            "patch builds {} instead of {}",
+            index.to_version, to_version
+        )));
+    }
+
+    let mut files: HashMap<PathBuf, &PatchFile> = HashMap::with_capacity(index.files.len());
+    for file in &index.files {
+        files.insert(relative_path(&file.path)?, file);
+    }
+
+    let mut progress = ExtractProgress {
+        total_bytes: index
+            .files
+            .iter()
+            .map(|f| f.size)
+            .fold(0, u64::saturating_add),
+        ..ExtractProgress::default()
+    };
+    let mut advance = |size: u64| {
+        progress.bytes_processed = progress.bytes_processed.saturating_add(size);
+        progress.entries_processed = progress.entries_processed.saturating_add(1);
+        on_progress(progress);
+    };
+
+    for entry in entries {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.to_path_buf();
+        let entry_name = entry_path.to_string_lossy().into_owned();
+
+        let (source, relative) = if let Ok(rest) = entry_path.strip_prefix(FULL_PREFIX) {
+            (PatchSource::Full, rest)
+        } else if let Ok(rest) = entry_path.strip_prefix(ZSTD_PREFIX) {
+            (PatchSource::ZstdPatch, rest)
+        } else {
+            return Err(PatchError::UnexpectedEntry(entry_name));
+        };
+
+        let relative =
+            enclosed_path(relative).ok_or_else(|| PatchError::UnsafePath(entry_name.clone()))?;
+        let file = files
+            .get(&relative)
+            .filter(|f| f.source == source)
+            .ok_or(PatchError::UnexpectedEntry(entry_name))?;
+
+        let output_path = target_dir.join(&relative);
+        if source == PatchSource::Full {
+            write_full(&mut entry, &output_path)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mode = entry.header().mode()? & 0o777;
+                fs::set_permissions(&output_path, fs::Permissions::from_mode(mode))?;
+            }
+        } else {
+            let base_path = base_dir.join(&relative);
+            if !base_path.exists() {
+                return Err(PatchError::MissingBase(file.path.clone()));
+            }
+            if file.size > MAX_ZSTD_PATCH_LEN
+                || fs::metadata(&base_path)?.len() > MAX_ZSTD_PATCH_LEN
+            {
+                return Err(PatchError::TooLarge(file.path.clone()));
+            }
+            write_zstd_patch(&mut entry, &base_path, &output_path)?;
+        }
+        advance(file.size);
+    }
+
+    for (relative, file) in &files {
+        if file.source != PatchSource::Unchanged {
+            continue;
+        }
+        let base_path = base_dir.join(relative);
+        if fs::symlink_metadata(&base_path).is_err() {
+            return Err(PatchError::MissingBase(file.path.clone()));
+        }
+        copy_unchanged(&base_path, &target_dir.join(relative))?;
+        advance(file.size);
+    }
+
+    for (relative, file) in &files {
+        if !file_matches(&target_dir.join(relative), file.size, &file.sha256)? {
+            return Err(PatchError::Mismatch {
+                path: file.path.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_path;
+
+    const FROM: &str = "v1.0.0";
+    const TO: &str = "v1.1.0";
+
+    fn sha256(content: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(content))
+    }
+
+    fn append(builder: &mut tar::Builder<Vec<u8>>, path: &str, content: &[u8]) -> io::Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, content)
+    }
+
+    struct Fixture {
+        root: PathBuf,
+        base: PathBuf,
+        target: PathBuf,
+        patch: PathBuf,
+    }
+
+    impl Fixture {
+        /// Installed version has `same.txt` and `changed.bin`, the new one
+        /// keeps `same.txt`, patches `changed.bin` and adds `added/new.txt`.
+        fn new(index_files: Option<serde_json::Value>) -> anyhow::Result<Self> {
+            let root = temp_path("dcl-patch");
+            let base = root.join("latest");
+            fs::create_dir_all(&base)?;
+
+            let old_changed = b"the quick brown fox jumps over the lazy dog".repeat(64);
+            let mut new_changed = old_changed.clone();
+            new_changed.extend_from_slice(b" and runs away");
+            fs::write(base.join("same.txt"), b"same")?;
+            fs::write(base.join("changed.bin"), &old_changed)?;
+
+            let mut delta =
+                zstd::stream::write::Encoder::with_ref_prefix(Vec::new(), 3, &old_changed)?;
+            delta.write_all(&new_changed)?;
+            let delta = delta.finish()?;
+
+            let files = index_files.unwrap_or_else(|| {
+                serde_json::json!([
+                    { "path": "same.txt", "size": 4, "sha256": sha256(b"same"), "source": "unchanged" },
+                    { "path": "changed.bin", "size": new_changed.len(), "sha256": sha256(&new_changed), "source": "zstdPatch" },
+                    { "path": "added/new.txt", "size": 3, "sha256": sha256(b"new"), "source": "full" },
+                ])
+            });
+            let index = serde_json::json!({ "fromVersion": FROM, "toVersion": TO, "files": files });
+
+            let mut builder = tar::Builder::new(Vec::new());
+            append(&mut builder, PATCH_INDEX_NAME, index.to_string().as_bytes())?;
+            append(&mut builder, "zstd/changed.bin", &delta)?;
+            append(&mut builder, "full/added/new.txt", b"new")?;
+            let patch = root.join("decentraland.patch");
+            fs::write(&patch, builder.into_inner()?)?;
+
+            Ok(Self {
+                target: root.join(TO),
+                root,
+                base,
+                patch,
+            })
+        }
+
+        fn apply(&self, from_version: &str) -> Result<(), PatchError> {
+            apply_patch(
+                &self.patch,
+                from_version,
+                TO,
+                &self.base,
+                &self.target,
+                &mut |_| {},
+            )
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn rebuilds_the_new_version() -> anyhow::Result<()> {
+        let fixture = Fixture::new(None)?;
+
+        fixture.apply(FROM)?;
+
+        assert_eq!(fs::read(fixture.target.join("same.txt"))?, b"same");
+        assert!(fs::read(fixture.target.join("changed.bin"))?.ends_with(b" and runs away"));
+        assert_eq!(fs::read(fixture.target.join("added/new.txt"))?, b"new");
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_a_patch_for_another_version() -> anyhow::Result<()> {
+        let fixture = Fixture::new(None)?;
+
+        let result = fixture.apply("v0.9.0");
+
+        assert!(matches!(result, Err(PatchError::VersionMismatch { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_files_that_dont_match_the_index() -> anyhow::Result<()> {
+        let fixture = Fixture::new(None)?;
+        // the installed file was modified locally
+        fs::write(fixture.base.join("same.txt"), b"else")?;
+
+        let result = fixture.apply(FROM);
+
+        assert!(matches!(result, Err(PatchError::Mismatch { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_zstd_patches_of_large_files() -> anyhow::Result<()> {
+        let fixture = Fixture::new(None)?;
+        // sparse, nothing is written
+        File::options()
+            .write(true)
+            .open(fixture.base.join("changed.bin"))?
+            .set_len(MAX_ZSTD_PATCH_LEN + 1)?;
+
+        let result = fixture.apply(FROM);
+
+        assert!(matches!(result, Err(PatchError::TooLarge(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_entries_missing_from_the_index() -> anyhow::Result<()> {
+        let fixture = Fixture::new(Some(serde_json::json!([
+            { "path": "same.txt", "size": 4, "sha256": sha256(b"same"), "source": "unchanged" },
+        ])))?;
+
+        let result = fixture.apply(FROM);
+
+        assert!(matches!(result, Err(PatchError::UnexpectedEntry(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_paths_outside_the_version_directory() -> anyhow::Result<()> {
+        let fixture = Fixture::new(Some(serde_json::json!([
+            { "path": "../escape.txt", "size": 4, "sha256": sha256(b"same"), "source": "unchanged" },
+        ])))?;
+
+        let result = fixture.apply(FROM);
+
+        assert!(matches!(result, Err(PatchError::UnsafePath(_))));
+        Ok(())
+    }
+}
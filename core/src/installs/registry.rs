@@ -0,0 +1,250 @@
+//! Registry of the installed builds, `version.json` in the installation
+//! directory.
+//!
+//! It used to be a flat object: `version` held the build in "latest", every
+//! other key was an installed version mapped to its install time, next to a
+//! `channel` key and a legacy `path` one. Such a file is migrated when it is
+//! read. `version` keeps its key, so older launchers still find the build in
+//! "latest".
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::atomic_write;
+use crate::release_channel::ReleaseChannel;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+const REGISTRY_FILENAME: &str = "version.json";
+
+/// Keys of the flat format that don't name a version.
+const LEGACY_KEYS: [&str; 3] = ["version", "channel", "path"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallRegistry {
+    pub schema_version: u32,
+    /// Version in "latest".
+    #[serde(rename = "version")]
+    pub current: Option<String>,
+    #[serde(default)]
+    pub builds: Vec<InstalledBuild>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledBuild {
+    pub version: String,
+    /// Unix time in seconds, the same as `last_launched_at`.
+    pub installed_at: Option<u64>,
+    /// Full build, patch or file manifest the build was installed from.
+    pub source_url: Option<String>,
+    /// SHA-256 of the source.
+    pub sha256: Option<String>,
+    /// Size of the build directory in bytes.
+    pub size: Option<u64>,
+    /// Name of the release channel.
+    pub channel: Option<String>,
+    pub last_launched_at: Option<u64>,
+}
+
+impl Default for InstallRegistry {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            current: None,
+            builds: Vec::new(),
+        }
+    }
+}
+
+pub fn unix_time() -> Option<u64> {
+    std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .ok()
+        .map(|time| time.as_secs())
+}
+
+impl InstallRegistry {
+    fn path(root: &Path) -> PathBuf {
+        root.join(REGISTRY_FILENAME)
+    }
+
+    /// `None` when nothing was installed yet or the file is unreadable. The
+    /// flat format is migrated and written back.
+    pub fn load(root: &Path) -> Option<Self> {
+        let content = fs::read_to_string(Self::path(root)).ok()?;
+        let value: Value = serde_json::from_str(&content)
+            .inspect_err(|e| log::error!("Cannot parse the install registry: {e}"))
+            .ok()?;
+        let Value::Object(data) = value else {
+            log::error!("Cannot parse the install registry: expected a JSON object");
+            return None;
+        };
+
+        if data.contains_key("schemaVersion") {
+            let registry: Self = serde_json::from_value(Value::Object(data))
+                .inspect_err(|e| log::error!("Cannot parse the install registry: {e}"))
+                .ok()?;
+            if registry.schema_version > SCHEMA_VERSION {
+                log::warn!(
+                    "Install registry has the newer schema {}, unknown fields are dropped",
+                    registry.schema_version
+                );
+            }
+            return Some(registry);
+        }
+
+        log::info!(
+            "Migrating the install registry to schema {}",
+            SCHEMA_VERSION
+        );
+        let registry = Self::from_legacy(&data);
+        if let Err(e) = registry.write(root) {
+            log::error!("Cannot write the migrated install registry: {e}");
+        }
+        Some(registry)
+    }
+
+    fn from_legacy(data: &Map<String, Value>) -> Self {
+        let mut registry = Self {
+            current: data
+                .get("version")
+                .and_then(Value::as_str)
+                .map(str::to_owned),
+            builds: data
+                .iter()
+                .filter(|(key, _)| !LEGACY_KEYS.contains(&key.as_str()))
+                .map(|(version, installed_at)| InstalledBuild {
+                    version: version.clone(),
+                    installed_at: installed_at.as_str().and_then(|time| time.parse().ok()),
+                    ..InstalledBuild::default()
+                })
+                .collect(),
+            ..Self::default()
+        };
+
+        // Installs made before channels existed come from stable
+        let channel = data
+            .get("channel")
+            .and_then(Value::as_str)
+            .map_or_else(|| ReleaseChannel::Stable.name().to_owned(), str::to_owned);
+        if let Some(current) = registry.current.clone() {
+            registry.build_mut(&current).channel = Some(channel);
+        }
+        registry
+    }
+
+    pub fn write(&self, root: &Path) -> io::Result<()> {
+        let content = serde_json::to_string(self)?;
+        atomic_write(&Self::path(root), content.as_bytes())
+    }
+
+    pub fn build(&self, version: &str) -> Option<&InstalledBuild> {
+        self.builds.iter().find(|build| build.version == version)
+    }
+
+    /// The entry of `version`, added if missing.
+    pub fn build_mut(&mut self, version: &str) -> &mut InstalledBuild {
+        let index = match self.builds.iter().position(|b| b.version == version) {
+            Some(index) => index,
+            None => {
+                self.builds.push(InstalledBuild {
+                    version: version.to_owned(),
+                    ..InstalledBuild::default()
+                });
+                self.builds.len().saturating_sub(1)
+            }
+        };
+        // Found or pushed right above
+        #[allow(clippy::indexing_slicing)]
+        &mut self.builds[index]
+    }
+
+    pub fn current_build(&self) -> Option<&InstalledBuild> {
+        self.build(self.current.as_deref()?)
+    }
+
+    pub fn remove_build(&mut self, version: &str) {
+        self.builds.retain(|build| build.version != version);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_path;
+    use serde_json::json;
+
+    #[test]
+    fn flat_format_is_migrated() -> anyhow::Result<()> {
+        let root = temp_path("dcl-registry-legacy");
+        fs::create_dir_all(&root)?;
+        fs::write(
+            root.join(REGISTRY_FILENAME),
+            json!({
+                "version": "v1.2.0",
+                "v1.1.0": "1700000000",
+                "v1.2.0": "1700000100",
+                "channel": "beta",
+                "path": "/old/path",
+            })
+            .to_string(),
+        )?;
+
+        let registry = InstallRegistry::load(&root);
+
+        let registry = registry.ok_or_else(|| anyhow::anyhow!("registry not loaded"))?;
+        assert_eq!(registry.schema_version, SCHEMA_VERSION);
+        assert_eq!(registry.current.as_deref(), Some("v1.2.0"));
+        assert_eq!(registry.builds.len(), 2);
+        let current = registry.current_build();
+        assert_eq!(current.and_then(|b| b.installed_at), Some(1_700_000_100));
+        assert_eq!(current.and_then(|b| b.channel.as_deref()), Some("beta"));
+        assert_eq!(
+            registry.build("v1.1.0").and_then(|b| b.installed_at),
+            Some(1_700_000_000)
+        );
+
+        // Written back, with the build in "latest" still under `version`
+        let written: Value =
+            serde_json::from_str(&fs::read_to_string(root.join(REGISTRY_FILENAME))?)?;
+        assert_eq!(written.get("version"), Some(&json!("v1.2.0")));
+        assert_eq!(written.get("schemaVersion"), Some(&json!(SCHEMA_VERSION)));
+        assert!(written.get("path").is_none());
+        assert!(!root.join("version.json.tmp").exists());
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn registry_round_trips() -> anyhow::Result<()> {
+        let root = temp_path("dcl-registry");
+        fs::create_dir_all(&root)?;
+        let mut registry = InstallRegistry {
+            current: Some("v2.0.0".to_owned()),
+            ..InstallRegistry::default()
+        };
+        *registry.build_mut("v2.0.0") = InstalledBuild {
+            version: "v2.0.0".to_owned(),
+            installed_at: Some(1),
+            source_url: Some("https://cdn.example.org/v2.0.0/build.zip".to_owned()),
+            sha256: Some("ab".repeat(32)),
+            size: Some(1024),
+            channel: Some("stable".to_owned()),
+            last_launched_at: Some(2),
+        };
+        registry.build_mut("v1.0.0").installed_at = Some(0);
+        registry.remove_build("v1.0.0");
+
+        registry.write(&root)?;
+
+        assert_eq!(InstallRegistry::load(&root), Some(registry));
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+//! Verify and repair of an installed build against its file manifest, see
+//! [`super::incremental`].
+//!
+//! Verify lists the files of the manifest that are missing or whose size or
+//! SHA-256 differ. Repair replaces only those files in place with the objects
+//! downloaded into the store; files the manifest doesn't list are left alone.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::incremental::{
+    FileManifest, IncrementalError, ManifestFile, link_or_copy, mark_executable, object_path,
+};
+use super::patches::file_matches;
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyReport {
+    pub version: String,
+    /// Files listed in the manifest.
+    pub checked: u64,
+    pub missing: Vec<String>,
+    /// Present with another size or content.
+    pub modified: Vec<String>,
+}
+
+impl VerifyReport {
+    pub const fn is_intact(&self) -> bool {
+        self.missing.is_empty() && self.modified.is_empty()
+    }
+
+    fn is_damaged(&self, path: &str) -> bool {
+        self.missing.iter().chain(&self.modified).any(|p| p == path)
+    }
+
+    /// Files to download for the repair, one per distinct hash.
+    pub fn damaged_files<'a>(&self, manifest: &'a FileManifest) -> Vec<&'a ManifestFile> {
+        let mut seen = HashSet::new();
+        manifest
+            .files
+            .iter()
+            .filter(|file| self.is_damaged(&file.path))
+            .filter(|file| seen.insert(file.sha256.to_lowercase()))
+            .collect()
+    }
+}
+
+/// Compares `dir` with `manifest`, every listed file is hashed.
+pub fn verify(manifest: &FileManifest, dir: &Path) -> Result<VerifyReport, IncrementalError> {
+    let mut report = VerifyReport {
+        version: manifest.version.clone(),
+        checked: manifest.files.len() as u64,
+        ..VerifyReport::default()
+    };
+
+    for file in &manifest.files {
+        let path = dir.join(FileManifest::relative(file)?);
+        match fs::symlink_metadata(&path) {
+            Ok(_) => {
+                if !file_matches(&path, file.size, &file.sha256)? {
+                    report.modified.push(file.path.clone());
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => report.missing.push(file.path.clone()),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Replaces the damaged files of `report` in `dir` with their objects in
+/// `store_dir`, then checks them again.
+pub fn repair(
+    manifest: &FileManifest,
+    report: &VerifyReport,
+    dir: &Path,
+    store_dir: &Path,
+) -> Result<(), IncrementalError> {
+    let damaged = manifest
+        .files
+        .iter()
+        .filter(|file| report.is_damaged(&file.path));
+
+    for file in damaged {
+        let path = dir.join(FileManifest::relative(file)?);
+        // Unlinked rather than overwritten, the file may be a hard link
+        // shared with a build kept for rollback.
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        link_or_copy(&object_path(store_dir, file), &path)?;
+        if file.executable {
+            mark_executable(&path)?;
+        }
+        if !file_matches(&path, file.size, &file.sha256)? {
+            return Err(IncrementalError::Mismatch {
+                path: file.path.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_path;
+    use sha2::{Digest, Sha256};
+
+    fn sha256(content: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(content))
+    }
+
+    fn manifest(files: &[(&str, &[u8])]) -> FileManifest {
+        FileManifest {
+            version: "v1.1.0".to_owned(),
+            files: files
+                .iter()
+                .map(|(path, content)| ManifestFile {
+                    path: (*path).to_owned(),
+                    size: content.len() as u64,
+                    sha256: sha256(content),
+                    executable: false,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn damaged_files_are_reported_and_repaired() -> anyhow::Result<()> {
+        let root = temp_path("dcl-verify");
+        let latest = root.join("latest");
+        let store = root.join("store");
+        fs::create_dir_all(latest.join("data"))?;
+        fs::create_dir_all(&store)?;
+        fs::write(latest.join("same.txt"), b"same")?;
+        // same size, different content
+        fs::write(latest.join("data/changed.bin"), b"olt")?;
+        fs::write(latest.join("extra.log"), b"not listed")?;
+
+        let manifest = manifest(&[
+            ("same.txt", b"same"),
+            ("data/changed.bin", b"old"),
+            ("data/gone.bin", b"gone"),
+            ("data/copy.bin", b"gone"),
+        ]);
+        let report = verify(&manifest, &latest)?;
+
+        assert_eq!(report.checked, 4);
+        assert_eq!(report.modified, vec!["data/changed.bin".to_owned()]);
+        assert_eq!(
+            report.missing,
+            vec!["data/gone.bin".to_owned(), "data/copy.bin".to_owned()]
+        );
+        assert_eq!(report.damaged_files(&manifest).len(), 2);
+
+        fs::write(store.join(sha256(b"old")), b"old")?;
+        fs::write(store.join(sha256(b"gone")), b"gone")?;
+        repair(&manifest, &report, &latest, &store)?;
+
+        assert!(verify(&manifest, &latest)?.is_intact());
+        assert_eq!(fs::read(latest.join("extra.log"))?, b"not listed");
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn repair_doesnt_touch_hard_linked_builds() -> anyhow::Result<()> {
+        let root = temp_path("dcl-verify-linked");
+        let latest = root.join("latest");
+        let retained = root.join("v1.0.0");
+        let store = root.join("store");
+        fs::create_dir_all(&latest)?;
+        fs::create_dir_all(&retained)?;
+        fs::create_dir_all(&store)?;
+        fs::write(retained.join("shared.bin"), b"bad")?;
+        fs::hard_link(retained.join("shared.bin"), latest.join("shared.bin"))?;
+        fs::write(store.join(sha256(b"good")), b"good")?;
+
+        let manifest = manifest(&[("shared.bin", b"good")]);
+        let report = verify(&manifest, &latest)?;
+        repair(&manifest, &report, &latest, &store)?;
+
+        assert_eq!(fs::read(latest.join("shared.bin"))?, b"good");
+        assert_eq!(fs::read(retained.join("shared.bin"))?, b"bad");
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+}
@@ -19,7 +19,7 @@ struct Storage {
 }
 
 impl RunningInstances {
-    #[cfg(target_os = "windows")]
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
     pub fn register_instance(&self, pid_raw: u32) {
         let system = sysinfo::System::new_all();
         let pid = Pid::from_u32(pid_raw);
@@ -98,13 +98,25 @@ impl RunningInstances {
     /// Authoritative — does not depend on the `running-instances.json` tracker,
     /// which can hold stale PIDs across sessions.
     pub fn explorer_processes_by_path(&self) -> Vec<String> {
-        let latest_path = installs::explorer_latest_version_path();
-        Self::processes_under_path(&latest_path)
+        Self::explorer_processes_under(&installs::explorer_latest_version_path())
+    }
+
+    /// Same scan for any directory, e.g. the version directories kept for
+    /// rollback that `--version` launches from.
+    pub fn explorer_processes_under(path: &Path) -> Vec<String> {
+        Self::processes_under_path(path)
             .into_iter()
             .map(|(pid, name, _)| format!("{name} (pid {pid})"))
             .collect()
     }
 
+    pub fn is_running(pid: u32) -> bool {
+        let pid = Pid::from_u32(pid);
+        let mut system = sysinfo::System::new();
+        system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[pid]), true);
+        system.process(pid).is_some()
+    }
+
     pub fn any_is_running(&self) -> Result<bool> {
         let system = sysinfo::System::new_all();
         let mut content = Self::file_content(self.path.as_path());
@@ -171,7 +183,7 @@ impl RunningInstances {
         Ok(())
     }
 
-    #[cfg(target_os = "windows")]
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
     fn write_to_json_file(&self, pid: u32, name: &str) -> Result<()> {
         let path = self.path.as_path();
         let mut content: Storage = Self::file_content(path);
@@ -24,18 +24,25 @@ pub mod analytics;
 pub mod app;
 pub mod download_origin_metadata;
 pub mod channel;
+pub mod cli;
 pub mod config;
 mod deeplink_bridge;
 pub mod environment;
 pub mod errors;
 pub mod flow;
+pub mod flow_control;
 pub mod installs;
 pub mod instances;
 pub mod logs;
+pub mod mirrors;
 mod monitoring;
 mod processes;
 pub mod protocols;
+pub mod release_channel;
+pub mod rollout;
 pub mod s3;
+#[cfg(test)]
+mod test_utils;
 pub mod types;
 pub mod utils;
 
@@ -35,13 +35,24 @@ impl From<&str> for LogDestination {
     }
 }
 
-pub fn dispath_logs() -> Result<()> {
+/// Console stream the logs are echoed to, besides the log file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LogConsole {
+    Stdout,
+    /// Keeps stdout for the machine-readable output of the headless CLI.
+    Stderr,
+}
+
+pub fn dispath_logs(console: LogConsole) -> Result<()> {
     let path = installs::log_file_path()?;
     let log_file = fern::log_file(&path)?;
     let path = path.to_string_lossy().to_string();
-    println!("Write logs to path: {}", &path);
+    match console {
+        LogConsole::Stdout => println!("Write logs to path: {}", &path),
+        LogConsole::Stderr => eprintln!("Write logs to path: {}", &path),
+    }
 
-    let (level, fern_log) = fern::Dispatch::new()
+    let dispatch = fern::Dispatch::new()
         // Perform allocation-free log formatting
         .format(|out, message, record| {
             out.finish(format_args!(
@@ -52,10 +63,12 @@ pub fn dispath_logs() -> Result<()> {
                 message
             ));
         })
-        .level(log::LevelFilter::Trace)
-        .chain(std::io::stdout())
-        .chain(log_file)
-        .into_log();
+        .level(log::LevelFilter::Trace);
+    let dispatch = match console {
+        LogConsole::Stdout => dispatch.chain(std::io::stdout()),
+        LogConsole::Stderr => dispatch.chain(std::io::stderr()),
+    };
+    let (level, fern_log) = dispatch.chain(log_file).into_log();
 
     let sentry_log = new_sentry_log();
 
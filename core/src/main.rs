@@ -1,24 +1,63 @@
-use anyhow::{Context, Ok, Result};
+use std::process::ExitCode;
+
+use anyhow::{Context, Result};
+use dcl_launcher_core::channel::ndjson::{EventStreamTarget, NdjsonChannel};
+use dcl_launcher_core::cli::{self, Command};
+use dcl_launcher_core::types::Status;
 use dcl_launcher_core::{app::AppState, channel::EventChannel};
-use log::info;
+use log::{error, info};
 
 struct ConsoleChannel();
 
 impl EventChannel for ConsoleChannel {
-    fn send(&self, status: dcl_launcher_core::types::Status) -> Result<()> {
+    fn send(&self, status: Status) -> Result<()> {
         let s = serde_json::to_string_pretty(&status)?;
         info!("{s}");
         Ok(())
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let app_state = AppState::setup().await.context("Cannot setup state")?;
-    let channel = ConsoleChannel();
+async fn launch() -> Result<()> {
+    let Some(target) = EventStreamTarget::from_args() else {
+        let app_state = AppState::setup().await.context("Cannot setup state")?;
+        return run_flow(app_state, &ConsoleChannel()).await;
+    };
+
+    // The logs move to stderr when the stream takes stdout
+    let app_state = if target == EventStreamTarget::Stdout {
+        AppState::setup_headless().await
+    } else {
+        AppState::setup().await
+    }
+    .context("Cannot setup state")?;
+    let channel = NdjsonChannel::open(&target)?;
+    run_flow(app_state, &channel).await
+}
+
+async fn run_flow<T: EventChannel>(app_state: AppState, channel: &T) -> Result<()> {
     app_state
         .flow
-        .launch(&channel, app_state.state)
+        .launch(channel, app_state.state)
         .await
-        .map_err(|e| anyhow::anyhow!(e.user_message))
+        .map_err(|e| {
+            if let Err(send_error) = channel.send(Status::from(&e)) {
+                error!("Cannot send the error to the channel: {send_error:#}");
+            }
+            anyhow::anyhow!(e.user_message)
+        })
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match Command::parse(std::env::args()) {
+        Err(e) => cli::report_usage_error(&e),
+        Ok(Some(command)) => cli::run(command).await,
+        Ok(None) => match launch().await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("Error: {e:?}");
+                ExitCode::FAILURE
+            }
+        },
+    }
 }
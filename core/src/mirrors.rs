@@ -0,0 +1,193 @@
+//! Origins serving the release manifests and builds. The `mirrors` key of
+//! `config.json` comes first, then the compiled bucket and its compiled
+//! mirrors.
+//!
+//! A URL on any of them is tried on each origin in turn while requests fail
+//! with network errors. Origins that failed are remembered for the session
+//! and only tried after the healthy ones. The origin that served the last
+//! request is reported in analytics as `mirror`.
+
+use std::fmt::Display;
+use std::sync::Mutex;
+
+use log::{error, info, warn};
+
+use crate::config;
+use crate::environment::AppEnvironment;
+
+struct Health {
+    failed: Vec<String>,
+    active: Option<String>,
+}
+
+static HEALTH: Mutex<Health> = Mutex::new(Health {
+    failed: Vec::new(),
+    active: None,
+});
+
+/// Errors after which the same request is sent to the next origin.
+pub trait MirrorFailure {
+    fn is_mirror_failure(&self) -> bool;
+}
+
+/// Server errors, and the codes CDNs use to refuse a region or a client.
+pub const fn is_mirror_http_failure(code: u16) -> bool {
+    code >= 500 || code == 403 || code == 429
+}
+
+fn with_health<T>(f: impl FnOnce(&mut Health) -> T) -> Option<T> {
+    match HEALTH.lock() {
+        Ok(mut guard) => Some(f(&mut guard)),
+        Err(e) => {
+            error!("cannot acquire mutex of mirrors HEALTH: {}", e);
+            None
+        }
+    }
+}
+
+fn origins() -> Vec<String> {
+    let mut origins: Vec<String> = Vec::new();
+    let configured = config::mirrors().into_iter();
+    let compiled =
+        std::iter::once(AppEnvironment::bucket_url()).chain(AppEnvironment::bucket_mirror_urls());
+    for origin in configured.chain(compiled) {
+        let origin = origin.trim_end_matches('/').to_owned();
+        if !origin.is_empty() && !origins.contains(&origin) {
+            origins.push(origin);
+        }
+    }
+    origins
+}
+
+fn origin_of<'a>(url: &str, origins: &'a [String]) -> Option<&'a String> {
+    origins.iter().find(|origin| {
+        url.strip_prefix(origin.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?']))
+    })
+}
+
+/// `url` on every origin, healthy ones first. URLs on other hosts, e.g. an
+/// artifact URL of the manifest, are left alone.
+fn candidates_for(url: &str, origins: &[String], failed: &[String]) -> Vec<String> {
+    let Some(origin) = origin_of(url, origins) else {
+        return vec![url.to_owned()];
+    };
+    let path = url.get(origin.len()..).unwrap_or_default();
+    let (healthy, failed): (Vec<&String>, Vec<&String>) =
+        origins.iter().partition(|origin| !failed.contains(origin));
+    healthy
+        .into_iter()
+        .chain(failed)
+        .map(|origin| format!("{}{}", origin, path))
+        .collect()
+}
+
+pub fn candidates(url: &str) -> Vec<String> {
+    let failed = with_health(|health| health.failed.clone()).unwrap_or_default();
+    candidates_for(url, &origins(), &failed)
+}
+
+fn mark_failed(url: &str) {
+    let origins = origins();
+    let Some(origin) = origin_of(url, &origins) else {
+        return;
+    };
+    with_health(|health| {
+        if !health.failed.contains(origin) {
+            health.failed.push(origin.clone());
+        }
+    });
+}
+
+fn mark_healthy(url: &str) {
+    let origins = origins();
+    let Some(origin) = origin_of(url, &origins) else {
+        return;
+    };
+    with_health(|health| {
+        health.failed.retain(|failed| failed != origin);
+        if health.active.as_ref() != Some(origin) {
+            info!("Using mirror {}", origin);
+            health.active = Some(origin.clone());
+        }
+    });
+}
+
+/// Origin that served the last request of the session.
+pub fn active() -> Option<String> {
+    with_health(|health| health.active.clone()).flatten()
+}
+
+/// Sends `request` to `url` on each origin until one doesn't fail with a
+/// [`MirrorFailure`], the error of the last one is returned.
+pub async fn with_fallback<T, E, F, Fut>(url: &str, mut request: F) -> Result<T, E>
+where
+    E: MirrorFailure + Display,
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut candidates = candidates(url).into_iter();
+    let mut candidate = candidates.next().unwrap_or_else(|| url.to_owned());
+    loop {
+        match request(candidate.clone()).await {
+            Ok(value) => {
+                mark_healthy(&candidate);
+                return Ok(value);
+            }
+            Err(e) if e.is_mirror_failure() => {
+                mark_failed(&candidate);
+                let Some(next) = candidates.next() else {
+                    return Err(e);
+                };
+                warn!("Request to {} failed, trying {}: {}", candidate, next, e);
+                candidate = next;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| (*v).to_owned()).collect()
+    }
+
+    #[test]
+    fn url_is_tried_on_every_origin() {
+        let origins = strings(&["https://a.cdn", "https://b.cdn", "https://c.cdn"]);
+
+        assert_eq!(
+            candidates_for("https://b.cdn/releases/latest.json?_t=1", &origins, &[]),
+            strings(&[
+                "https://a.cdn/releases/latest.json?_t=1",
+                "https://b.cdn/releases/latest.json?_t=1",
+                "https://c.cdn/releases/latest.json?_t=1",
+            ])
+        );
+        assert_eq!(
+            candidates_for(
+                "https://a.cdn/build.zip",
+                &origins,
+                &strings(&["https://a.cdn"])
+            ),
+            strings(&[
+                "https://b.cdn/build.zip",
+                "https://c.cdn/build.zip",
+                "https://a.cdn/build.zip",
+            ])
+        );
+    }
+
+    #[test]
+    fn other_hosts_are_left_alone() {
+        let origins = strings(&["https://a.cdn"]);
+
+        assert_eq!(
+            candidates_for("https://a.cdn.evil/build.zip", &origins, &[]),
+            strings(&["https://a.cdn.evil/build.zip"])
+        );
+    }
+}
@@ -1,7 +1,7 @@
-#[cfg(windows)]
+#[cfg(any(windows, target_os = "linux"))]
 use std::process::Command;
 
-#[cfg(windows)]
+#[cfg(any(windows, target_os = "linux"))]
 pub trait CommandExtDetached {
     fn detached(&mut self) -> &mut Self;
 }
@@ -12,3 +12,14 @@ impl CommandExtDetached for Command {
         self
     }
 }
+
+/// Starts the process in its own process group, so closing the launcher or
+/// the terminal it was started from doesn't take the Explorer down with it.
+#[cfg(target_os = "linux")]
+impl CommandExtDetached for Command {
+    fn detached(&mut self) -> &mut Self {
+        use std::os::unix::process::CommandExt;
+
+        self.process_group(0)
+    }
+}
@@ -91,6 +91,10 @@ impl DeepLink {
         }
     }
 
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.args.get(key).map(String::as_str)
+    }
+
     pub fn original(&self) -> &str {
         &self.original
     }
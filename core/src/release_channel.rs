@@ -0,0 +1,148 @@
+//! Release channels of the Explorer. Each channel has its own `latest.json`,
+//! signed manifest and builds in the bucket:
+//!
+//! - `stable`: `@dcl/unity-explorer/releases`
+//! - any other: `@dcl/unity-explorer/releases/channels/<name>`
+//!
+//! The channel is picked, first match wins, from `--release-channel <name>`,
+//! the legacy `--prerelease` flag (beta), the `release-channel` deeplink
+//! parameter and the `release-channel` key of `config.json`.
+
+use std::fmt;
+
+use log::{info, warn};
+
+use crate::config;
+use crate::environment::AppEnvironment;
+use crate::protocols::Protocol;
+use crate::s3::RELEASE_PREFIX;
+use crate::utils;
+
+const STABLE: &str = "stable";
+const BETA: &str = "beta";
+const NIGHTLY: &str = "nightly";
+
+const DEEPLINK_KEY: &str = "release-channel";
+const MAX_NAME_LENGTH: usize = 64;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ReleaseChannel {
+    #[default]
+    Stable,
+    Beta,
+    Nightly,
+    Custom(String),
+}
+
+impl ReleaseChannel {
+    /// Names are case insensitive and, since custom ones end up in the
+    /// bucket path, limited to ASCII letters, digits, `-` and `_`.
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        let valid = !name.is_empty()
+            && name.len() <= MAX_NAME_LENGTH
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return None;
+        }
+
+        Some(match name.as_str() {
+            STABLE => Self::Stable,
+            BETA => Self::Beta,
+            NIGHTLY => Self::Nightly,
+            _ => Self::Custom(name),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Stable => STABLE,
+            Self::Beta => BETA,
+            Self::Nightly => NIGHTLY,
+            Self::Custom(name) => name,
+        }
+    }
+
+    /// Bucket prefix of the channel's manifests and builds.
+    pub fn release_prefix(&self) -> String {
+        match self {
+            Self::Stable => RELEASE_PREFIX.to_owned(),
+            other => format!("{}/channels/{}", RELEASE_PREFIX, other.name()),
+        }
+    }
+
+    /// Channel chosen by the user, `stable` when none is.
+    pub fn selected() -> Self {
+        let from_prerelease = || {
+            utils::is_prerelease(&utils::parse_args(AppEnvironment::raw_cmd_args()))
+                .then(|| BETA.to_owned())
+        };
+        let from_deeplink = || {
+            Protocol::value().and_then(|deeplink| deeplink.value(DEEPLINK_KEY).map(str::to_owned))
+        };
+
+        let sources: [(&str, &dyn Fn() -> Option<String>); 4] = [
+            ("arguments", &|| AppEnvironment::cmd_args().release_channel),
+            ("prerelease flag", &from_prerelease),
+            ("deeplink", &from_deeplink),
+            ("config", &config::release_channel),
+        ];
+
+        for (source, value) in sources {
+            let Some(name) = value() else {
+                continue;
+            };
+            match Self::parse(&name) {
+                Some(channel) => {
+                    info!("Release channel {} selected from {}", channel, source);
+                    return channel;
+                }
+                None => warn!(
+                    "Ignoring invalid release channel {:?} from {}",
+                    name, source
+                ),
+            }
+        }
+
+        Self::Stable
+    }
+}
+
+impl fmt::Display for ReleaseChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("stable", Some(ReleaseChannel::Stable))]
+    #[case("Beta", Some(ReleaseChannel::Beta))]
+    #[case(" nightly ", Some(ReleaseChannel::Nightly))]
+    #[case("qa-1_b", Some(ReleaseChannel::Custom("qa-1_b".to_owned())))]
+    #[case("", None)]
+    #[case("../latest", None)]
+    #[case("a/b", None)]
+    fn parses_channel_names(#[case] name: &str, #[case] expected: Option<ReleaseChannel>) {
+        assert_eq!(ReleaseChannel::parse(name), expected);
+    }
+
+    #[test]
+    fn each_channel_has_its_own_prefix() {
+        assert_eq!(ReleaseChannel::Stable.release_prefix(), RELEASE_PREFIX);
+        assert_eq!(
+            ReleaseChannel::Beta.release_prefix(),
+            "@dcl/unity-explorer/releases/channels/beta"
+        );
+        assert_eq!(
+            ReleaseChannel::Custom("qa".to_owned()).release_prefix(),
+            "@dcl/unity-explorer/releases/channels/qa"
+        );
+    }
+}
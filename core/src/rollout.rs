@@ -0,0 +1,73 @@
+//! Staged rollouts: a release published with `"rollout": {"percentage": 5,
+//! "previousVersion": "v1.2.3"}` is only installed by that share of players,
+//! the others stay on the previous version.
+//!
+//! Every launcher puts itself in one of 100 buckets by hashing its analytics
+//! user id together with the released version. The bucket doesn't change
+//! while the percentage grows, and each release picks different players.
+
+use sha2::{Digest, Sha256};
+
+pub const BUCKETS: u8 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RolloutAssignment {
+    /// From 0 to 99.
+    pub bucket: u8,
+    pub in_cohort: bool,
+}
+
+impl RolloutAssignment {
+    pub fn new(user_id: &str, version: &str, percentage: u8) -> Self {
+        let bucket = bucket(user_id, version);
+        Self {
+            bucket,
+            in_cohort: bucket < percentage,
+        }
+    }
+
+    pub const fn cohort(&self) -> &'static str {
+        if self.in_cohort {
+            "rollout"
+        } else {
+            "previous"
+        }
+    }
+}
+
+fn bucket(user_id: &str, version: &str) -> u8 {
+    let digest = Sha256::new()
+        .chain_update(user_id)
+        .chain_update(":")
+        .chain_update(version)
+        .finalize();
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(digest.get(..8).unwrap_or(&[0u8; 8]));
+    u64::from_be_bytes(prefix)
+        .checked_rem(u64::from(BUCKETS))
+        .and_then(|bucket| u8::try_from(bucket).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_is_stable_per_user_and_version() {
+        let first = RolloutAssignment::new("user", "v1.2.3", 50);
+        assert_eq!(first, RolloutAssignment::new("user", "v1.2.3", 50));
+        assert!(first.bucket < BUCKETS);
+
+        assert!(!RolloutAssignment::new("user", "v1.2.3", 0).in_cohort);
+        assert!(RolloutAssignment::new("user", "v1.2.3", 100).in_cohort);
+    }
+
+    #[test]
+    fn percentage_matches_the_share_of_users() {
+        let in_cohort = (0..10_000)
+            .filter(|id| RolloutAssignment::new(&format!("user-{id}"), "v1.2.3", 25).in_cohort)
+            .count();
+        assert!((2_250..2_750).contains(&in_cohort), "{in_cohort}");
+    }
+}
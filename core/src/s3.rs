@@ -1,25 +1,188 @@
+use ed25519_dalek::VerifyingKey;
 use reqwest;
+use semver::Version;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::environment::{AppEnvironment, Args};
-use crate::errors::{DCLError, DCLErrorTyped};
-use crate::utils::get_os_name;
+use crate::errors::{DCLError, DCLErrorResult, DCLErrorTyped};
+use crate::installs::compression::ArchiveFormat;
+use crate::installs::downloads::ExpectedChecksum;
+use crate::mirrors;
+use crate::release_channel::ReleaseChannel;
+use crate::utils::{app_version, get_os_name, is_sha256_hex};
+
+pub mod manifest;
+
+use manifest::ManifestSignatureError;
 
 pub const RELEASE_PREFIX: &str = "@dcl/unity-explorer/releases";
+/// Unsigned release manifest published next to the build of every version.
+const VERSION_RELEASE_FILE_NAME: &str = "release.json";
 
-#[derive(Deserialize, Debug)]
+/// Shape of both the unsigned `latest.json` and the payload of the signed
+/// release manifest, also of the manifests published for every version.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
 struct LatestRelease {
     version: String,
+    /// Oldest launcher that can install this release, older ones update
+    /// themselves first.
+    min_launcher_version: Option<String>,
+    /// Newest launcher that can install this release.
+    max_launcher_version: Option<String>,
+    /// Version every launcher of the channel goes back to instead of
+    /// `version`, set when a release is pulled.
+    rollback_to: Option<String>,
+    /// Staged rollout of `version`, see `rollout`.
+    rollout: Option<ReleaseRollout>,
+    /// Per-OS artifact metadata keyed by `get_os_name()`. Older manifests
+    /// don't have it, then the sibling `.sha256` object is used instead.
+    #[serde(default)]
+    artifacts: HashMap<String, ReleaseArtifact>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ReleaseArtifact {
+    /// File name of the build, e.g. `Decentraland_macos.tar.zst`. Defaults to
+    /// `Decentraland_<os>.zip`.
+    name: Option<String>,
+    /// Overrides the default `<bucket>/<prefix>/<version>/<name>`.
+    url: Option<String>,
+    sha256: Option<String>,
+    size: Option<u64>,
+    /// Size of the build once unpacked.
+    installed_size: Option<u64>,
+    /// Patches that build this version from older ones, see `installs::patches`.
+    #[serde(default)]
+    patches: Vec<PatchArtifact>,
+    /// Per-file listing of this version, see `installs::incremental`.
+    file_manifest: Option<FileManifestArtifact>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseRollout {
+    /// Share of players, from 0 to 100, that install the release.
+    pub percentage: u8,
+    /// Version installed by everyone else.
+    pub previous_version: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct FileManifestArtifact {
+    url: String,
+    sha256: String,
+    size: Option<u64>,
+    /// Files are served as `<filesBaseUrl>/<sha256>`.
+    files_base_url: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PatchArtifact {
+    from_version: String,
+    url: String,
+    sha256: String,
+    size: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct ReleaseResponse {
     pub browser_download_url: String,
     pub version: String,
+    pub checksum: Option<ExpectedChecksum>,
+    pub installed_size: Option<u64>,
+    pub min_launcher_version: Option<String>,
+    pub max_launcher_version: Option<String>,
+    pub rollback_to: Option<String>,
+    pub rollout: Option<ReleaseRollout>,
+    pub patches: Vec<ReleasePatch>,
+    pub file_manifest: Option<ReleaseFileManifest>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReleasePatch {
+    pub from_version: String,
+    pub url: String,
+    pub checksum: ExpectedChecksum,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReleaseFileManifest {
+    pub url: String,
+    pub checksum: ExpectedChecksum,
+    pub files_base_url: String,
 }
 
-fn latest_json_url() -> String {
+impl ReleaseResponse {
+    pub fn patch_from(&self, installed_version: &str) -> Option<&ReleasePatch> {
+        self.patches
+            .iter()
+            .find(|p| p.from_version == installed_version)
+    }
+
+    /// Errors when this launcher is outside the bounds of the release. The
+    /// flow decides what to do then, an installed build can still be launched.
+    pub fn check_launcher_version(&self) -> DCLErrorResult {
+        check_launcher_version(
+            &self.version,
+            self.min_launcher_version.as_deref(),
+            self.max_launcher_version.as_deref(),
+            app_version(),
+        )
+    }
+}
+
+fn release_patches(artifact: Option<&ReleaseArtifact>) -> Vec<ReleasePatch> {
+    let Some(artifact) = artifact else {
+        return Vec::new();
+    };
+
+    artifact
+        .patches
+        .iter()
+        .filter_map(|p| match parse_sha256_object(&p.sha256) {
+            Some(sha256) => Some(ReleasePatch {
+                from_version: p.from_version.clone(),
+                url: p.url.clone(),
+                checksum: ExpectedChecksum {
+                    sha256,
+                    size: p.size,
+                },
+            }),
+            None => {
+                log::warn!(
+                    "[release_patches] Patch from {} has an invalid sha256, ignored",
+                    p.from_version
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+fn release_file_manifest(artifact: Option<&ReleaseArtifact>) -> Option<ReleaseFileManifest> {
+    let file_manifest = artifact?.file_manifest.as_ref()?;
+    let Some(sha256) = parse_sha256_object(&file_manifest.sha256) else {
+        log::warn!("[release_file_manifest] File manifest has an invalid sha256, ignored");
+        return None;
+    };
+
+    Some(ReleaseFileManifest {
+        url: file_manifest.url.clone(),
+        checksum: ExpectedChecksum {
+            sha256,
+            size: file_manifest.size,
+        },
+        files_base_url: file_manifest.files_base_url.clone(),
+    })
+}
+
+fn latest_json_url(channel: &ReleaseChannel) -> String {
     let args: Args = AppEnvironment::cmd_args();
     if let Some(url) = args.use_latest_json_url {
         return url;
@@ -32,12 +195,77 @@ fn latest_json_url() -> String {
         .as_millis();
     format!(
         "{}/{}/latest.json?_t={}",
-        bucket_url, RELEASE_PREFIX, timestamp
+        bucket_url,
+        channel.release_prefix(),
+        timestamp
     )
 }
 
-async fn fetch_explorer_latest_release() -> DCLErrorTyped<LatestRelease> {
-    let url = latest_json_url();
+fn latest_manifest_url(channel: &ReleaseChannel) -> String {
+    let args: Args = AppEnvironment::cmd_args();
+    if let Some(url) = args.use_latest_manifest_url {
+        return url;
+    }
+
+    let bucket_url = AppEnvironment::bucket_url();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    format!(
+        "{}/{}/{}?_t={}",
+        bucket_url,
+        channel.release_prefix(),
+        manifest::MANIFEST_FILE_NAME,
+        timestamp
+    )
+}
+
+async fn fetch_signed_release(url: &str, key: &VerifyingKey) -> DCLErrorTyped<LatestRelease> {
+    log::info!(
+        "[fetch_signed_release] Fetching signed release manifest from: {}",
+        url
+    );
+
+    let response = reqwest::get(url).await?;
+    let status = response.status();
+
+    if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::FORBIDDEN {
+        return Err(DCLError::from_manifest(
+            url,
+            ManifestSignatureError::Unsigned {
+                status: status.into(),
+            },
+        ));
+    }
+
+    if !status.is_success() {
+        return DCLError::E2004_DOWNLOAD_FAILED_HTTP_CODE {
+            url: url.to_owned(),
+            code: status.into(),
+        }
+        .into();
+    }
+
+    let content = response.bytes().await?;
+    let payload = manifest::verify(&content, key).map_err(|e| DCLError::from_manifest(url, e))?;
+    let data = serde_json::from_slice::<LatestRelease>(&payload).map_err(|e| {
+        DCLError::from_manifest(url, ManifestSignatureError::Malformed(e.to_string()))
+    })?;
+
+    log::info!(
+        "[fetch_signed_release] Signed release verified successfully: {:?}",
+        data
+    );
+
+    Ok(data)
+}
+
+async fn fetch_explorer_latest_release(channel: &ReleaseChannel) -> DCLErrorTyped<LatestRelease> {
+    mirrors::with_fallback(&latest_json_url(channel), fetch_latest_release_from).await
+}
+
+async fn fetch_latest_release_from(url: String) -> DCLErrorTyped<LatestRelease> {
     log::info!(
         "[fetch_explorer_latest_release] Fetching latest release from: {}",
         url
@@ -62,27 +290,577 @@ async fn fetch_explorer_latest_release() -> DCLErrorTyped<LatestRelease> {
     Ok(data)
 }
 
-pub async fn get_latest_explorer_release() -> DCLErrorTyped<ReleaseResponse> {
+/// Manifest published with the build of `version` under `version_url`:
+/// the signed one when a key is compiled in, `release.json` otherwise.
+/// Returns it with its URL.
+///
+/// Versions published before these manifests have none. With a key they are
+/// refused, nothing authenticates their build. Without one only the build at
+/// the default location is known, checked against its sibling `.sha256`.
+async fn fetch_version_release(
+    version_url: &str,
+    version: &str,
+    public_key: Option<&VerifyingKey>,
+) -> DCLErrorTyped<(String, LatestRelease)> {
+    let (manifest_url, release) = match public_key {
+        Some(key) => {
+            let url = format!("{}/{}", version_url, manifest::VERSION_MANIFEST_FILE_NAME);
+            let release =
+                mirrors::with_fallback(
+                    &url,
+                    |url| async move { fetch_signed_release(&url, key).await },
+                )
+                .await
+                .map_err(|e| match e {
+                    DCLError::E2008_RELEASE_MANIFEST_UNVERIFIED {
+                        url,
+                        error: ManifestSignatureError::Unsigned { .. },
+                    } => DCLError::E2011_RELEASE_MANIFEST_NOT_PUBLISHED {
+                        version: version.to_owned(),
+                        url,
+                    },
+                    e => e,
+                })?;
+            (url, release)
+        }
+        None => {
+            let url = format!("{}/{}", version_url, VERSION_RELEASE_FILE_NAME);
+            match mirrors::with_fallback(&url, fetch_latest_release_from).await {
+                Ok(release) => (url, release),
+                Err(DCLError::E2004_DOWNLOAD_FAILED_HTTP_CODE {
+                    code: 403 | 404, ..
+                }) => {
+                    log::warn!(
+                        "[fetch_version_release] No release manifest at {}, using the build at the default location",
+                        url
+                    );
+                    let release = LatestRelease {
+                        version: version.to_owned(),
+                        ..LatestRelease::default()
+                    };
+                    (url, release)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    };
+
+    // A validly signed manifest of another version must not stand in for it
+    if release.version != version {
+        return Err(DCLError::from_manifest(
+            &manifest_url,
+            ManifestSignatureError::Malformed(format!(
+                "manifest is for {} instead of {}",
+                release.version, version
+            )),
+        ));
+    }
+    Ok((manifest_url, release))
+}
+
+/// Accepts both a bare digest and the `sha256sum` output format
+/// (`<digest>  <file name>`).
+fn parse_sha256_object(content: &str) -> Option<String> {
+    let digest = content.split_whitespace().next()?;
+    is_sha256_hex(digest).then(|| digest.to_lowercase())
+}
+
+/// `None` when no checksum is published next to the build. Any other
+/// failure is an error, an unreachable checksum must not skip the check.
+async fn fetch_sibling_sha256(release_url: &str) -> DCLErrorTyped<Option<String>> {
+    let url = format!("{}.sha256", release_url);
+    mirrors::with_fallback(&url, fetch_sha256_object).await
+}
+
+async fn fetch_sha256_object(url: String) -> DCLErrorTyped<Option<String>> {
+    let response = reqwest::get(&url).await?;
+    let status = response.status();
+
+    if status == reqwest::StatusCode::NOT_FOUND {
+        log::warn!("[fetch_sibling_sha256] No checksum published at {}", url);
+        return Ok(None);
+    }
+
+    if !status.is_success() {
+        return DCLError::E2004_DOWNLOAD_FAILED_HTTP_CODE {
+            url,
+            code: status.into(),
+        }
+        .into();
+    }
+
+    let content = response.text().await?;
+    match parse_sha256_object(&content) {
+        Some(digest) => Ok(Some(digest)),
+        None => Err(DCLError::from_manifest(
+            &url,
+            ManifestSignatureError::Malformed("invalid checksum content".to_owned()),
+        )),
+    }
+}
+
+async fn release_checksum(
+    artifact: Option<&ReleaseArtifact>,
+    release_url: &str,
+) -> DCLErrorTyped<Option<ExpectedChecksum>> {
+    let size = artifact.and_then(|a| a.size);
+    let from_manifest = artifact
+        .and_then(|a| a.sha256.as_deref())
+        .and_then(parse_sha256_object);
+
+    let sha256 = match from_manifest {
+        Some(sha256) => Some(sha256),
+        None => fetch_sibling_sha256(release_url).await?,
+    };
+
+    if sha256.is_none() {
+        log::warn!(
+            "[release_checksum] Release {} has no published checksum, integrity cannot be verified",
+            release_url
+        );
+    }
+
+    Ok(sha256.map(|sha256| ExpectedChecksum { sha256, size }))
+}
+
+/// Only the authenticated manifest may provide the checksum of a signed
+/// release: the sibling `.sha256` object is not covered by the signature.
+fn signed_checksum(
+    artifact: Option<&ReleaseArtifact>,
+    manifest_url: &str,
+) -> DCLErrorTyped<ExpectedChecksum> {
+    let sha256 = artifact
+        .and_then(|a| a.sha256.as_deref())
+        .and_then(parse_sha256_object)
+        .ok_or_else(|| {
+            DCLError::from_manifest(
+                manifest_url,
+                ManifestSignatureError::Malformed(format!(
+                    "no sha256 for the os {}",
+                    get_os_name()
+                )),
+            )
+        })?;
+
+    Ok(ExpectedChecksum {
+        sha256,
+        size: artifact.and_then(|a| a.size),
+    })
+}
+
+/// Versions may be `v` prefixed. A bound that isn't valid semver is ignored,
+/// a broken manifest shouldn't lock every launcher out.
+fn parse_launcher_version(value: &str) -> Option<Version> {
+    Version::parse(value.trim_start_matches('v'))
+        .inspect_err(|e| log::error!("Invalid launcher version {}: {}", value, e))
+        .ok()
+}
+
+fn check_launcher_version(
+    version: &str,
+    min_launcher_version: Option<&str>,
+    max_launcher_version: Option<&str>,
+    launcher_version: &str,
+) -> DCLErrorResult {
+    let Some(current) = parse_launcher_version(launcher_version) else {
+        return Ok(());
+    };
+
+    if let Some(min) = min_launcher_version
+        && parse_launcher_version(min).is_some_and(|min| current < min)
+    {
+        return Err(DCLError::E2009_LAUNCHER_UPDATE_REQUIRED {
+            version: version.to_owned(),
+            launcher_version: launcher_version.to_owned(),
+            min_launcher_version: min.to_owned(),
+        });
+    }
+
+    if let Some(max) = max_launcher_version
+        && parse_launcher_version(max).is_some_and(|max| current > max)
+    {
+        return Err(DCLError::E2010_LAUNCHER_VERSION_UNSUPPORTED {
+            version: version.to_owned(),
+            launcher_version: launcher_version.to_owned(),
+            max_launcher_version: max.to_owned(),
+        });
+    }
+
+    Ok(())
+}
+
+fn default_release_name() -> String {
+    format!("Decentraland_{}.zip", get_os_name())
+}
+
+/// Release of a version other than the latest one, e.g. a pinned version or
+/// the target of a rollback or a staged rollout.
+///
+/// It comes from the manifest published with the build of the version,
+/// verified like the latest one, so the checksum and the launcher bounds are
+/// as trustworthy. Channel-wide fields of that manifest are ignored. Versions
+/// published before per-version manifests fail with
+/// `E2011_RELEASE_MANIFEST_NOT_PUBLISHED` when manifests are signed, see
+/// `fetch_version_release`.
+pub async fn get_explorer_release(
+    channel: &ReleaseChannel,
+    version: &str,
+) -> DCLErrorTyped<ReleaseResponse> {
+    let version_url = format!(
+        "{}/{}/{}",
+        AppEnvironment::bucket_url(),
+        channel.release_prefix(),
+        version
+    );
+    let public_key =
+        manifest::public_key().map_err(|e| DCLError::from_manifest(&version_url, e))?;
+
+    let (manifest_url, release) =
+        fetch_version_release(&version_url, version, public_key.as_ref()).await?;
+
+    let response = release_response(channel, release, public_key.is_some(), &manifest_url).await?;
+    Ok(ReleaseResponse {
+        rollback_to: None,
+        rollout: None,
+        ..response
+    })
+}
+
+pub async fn get_latest_explorer_release(
+    channel: &ReleaseChannel,
+) -> DCLErrorTyped<ReleaseResponse> {
+    let manifest_url = latest_manifest_url(channel);
+    let public_key =
+        manifest::public_key().map_err(|e| DCLError::from_manifest(&manifest_url, e))?;
+
+    let latest_release = match &public_key {
+        Some(key) => {
+            mirrors::with_fallback(&manifest_url, |url| async move {
+                fetch_signed_release(&url, key).await
+            })
+            .await?
+        }
+        None => fetch_explorer_latest_release(channel).await?,
+    };
+
+    release_response(channel, latest_release, public_key.is_some(), &manifest_url).await
+}
+
+/// Only a signed manifest may provide the checksum when `signed`, the
+/// sibling `.sha256` object is the fallback of unsigned ones.
+async fn release_response(
+    channel: &ReleaseChannel,
+    release: LatestRelease,
+    signed: bool,
+    manifest_url: &str,
+) -> DCLErrorTyped<ReleaseResponse> {
     let url = AppEnvironment::bucket_url();
-    let latest_release = fetch_explorer_latest_release().await?;
     let os = get_os_name();
-    let release_name = format!("Decentraland_{}.zip", os);
-    let release_url = format!(
-        "{}/{}/{}/{}",
-        url, RELEASE_PREFIX, latest_release.version, release_name
-    );
+    let artifact = release.artifacts.get(os);
+    let release_name = artifact
+        .and_then(|a| a.name.clone())
+        .unwrap_or_else(default_release_name);
+    if ArchiveFormat::from_file_name(&release_name).is_none() {
+        log::warn!(
+            "[release_response] Unknown archive extension of {}, the format will be detected on install",
+            release_name
+        );
+    }
+    let release_url = artifact.and_then(|a| a.url.clone()).unwrap_or_else(|| {
+        format!(
+            "{}/{}/{}/{}",
+            url,
+            channel.release_prefix(),
+            release.version,
+            release_name
+        )
+    });
 
     log::info!(
-        "[get_latest_explorer_release] Release URL generated: {{ os: {}, version: {}, url: {} }}",
+        "[release_response] Release URL generated: {{ channel: {}, os: {}, version: {}, url: {} }}",
+        channel,
         os,
-        latest_release.version,
+        release.version,
         release_url
     );
 
-    let response = ReleaseResponse {
-        browser_download_url: release_url,
-        version: latest_release.version,
+    let checksum = if signed {
+        Some(signed_checksum(artifact, manifest_url)?)
+    } else {
+        release_checksum(artifact, &release_url).await?
     };
 
-    Ok(response)
+    Ok(ReleaseResponse {
+        browser_download_url: release_url,
+        version: release.version,
+        checksum,
+        installed_size: artifact.and_then(|a| a.installed_size),
+        min_launcher_version: release.min_launcher_version,
+        max_launcher_version: release.max_launcher_version,
+        rollback_to: release.rollback_to,
+        rollout: release.rollout,
+        patches: release_patches(artifact),
+        file_manifest: release_file_manifest(artifact),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{Reply, StandIn};
+    use rstest::rstest;
+
+    const DIGEST: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    #[rstest]
+    #[case(DIGEST, Some(DIGEST))]
+    #[case(
+        "9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08\n",
+        Some(DIGEST)
+    )]
+    #[case(
+        "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08  Decentraland_macos.zip",
+        Some(DIGEST)
+    )]
+    #[case("", None)]
+    #[case("not-a-digest", None)]
+    #[case("9f86d081884c7d659a2feaa0c55ad015", None)]
+    fn parses_sha256_object(#[case] content: &str, #[case] expected: Option<&str>) {
+        assert_eq!(parse_sha256_object(content).as_deref(), expected);
+    }
+
+    #[test]
+    fn latest_release_without_artifacts_is_accepted() -> serde_json::Result<()> {
+        let release: LatestRelease = serde_json::from_str(r#"{"version":"v1.2.3"}"#)?;
+        assert_eq!(release.version, "v1.2.3");
+        assert!(release.artifacts.is_empty());
+        assert_eq!(release.rollback_to, None);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(r#"{"version":"v2"}"#, "1.0.0", None)]
+    #[case(r#"{"version":"v2","minLauncherVersion":"1.2.0"}"#, "1.2.0", None)]
+    #[case(
+        r#"{"version":"v2","minLauncherVersion":"v1.10.0"}"#,
+        "1.9.3",
+        Some("E2009_LAUNCHER_UPDATE_REQUIRED")
+    )]
+    #[case(
+        r#"{"version":"v2","maxLauncherVersion":"1.9.0"}"#,
+        "1.10.0",
+        Some("E2010_LAUNCHER_VERSION_UNSUPPORTED")
+    )]
+    #[case(r#"{"version":"v2","minLauncherVersion":"latest"}"#, "1.0.0", None)]
+    fn launcher_version_is_checked(
+        #[case] release: &str,
+        #[case] launcher_version: &str,
+        #[case] expected: Option<&str>,
+    ) -> serde_json::Result<()> {
+        let release: LatestRelease = serde_json::from_str(release)?;
+        let result = check_launcher_version(
+            &release.version,
+            release.min_launcher_version.as_deref(),
+            release.max_launcher_version.as_deref(),
+            launcher_version,
+        );
+        assert_eq!(result.err().as_ref().map(DCLError::code), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn rollback_is_parsed() -> serde_json::Result<()> {
+        let release: LatestRelease =
+            serde_json::from_str(r#"{"version":"v1.2.3","rollbackTo":"v1.2.2"}"#)?;
+        assert_eq!(release.rollback_to.as_deref(), Some("v1.2.2"));
+        Ok(())
+    }
+
+    #[test]
+    fn rollout_is_parsed() -> serde_json::Result<()> {
+        let release: LatestRelease = serde_json::from_str(
+            r#"{"version":"v1.2.3","rollout":{"percentage":25,"previousVersion":"v1.2.2"}}"#,
+        )?;
+        assert!(
+            release
+                .rollout
+                .is_some_and(|r| r.percentage == 25 && r.previous_version == "v1.2.2")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn patches_without_valid_checksum_are_ignored() -> serde_json::Result<()> {
+        let artifact: ReleaseArtifact = serde_json::from_str(&format!(
+            r#"{{"patches":[
+                {{"fromVersion":"v1.2.2","url":"https://cdn/p1.patch","sha256":"{}","size":10}},
+                {{"fromVersion":"v1.2.1","url":"https://cdn/p2.patch","sha256":"nope"}}
+            ]}}"#,
+            DIGEST
+        ))?;
+
+        let patches = release_patches(Some(&artifact));
+
+        assert_eq!(patches.len(), 1);
+        assert!(patches.iter().all(|p| p.from_version == "v1.2.2"));
+        assert!(patches.iter().all(|p| p.checksum.size == Some(10)));
+        Ok(())
+    }
+
+    #[test]
+    fn file_manifest_is_parsed() -> serde_json::Result<()> {
+        let artifact: ReleaseArtifact = serde_json::from_str(&format!(
+            r#"{{"fileManifest":{{"url":"https://cdn/files.json","sha256":"{}","filesBaseUrl":"https://cdn/files"}}}}"#,
+            DIGEST
+        ))?;
+
+        let file_manifest = release_file_manifest(Some(&artifact));
+
+        assert!(file_manifest.is_some_and(
+            |m| m.checksum.sha256 == DIGEST && m.files_base_url == "https://cdn/files"
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn signed_release_is_verified() -> anyhow::Result<()> {
+        let key = manifest::tests::test_signing_key();
+        let payload = format!(
+            r#"{{"version":"v1.2.3","minLauncherVersion":"1.20.0","artifacts":{{"{}":{{"name":"Decentraland.tar.zst","sha256":"{}","size":4}}}}}}"#,
+            get_os_name(),
+            DIGEST
+        );
+        let server = StandIn::start(vec![Reply::ok(manifest::tests::signed_envelope(
+            payload.as_bytes(),
+            &key,
+        ))])
+        .await?;
+
+        let manifest_url = server.url(manifest::MANIFEST_FILE_NAME);
+        let release = fetch_signed_release(&manifest_url, &key.verifying_key()).await?;
+        server.requests().await?;
+
+        assert_eq!(release.version, "v1.2.3");
+        assert_eq!(release.min_launcher_version.as_deref(), Some("1.20.0"));
+        let artifact = release.artifacts.get(get_os_name());
+        assert_eq!(
+            artifact.and_then(|a| a.name.as_deref()),
+            Some("Decentraland.tar.zst")
+        );
+        let checksum = signed_checksum(artifact, &manifest_url)?;
+        assert_eq!(checksum.sha256, DIGEST);
+        assert_eq!(checksum.size, Some(4));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn version_release_is_signed_for_that_version() -> anyhow::Result<()> {
+        let key = manifest::tests::test_signing_key();
+        let payload = format!(
+            r#"{{"version":"v1.2.2","minLauncherVersion":"1.20.0","artifacts":{{"{}":{{"sha256":"{}"}}}}}}"#,
+            get_os_name(),
+            DIGEST
+        );
+        let envelope = manifest::tests::signed_envelope(payload.as_bytes(), &key);
+        let server = StandIn::start(vec![Reply::ok(envelope.clone()), Reply::ok(envelope)]).await?;
+
+        let (manifest_url, release) =
+            fetch_version_release(&server.url("v1.2.2"), "v1.2.2", Some(&key.verifying_key()))
+                .await?;
+        let other =
+            fetch_version_release(&server.url("v1.2.1"), "v1.2.1", Some(&key.verifying_key()))
+                .await;
+        let requests = server.requests().await?;
+
+        assert!(manifest_url.ends_with("/v1.2.2/release.manifest.json"));
+        assert!(requests.iter().all(|r| r.contains("release.manifest.json")));
+        assert_eq!(release.min_launcher_version.as_deref(), Some("1.20.0"));
+        assert_eq!(
+            signed_checksum(release.artifacts.get(get_os_name()), &manifest_url)?.sha256,
+            DIGEST
+        );
+        assert!(matches!(
+            other,
+            Err(DCLError::E2008_RELEASE_MANIFEST_UNVERIFIED {
+                error: ManifestSignatureError::Malformed(_),
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unreachable_sibling_checksum_is_an_error() -> anyhow::Result<()> {
+        let server = StandIn::start(vec![
+            Reply::not_found(),
+            Reply::new("503 Service Unavailable", Vec::new()),
+            Reply::ok(DIGEST),
+        ])
+        .await?;
+
+        let release_url = server.url("build.zip");
+        let missing = fetch_sibling_sha256(&release_url).await;
+        let failed = fetch_sibling_sha256(&release_url).await;
+        let published = fetch_sibling_sha256(&release_url).await;
+        server.requests().await?;
+
+        assert!(matches!(missing, Ok(None)));
+        assert!(matches!(
+            failed,
+            Err(DCLError::E2004_DOWNLOAD_FAILED_HTTP_CODE { code: 503, .. })
+        ));
+        assert_eq!(published?.as_deref(), Some(DIGEST));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn missing_signed_release_is_refused() -> anyhow::Result<()> {
+        let key = manifest::tests::test_signing_key();
+        let server = StandIn::start(vec![Reply::not_found()]).await?;
+
+        let result = fetch_signed_release(
+            &server.url(manifest::MANIFEST_FILE_NAME),
+            &key.verifying_key(),
+        )
+        .await;
+        server.requests().await?;
+
+        assert!(matches!(
+            result,
+            Err(DCLError::E2008_RELEASE_MANIFEST_UNVERIFIED {
+                error: ManifestSignatureError::Unsigned { status: 404 },
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn version_without_signed_manifest_is_not_published() -> anyhow::Result<()> {
+        let key = manifest::tests::test_signing_key();
+        let server = StandIn::start(vec![Reply::not_found()]).await?;
+
+        let result =
+            fetch_version_release(&server.url("v1.0.0"), "v1.0.0", Some(&key.verifying_key()))
+                .await;
+        server.requests().await?;
+
+        assert!(matches!(
+            result,
+            Err(DCLError::E2011_RELEASE_MANIFEST_NOT_PUBLISHED { version, .. }) if version == "v1.0.0"
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn version_without_unsigned_manifest_uses_the_default_build() -> anyhow::Result<()> {
+        let server = StandIn::start(vec![Reply::not_found()]).await?;
+
+        let (_, release) = fetch_version_release(&server.url("v1.0.0"), "v1.0.0", None).await?;
+        server.requests().await?;
+
+        assert_eq!(release.version, "v1.0.0");
+        assert!(release.artifacts.is_empty());
+        Ok(())
+    }
 }
@@ -0,0 +1,152 @@
+use std::fmt::{Display, Formatter};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::Deserialize;
+
+use crate::environment::AppEnvironment;
+
+pub const MANIFEST_FILE_NAME: &str = "latest.manifest.json";
+/// Signed manifest published next to the build of every version.
+pub const VERSION_MANIFEST_FILE_NAME: &str = "release.manifest.json";
+
+/// Envelope published next to `latest.json`. The payload is kept as the exact
+/// signed bytes (base64) so verification doesn't depend on how JSON is
+/// re-serialized; it decodes to the same shape as `latest.json`.
+#[derive(Deserialize)]
+struct SignedEnvelope {
+    payload: String,
+    signature: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ManifestSignatureError {
+    InvalidPublicKey,
+    Unsigned { status: u16 },
+    Malformed(String),
+    BadSignature,
+}
+
+impl Display for ManifestSignatureError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidPublicKey => write!(f, "release manifest public key is invalid"),
+            Self::Unsigned { status } => {
+                write!(f, "signed release manifest is not published: {}", status)
+            }
+            Self::Malformed(reason) => write!(f, "release manifest is malformed: {}", reason),
+            Self::BadSignature => write!(f, "release manifest signature doesn't match"),
+        }
+    }
+}
+
+pub fn parse_public_key(raw: &str) -> Result<VerifyingKey, ManifestSignatureError> {
+    let bytes = STANDARD
+        .decode(raw.trim())
+        .map_err(|_| ManifestSignatureError::InvalidPublicKey)?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| ManifestSignatureError::InvalidPublicKey)?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| ManifestSignatureError::InvalidPublicKey)
+}
+
+/// Key used to authenticate release manifests. Production builds can't be
+/// made without it (see `build.rs`), other builds fall back to the unsigned
+/// `latest.json`.
+pub fn public_key() -> Result<Option<VerifyingKey>, ManifestSignatureError> {
+    match AppEnvironment::manifest_public_key() {
+        Some(raw) => parse_public_key(raw).map(Some),
+        None => {
+            log::warn!(
+                "EXPLORER_MANIFEST_PUBLIC_KEY is not provided via env variables, release manifests are not verified"
+            );
+            Ok(None)
+        }
+    }
+}
+
+/// Returns the signed payload bytes once the signature is verified.
+pub fn verify(content: &[u8], key: &VerifyingKey) -> Result<Vec<u8>, ManifestSignatureError> {
+    let envelope: SignedEnvelope = serde_json::from_slice(content)
+        .map_err(|e| ManifestSignatureError::Malformed(e.to_string()))?;
+
+    let payload = STANDARD
+        .decode(envelope.payload.trim())
+        .map_err(|e| ManifestSignatureError::Malformed(format!("payload: {}", e)))?;
+    let signature = STANDARD
+        .decode(envelope.signature.trim())
+        .map_err(|e| ManifestSignatureError::Malformed(format!("signature: {}", e)))?;
+    let signature = Signature::from_slice(&signature)
+        .map_err(|e| ManifestSignatureError::Malformed(format!("signature: {}", e)))?;
+
+    key.verify_strict(&payload, &signature)
+        .map_err(|_| ManifestSignatureError::BadSignature)?;
+
+    Ok(payload)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    pub fn test_signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[7u8; 32])
+    }
+
+    pub fn signed_envelope(payload: &[u8], key: &SigningKey) -> Vec<u8> {
+        let signature = key.sign(payload);
+        serde_json::json!({
+            "payload": STANDARD.encode(payload),
+            "signature": STANDARD.encode(signature.to_bytes()),
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    const PAYLOAD: &[u8] = br#"{"version":"v1.2.3"}"#;
+
+    #[test]
+    fn verifies_a_signed_payload() -> Result<(), ManifestSignatureError> {
+        let key = test_signing_key();
+        let envelope = signed_envelope(PAYLOAD, &key);
+        assert_eq!(verify(&envelope, &key.verifying_key())?, PAYLOAD);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_a_payload_signed_by_another_key() {
+        let envelope = signed_envelope(PAYLOAD, &SigningKey::from_bytes(&[9u8; 32]));
+        let result = verify(&envelope, &test_signing_key().verifying_key());
+        assert!(matches!(result, Err(ManifestSignatureError::BadSignature)));
+    }
+
+    #[test]
+    fn rejects_a_tampered_payload() {
+        let key = test_signing_key();
+        let signature = key.sign(PAYLOAD);
+        let envelope = serde_json::json!({
+            "payload": STANDARD.encode(br#"{"version":"v6.6.6"}"#),
+            "signature": STANDARD.encode(signature.to_bytes()),
+        })
+        .to_string();
+
+        let result = verify(envelope.as_bytes(), &key.verifying_key());
+        assert!(matches!(result, Err(ManifestSignatureError::BadSignature)));
+    }
+
+    #[test]
+    fn rejects_a_plain_latest_json() {
+        let result = verify(PAYLOAD, &test_signing_key().verifying_key());
+        assert!(matches!(result, Err(ManifestSignatureError::Malformed(_))));
+    }
+
+    #[test]
+    fn parses_a_base64_public_key() {
+        let key = test_signing_key().verifying_key();
+        let raw = STANDARD.encode(key.to_bytes());
+        assert!(matches!(parse_public_key(&raw), Ok(parsed) if parsed == key));
+        assert!(parse_public_key("AAAA").is_err());
+    }
+}
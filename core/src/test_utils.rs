@@ -0,0 +1,166 @@
+//! Local HTTP stand-in for the bucket, used by tests that exercise the real
+//! `reqwest` code paths without touching the network.
+
+use std::io::{ErrorKind, Result};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+pub struct Reply {
+    status: &'static str,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    cut_after: Option<usize>,
+    ranged: bool,
+}
+
+impl Reply {
+    pub fn new(status: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+            cut_after: None,
+            ranged: false,
+        }
+    }
+
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self::new("200 OK", body)
+    }
+
+    pub fn not_found() -> Self {
+        Self::new("404 Not Found", Vec::new())
+    }
+
+    #[must_use]
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_owned(), value.into()));
+        self
+    }
+
+    /// Drops the connection after `bytes` of the body have been sent while
+    /// still advertising the full `Content-Length`.
+    #[must_use]
+    pub const fn cut_after(mut self, bytes: usize) -> Self {
+        self.cut_after = Some(bytes);
+        self
+    }
+
+    /// Serves the part of `body` asked for by the `Range` header of the
+    /// request, or all of it, and answers `HEAD` requests without a body.
+    pub fn ranges(body: impl Into<Vec<u8>>) -> Self {
+        let mut reply = Self::ok(body).header("Accept-Ranges", "bytes");
+        reply.ranged = true;
+        reply
+    }
+
+    fn requested_range(&self, request: &str) -> Option<(usize, usize)> {
+        let range = request
+            .lines()
+            .find_map(|line| line.strip_prefix("range: bytes="))?;
+        let (start, end) = range.trim().split_once('-')?;
+        let last = self.body.len().checked_sub(1)?;
+        Some((start.parse().ok()?, end.parse().unwrap_or(last).min(last)))
+    }
+
+    async fn write_to(&self, socket: &mut TcpStream, request: &str) -> Result<()> {
+        let mut status = self.status;
+        let mut headers = self.headers.clone();
+        let mut body = self.body.as_slice();
+        if self.ranged
+            && let Some((start, end)) = self.requested_range(request)
+        {
+            status = "206 Partial Content";
+            headers.push((
+                "Content-Range".to_owned(),
+                format!("bytes {}-{}/{}", start, end, self.body.len()),
+            ));
+            body = self.body.get(start..=end).unwrap_or_default();
+        }
+
+        let headers: String = headers
+            .iter()
+            .flat_map(|(name, value)| [name.as_str(), ": ", value.as_str(), "\r\n"])
+            .collect();
+        let head = format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n",
+            status,
+            body.len(),
+            headers
+        );
+        socket.write_all(head.as_bytes()).await?;
+
+        if self.ranged && request.starts_with("head ") {
+            return socket.flush().await;
+        }
+        let body = match self.cut_after {
+            Some(bytes) => body.get(..bytes).unwrap_or_default(),
+            None => body,
+        };
+        socket.write_all(body).await?;
+        socket.flush().await
+    }
+}
+
+/// Serves `replies` in order, one connection each, and records the request
+/// heads (lowercased) it received.
+pub struct StandIn {
+    base_url: String,
+    handle: JoinHandle<Result<Vec<String>>>,
+}
+
+impl StandIn {
+    pub async fn start(replies: Vec<Reply>) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for reply in replies {
+                let (mut socket, _) = listener.accept().await?;
+                let request = read_request_head(&mut socket).await?;
+                match reply.write_to(&mut socket, &request).await {
+                    Ok(()) => {}
+                    // The client stopped reading, e.g. a paused download
+                    Err(e)
+                        if matches!(
+                            e.kind(),
+                            ErrorKind::BrokenPipe | ErrorKind::ConnectionReset
+                        ) => {}
+                    Err(e) => return Err(e),
+                }
+                requests.push(request);
+            }
+            Ok(requests)
+        });
+
+        Ok(Self { base_url, handle })
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    pub async fn requests(self) -> anyhow::Result<Vec<String>> {
+        Ok(self.handle.await??)
+    }
+}
+
+async fn read_request_head(socket: &mut TcpStream) -> Result<String> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = socket.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(buf.get(..n).unwrap_or_default());
+    }
+    Ok(String::from_utf8_lossy(&head).to_lowercase())
+}
+
+pub fn temp_path(prefix: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("{}-{}", prefix, uuid::Uuid::new_v4()))
+}
@@ -6,7 +6,11 @@ pub enum Status {
     #[serde(rename_all = "camelCase")]
     State { step: Step },
     #[serde(rename_all = "camelCase")]
-    Error { message: String },
+    Error {
+        message: String,
+        can_free_space: bool,
+        can_repair: bool,
+    },
 }
 
 #[derive(Clone, Serialize)]
@@ -19,9 +23,11 @@ pub enum Step {
     #[serde(rename_all = "camelCase")]
     Fetching,
     #[serde(rename_all = "camelCase")]
+    Verifying,
+    #[serde(rename_all = "camelCase")]
     Downloading { progress: u8, build_type: BuildType },
     #[serde(rename_all = "camelCase")]
-    Installing { build_type: BuildType },
+    Installing { progress: u8, build_type: BuildType },
     #[serde(rename_all = "camelCase")]
     Launching,
 }
@@ -34,6 +40,7 @@ pub enum LauncherUpdate {
     DownloadFinished,
     InstallingUpdate,
     RestartingApp,
+    Required,
 }
 
 impl From<LauncherUpdate> for Status {
@@ -12,7 +12,8 @@ pub fn get_os_name() -> &'static str {
     }
 }
 
-fn is_valid_version(version: &str) -> bool {
+#[must_use]
+pub fn is_valid_version(version: &str) -> bool {
     let version_regex = Regex::new(r"^(v(0|[1-9]\d*)\.(0|[1-9]\d*)\.(0|[1-9]\d*)(?:-((?:0|[1-9]\d*|\d*[a-zA-Z-][0-9a-zA-Z-]*)(?:\.(?:0|[1-9]\d*|\d*[a-zA-Z-][0-9a-zA-Z-]*))*))?(?:\+([0-9a-zA-Z-]+(?:\.[0-9a-zA-Z-]+)*))?)|dev$")
         .ok();
 
@@ -20,6 +21,29 @@ fn is_valid_version(version: &str) -> bool {
     version_regex.is_some_and(|regex| regex.is_match(version))
 }
 
+/// Lowercase or uppercase hex of a SHA-256 digest.
+#[must_use]
+pub fn is_sha256_hex(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/**
+ * Parses the `--key=value` command-line arguments, a bare `--flag` gets the value "true".
+ * Arguments that aren't flags, e.g. deeplinks, are skipped.
+ * @returns The arguments for `get_version`, `should_run_dev_version` and `downloaded_file_path`.
+ */
+#[must_use]
+pub fn parse_args(args: impl Iterator<Item = String>) -> HashMap<String, String> {
+    args.filter_map(|arg| {
+        let flag = arg.strip_prefix("--")?;
+        Some(match flag.split_once('=') {
+            Some((key, value)) => (key.to_owned(), value.to_owned()),
+            None => (flag.to_owned(), "true".to_owned()),
+        })
+    })
+    .collect()
+}
+
 /**
  * Retrieves the version from the parsed command-line arguments.
  * @returns The version string if available, otherwise undefined.
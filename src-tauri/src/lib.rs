@@ -13,6 +13,8 @@
 use dcl_launcher_core::analytics::event::Event;
 use dcl_launcher_core::environment::{AppEnvironment, Args};
 use dcl_launcher_core::errors::FlowError;
+use dcl_launcher_core::flow_control::FlowControl;
+use dcl_launcher_core::installs;
 use dcl_launcher_core::log::{error, info};
 use dcl_launcher_core::protocols::Protocol;
 use dcl_launcher_core::types::LauncherUpdate;
@@ -73,6 +75,57 @@ async fn retry(
     launch_internal(app, state, channel).await
 }
 
+#[tauri::command]
+async fn free_space_and_retry(
+    app: AppHandle,
+    state: State<'_, MutState>,
+    channel: Channel<types::Status>,
+) -> Result<(), String> {
+    info!("tauri command: free_space_and_retry");
+    let freed = match installs::prune_old_versions() {
+        Ok(freed) => freed,
+        Err(e) => {
+            error!("Cannot remove the old versions: {:#}", e);
+            let flow_error = FlowError::from(&e);
+            StatusChannel(channel).notify_error(&flow_error);
+            return Err(flow_error.user_message);
+        }
+    };
+    state
+        .lock()
+        .await
+        .analytics
+        .lock()
+        .await
+        .track_and_flush_silent(Event::OLD_VERSIONS_PRUNED { freed })
+        .await;
+    launch_internal(app, state, channel).await
+}
+
+#[tauri::command]
+async fn repair_and_retry(
+    app: AppHandle,
+    state: State<'_, MutState>,
+    channel: Channel<types::Status>,
+) -> Result<(), String> {
+    info!("tauri command: repair_and_retry");
+    let status_channel = StatusChannel(channel.clone());
+    let guard = state.lock().await;
+    guard.flow_control.begin();
+    let repaired = guard
+        .flow
+        .repair(&status_channel, guard.state.clone(), false)
+        .await;
+    drop(guard);
+    if let Err(e) = repaired {
+        error!("Cannot repair the installed build: {:#}", e);
+        let flow_error = FlowError::from(&e);
+        status_channel.notify_error(&flow_error);
+        return Err(flow_error.user_message);
+    }
+    launch_internal(app, state, channel).await
+}
+
 #[tauri::command]
 async fn launch(
     app: AppHandle,
@@ -83,6 +136,24 @@ async fn launch(
     launch_internal(app, state, channel).await
 }
 
+#[tauri::command]
+fn cancel(control: State<'_, FlowControl>) {
+    info!("tauri command: cancel");
+    control.cancel();
+}
+
+#[tauri::command]
+fn pause(control: State<'_, FlowControl>) {
+    info!("tauri command: pause");
+    control.pause();
+}
+
+#[tauri::command]
+fn resume(control: State<'_, FlowControl>) {
+    info!("tauri command: resume");
+    control.resume();
+}
+
 async fn launch_internal(
     app: AppHandle,
     state: State<'_, MutState>,
@@ -92,6 +163,7 @@ async fn launch_internal(
     let guard = state.lock().await;
 
     let flow_state = guard.state.clone();
+    guard.flow_control.begin();
 
     if let Err(e) = update_if_needed_and_restart(&app, &guard, &status_channel).await {
         error!("Cannot update the launcher: {}", e);
@@ -168,46 +240,57 @@ async fn update_if_needed_and_restart(
     if let Some(update) = current_updater(app)?.check().await? {
         let mut downloaded: usize = 0;
 
-        let content = update
-            .download(
-                |chunk_length, content_length| {
-                    downloaded = downloaded.saturating_add(chunk_length);
-                    info!("downloaded {downloaded} from {content_length:?}");
-                    match content_length {
-                        Some(length) => {
-                            let current = (downloaded as u64).saturating_mul(100);
-                            let percentage = current.checked_div(length);
-
-                            match percentage {
-                                Some(p) => {
-                                    let progress: u8 = p.min(100) as u8;
-
-                                    channel.send_silent(
-                                        LauncherUpdate::Downloading {
-                                            progress: Some(progress),
-                                        }
-                                        .into(),
-                                    );
-                                }
-                                None => {
-                                    channel.send_silent(
-                                        LauncherUpdate::Downloading { progress: None }.into(),
-                                    );
-                                }
+        let download = update.download(
+            |chunk_length, content_length| {
+                downloaded = downloaded.saturating_add(chunk_length);
+                info!("downloaded {downloaded} from {content_length:?}");
+                match content_length {
+                    Some(length) => {
+                        let current = (downloaded as u64).saturating_mul(100);
+                        let percentage = current.checked_div(length);
+
+                        match percentage {
+                            Some(p) => {
+                                let progress: u8 = p.min(100) as u8;
+
+                                channel.send_silent(
+                                    LauncherUpdate::Downloading {
+                                        progress: Some(progress),
+                                    }
+                                    .into(),
+                                );
+                            }
+                            None => {
+                                channel.send_silent(
+                                    LauncherUpdate::Downloading { progress: None }.into(),
+                                );
                             }
-                        }
-                        None => {
-                            channel
-                                .send_silent(LauncherUpdate::Downloading { progress: None }.into());
                         }
                     }
-                },
-                || {
-                    info!("download finished");
-                    channel.send_silent(LauncherUpdate::DownloadFinished.into());
-                },
-            )
-            .await?;
+                    None => {
+                        channel.send_silent(LauncherUpdate::Downloading { progress: None }.into());
+                    }
+                }
+            },
+            || {
+                info!("download finished");
+                channel.send_silent(LauncherUpdate::DownloadFinished.into());
+            },
+        );
+        // The flow that follows stops on its own, it shares the cancellation
+        let Ok(content) = app_state.flow_control.until_cancelled(download).await else {
+            info!("launcher update cancelled");
+            app_state
+                .analytics
+                .lock()
+                .await
+                .track_and_flush_silent(Event::LAUNCHER_UPDATE_CANCELLED {
+                    version: update.version.clone(),
+                })
+                .await;
+            return Ok(());
+        };
+        let content = content?;
 
         channel.send_silent(LauncherUpdate::InstallingUpdate.into());
         update.install(content)?;
@@ -273,6 +356,7 @@ fn setup(a: &mut App) -> std::result::Result<(), Box<dyn std::error::Error>> {
     setup_deeplink(a, &app_state.protocol);
     Protocol::try_seed_from_startup_location();
 
+    a.manage(app_state.flow_control.clone());
     let mut_state: MutState = Arc::new(Mutex::new(app_state));
     a.manage(mut_state);
     Ok(())
@@ -292,7 +376,15 @@ pub fn run() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_deep_link::init())
         .setup(setup)
-        .invoke_handler(tauri::generate_handler![launch, retry])
+        .invoke_handler(tauri::generate_handler![
+            launch,
+            retry,
+            free_space_and_retry,
+            repair_and_retry,
+            cancel,
+            pause,
+            resume
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -46,6 +46,13 @@ export const ErrorDialogButton = styled(Button)((props) => ({
   borderRadius: "12px",
 }));
 
+export const StepControlButton = styled(Button)((props) => ({
+  ...props,
+  minWidth: "0px",
+  padding: "0px 6px",
+  fontSize: "12px",
+}));
+
 export const LoadingBar = styled(LinearProgress)((props) => ({
   ...props,
   width: "348px",
@@ -7,6 +7,7 @@ import {
   Logo,
   ErrorIcon,
   ErrorDialogButton,
+  StepControlButton,
 } from "./Home.styles";
 import { versionLabel } from "./VersionLabel";
 
@@ -75,8 +76,10 @@ const channel = newChannelProxy();
 
 export const Home: React.FC = memo(() => {
   const currentStatus = useChannelUpdates(channel);
+  const [paused, setPaused] = useState(false);
 
   const rustCall = async (functionName: string) => {
+    setPaused(false);
     const newChannel = new Channel<Status>();
     channel.assignNewChannel(newChannel);
     await invoke(functionName, { channel: newChannel }).catch(console.error);
@@ -84,6 +87,18 @@ export const Home: React.FC = memo(() => {
 
   const launchFlow = async () => await rustCall("launch");
   const retryFlow = async () => await rustCall("retry");
+  const freeSpaceAndRetry = async () => await rustCall("free_space_and_retry");
+  const repairAndRetry = async () => await rustCall("repair_and_retry");
+
+  const pauseDownload = async () => {
+    await invoke("pause").catch(console.error);
+    setPaused(true);
+  };
+  const resumeDownload = async () => {
+    await invoke("resume").catch(console.error);
+    setPaused(false);
+  };
+  const cancelFlow = async () => await invoke("cancel").catch(console.error);
 
   useEffect(() => {
     launchFlow();
@@ -110,12 +125,18 @@ export const Home: React.FC = memo(() => {
                 return renderStep("Installing update...");
               case "restartingApp":
                 return renderStep("Restarting app...");
+              case "required":
+                return renderStep(
+                  "A launcher update is needed for the latest version, launching the installed one...",
+                );
             }
           }
           case "deeplinkOpening":
             return renderDeeplinkOpeningStep();
           case "fetching":
             return renderFetchStep();
+          case "verifying":
+            return renderVerifyStep();
           case "downloading": {
             let data = currentStatus.data.step.data;
             let isUpdate = data.buildType === BuildType.Update;
@@ -125,12 +146,16 @@ export const Home: React.FC = memo(() => {
           case "installing":
             let data = currentStatus.data.step.data;
             let isUpdate = data.buildType === BuildType.Update;
-            return renderInstallStep(isUpdate);
+            return renderInstallStep(isUpdate, data.progress);
           case "launching":
             return renderLaunchStep();
         }
       case "error":
-        return renderError(currentStatus.data.message);
+        return renderError(
+          currentStatus.data.message,
+          currentStatus.data.canFreeSpace,
+          currentStatus.data.canRepair,
+        );
       default:
         return null;
     }
@@ -140,20 +165,42 @@ export const Home: React.FC = memo(() => {
 
   const renderFetchStep = () => renderStep("Fetching Latest...");
 
+  const renderVerifyStep = () => renderStep("Verifying Installation...");
+
   const renderDownloadStep = (isUpdate: boolean, downloadingProgress: number) =>
     renderStep(
-      isUpdate ? "Downloading Update..." : "Downloading Decentraland...",
+      paused
+        ? "Download Paused"
+        : isUpdate
+          ? "Downloading Update..."
+          : "Downloading Decentraland...",
       downloadingProgress,
+      <>
+        <StepControlButton
+          variant="text"
+          onClick={paused ? resumeDownload : pauseDownload}
+        >
+          {paused ? "RESUME" : "PAUSE"}
+        </StepControlButton>
+        <StepControlButton variant="text" onClick={cancelFlow}>
+          CANCEL
+        </StepControlButton>
+      </>,
     );
 
-  const renderInstallStep = (isUpdate: boolean) =>
+  const renderInstallStep = (isUpdate: boolean, installingProgress: number) =>
     renderStep(
       isUpdate ? "Installing Update..." : "Installation in Progress...",
+      installingProgress,
     );
 
   const renderLaunchStep = () => renderStep("Launching Decentraland...");
 
-  const renderError = (message: string) => {
+  const renderError = (
+    message: string,
+    canFreeSpace: boolean,
+    canRepair: boolean,
+  ) => {
     resizeWindow(errorWindowSize);
     return (
       <Box
@@ -192,6 +239,16 @@ export const Home: React.FC = memo(() => {
           >
             EXIT
           </ErrorDialogButton>
+          {canFreeSpace && (
+            <ErrorDialogButton variant="contained" onClick={freeSpaceAndRetry}>
+              FREE UP SPACE
+            </ErrorDialogButton>
+          )}
+          {canRepair && (
+            <ErrorDialogButton variant="contained" onClick={repairAndRetry}>
+              REPAIR
+            </ErrorDialogButton>
+          )}
           <ErrorDialogButton variant="contained" onClick={retryFlow}>
             RETRY
           </ErrorDialogButton>
@@ -203,6 +260,7 @@ export const Home: React.FC = memo(() => {
   const renderStep = (
     message: string,
     downloadingProgress: number | undefined = undefined,
+    controls: React.ReactNode = null,
   ) => {
     resizeWindow(stateWindowSize);
     return (
@@ -247,6 +305,7 @@ export const Home: React.FC = memo(() => {
                 visibility={downloadingProgress ? "visible" : "hidden"}
               >{`${Math.round(downloadingProgress ?? 0)}%`}</Typography>
             }
+            {controls}
           </Box>
         </Box>
       </>
@@ -8,16 +8,21 @@ export type LauncherUpdate =
   | { event: "downloading"; data: { progress: number | null } }
   | { event: "downloadFinished"; data: {} }
   | { event: "installingUpdate"; data: {} }
-  | { event: "restartingApp"; data: {} };
+  | { event: "restartingApp"; data: {} }
+  | { event: "required"; data: {} };
 
 export type Step =
   | { event: "launcherUpdate"; data: LauncherUpdate }
   | { event: "fetching"; data: {} }
   | { event: "deeplinkOpening"; data: {} }
+  | { event: "verifying"; data: {} }
   | { event: "downloading"; data: { progress: number; buildType: BuildType } }
-  | { event: "installing"; data: { buildType: BuildType } }
+  | { event: "installing"; data: { progress: number; buildType: BuildType } }
   | { event: "launching"; data: {} };
 
 export type Status =
   | { event: "state"; data: { step: Step } }
-  | { event: "error"; data: { message: string } };
+  | {
+      event: "error";
+      data: { message: string; canFreeSpace: boolean; canRepair: boolean };
+    };